@@ -14,110 +14,300 @@
  * limitations under the License.
  */
 
+#[cfg(feature = "bbcode")]
 pub mod bbcode;
 mod builder;
+// The BBCode parser writes tables GFM can't express as raw HTML, and the
+// CommonMark writer every table, so they need the HTML writer even without the
+// html feature.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+pub mod html;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+#[cfg(feature = "text")]
+pub mod text;
 pub use builder::*;
 
-use markdown::mdast::Node;
-use tl::VDom;
-use tl::errors::ParseError as TlError;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+use std::io;
 
-use crate::{IntoMarkdownAst, IntoBBCodeAst, IntoHtmlDom, Error as InternalError, MarkdownFlavor, IntoMarkdownText, IntoBBCodeText, IntoHtmlText, IntoHtmlDomOwned};
+#[cfg(feature = "markdown")]
+use ::markdown::mdast::Node;
+#[cfg(feature = "html")]
+use tl::VDomGuard;
 
-use self::bbcode::Error as BbError;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+use crate::Result;
+#[cfg(feature = "markdown")]
+use crate::{IntoMarkdownAst, IntoMarkdownText, MarkdownFlavor};
+#[cfg(feature = "bbcode")]
+use crate::{IntoBBCodeAst, IntoBBCodeText};
+#[cfg(feature = "html")]
+use crate::{IntoHtmlDom, IntoHtmlDomOwned, IntoHtmlText};
+#[cfg(feature = "text")]
+use crate::IntoPlainText;
 
-/// A common AST for all supported markup languages. This is a wrapper around the
-/// Markdown crate's AST; parsing is simply converting to Markdown.
-pub struct TmDoc(pub Node);
+use crate::tmast::{Code, Html, InlineCode, InlineMath, IntoOwned, Math, Root, Text, Visit};
 
-pub enum ParseErrorKind {
-	/// Some error ocurred during AST conversion.
-	AstConversion,
-}
+#[cfg(feature = "bbcode")]
+use self::bbcode::Dialect;
 
-pub struct ParseError<P> {
-	pub kind: ParseErrorKind,
-	pub inner: Option<InternalError<P>>
-}
+/// A common AST for all supported markup languages, wrapping a [tmast] tree.
+/// Parsers borrow text from their input where they can, so a document may only
+/// live as long as the text it was parsed from; see [TmDoc::into_owned].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(transparent)
+)]
+pub struct TmDoc<'t>(pub Root<'t>);
 
-impl<P> ParseError<P> {
-	fn ast_conversion(err: InternalError<P>) -> Self {
-		Self { kind: ParseErrorKind::AstConversion, inner: Some(err) }
+impl TmDoc<'static> {
+	#[cfg(feature = "markdown")]
+	pub fn parse_markdown(markdown: impl IntoMarkdownAst, flavor: MarkdownFlavor) -> Result<Self> {
+		let md = markdown.into_markdown_ast(flavor)?;
+		
+		Ok(TmDoc(md.into()))
+	}
+
+	#[cfg(feature = "html")]
+	pub fn parse_html_owned(html: impl IntoHtmlDomOwned) -> Result<Self> {
+		// Safety: the guard is dropped before returning, and nothing borrowed from
+		// it escapes into the document.
+		let dom = unsafe { html.into_html_dom_owned() }?;
+
+		Ok(html::parse(dom.get_ref()).into_owned())
 	}
 }
 
-impl TmDoc {
-	pub fn parse_markdown(markdown: impl IntoMarkdownAst, flavor: MarkdownFlavor) -> Result<TmDoc, ParseError<!>> {
-		let md = markdown.into_markdown_ast(flavor)
-			.map_err(ParseError::ast_conversion)?;
-		
-		Ok(TmDoc(md))
+impl<'t> TmDoc<'t> {
+	#[cfg(feature = "bbcode")]
+	pub fn parse_bbcode(bbcode: impl IntoBBCodeAst<'t>, dialect: Dialect) -> Result<Self> {
+		bbcode.into_bbcode_ast(dialect)
 	}
 
-	pub fn parse_bbcode(bbcode: impl IntoBBCodeAst) -> Result<TmDoc, ParseError<BbError>> {
-		bbcode.into_bbcode_ast().map_err(ParseError::ast_conversion)
+	#[cfg(feature = "html")]
+	pub fn parse_html(html: impl IntoHtmlDom<'t>) -> Result<Self> {
+		let dom = html.into_html_dom()?;
+
+		Ok(html::parse(&dom))
 	}
 
-	pub fn parse_html<'d>(html: impl IntoHtmlDom<'d>) -> Result<TmDoc, ParseError<TlError>> {
-		todo!()
+	/// Copies any text borrowed from the source into the document, so it can
+	/// outlive the source.
+	pub fn into_owned(self) -> TmDoc<'static> {
+		TmDoc(self.0.into_owned())
 	}
 
-	pub fn parse_html_owned(html: impl IntoHtmlDomOwned) -> Result<TmDoc, ParseError<TlError>> {
-		todo!()
+	/// Returns the values of every literal node, such as text and code, joined in
+	/// reading order without any separators.
+	pub fn text_content(&self) -> String {
+		#[derive(Default)]
+		struct Literals(String);
+
+		impl<'a> Visit<'a> for Literals {
+			fn visit_code       (&mut self, node: &'a Code      <'a>) { self.0.push_str(&node.value) }
+			fn visit_html       (&mut self, node: &'a Html      <'a>) { self.0.push_str(&node.value) }
+			fn visit_inline_code(&mut self, node: &'a InlineCode<'a>) { self.0.push_str(&node.value) }
+			fn visit_inline_math(&mut self, node: &'a InlineMath<'a>) { self.0.push_str(&node.value) }
+			fn visit_math       (&mut self, node: &'a Math      <'a>) { self.0.push_str(&node.value) }
+			fn visit_text       (&mut self, node: &'a Text      <'a>) { self.0.push_str(&node.value) }
+		}
+
+		let mut literals = Literals::default();
+		literals.visit_root(&self.0);
+		literals.0
 	}
 
-	fn to_md(self) -> Node { self.0 }
+	// The to_* helpers consume the document, as the conversions calling them do.
+	#[cfg(feature = "markdown")]
+	#[allow(clippy::wrong_self_convention)]
+	fn to_md(self) -> Node { self.0.into() }
 
-	fn to_html<'d>(self) -> VDom<'d> {
-		todo!()
+	#[cfg(feature = "html")]
+	#[allow(clippy::wrong_self_convention)]
+	fn to_html(self) -> Result<VDomGuard> {
+		// Safety: the guard owns the text it was parsed from.
+		unsafe { self.to_html_text().into_html_dom_owned() }
 	}
 
+	#[cfg(feature = "markdown")]
+	#[allow(clippy::wrong_self_convention)]
 	fn to_md_text(self) -> String {
-		self.to_md().into_markdown_text()
+		markdown::write(&self)
 	}
 
-	fn to_bb_text(self) -> String {
-		self.into_bbcode_text()
+	#[cfg(feature = "bbcode")]
+	#[allow(clippy::wrong_self_convention)]
+	fn to_bb_text(self, dialect: Dialect) -> String {
+		bbcode::write(&self, dialect)
 	}
 
+	#[cfg(feature = "html")]
+	#[allow(clippy::wrong_self_convention)]
 	fn to_html_text(self) -> String {
-		self.to_html().into_html_text()
+		html::write(&self)
 	}
 
+	#[cfg(feature = "text")]
+	#[allow(clippy::wrong_self_convention)]
 	fn to_plain_text(self) -> String {
-		todo!()
+		self.to_plain_text_wrapped(Some(text::DEFAULT_WIDTH))
 	}
+
+	/// Renders the document as plain text, wrapped at `width` columns, or not
+	/// wrapped if `None`.
+	#[cfg(feature = "text")]
+	pub fn to_plain_text_wrapped(&self, width: Option<usize>) -> String {
+		text::write(self, width)
+	}
+
+	/// Writes the document as plain text to `out`, wrapped at `width` columns, or
+	/// not wrapped if `None`.
+	#[cfg(feature = "text")]
+	pub fn write_plain_text_wrapped(&self, width: Option<usize>, out: &mut impl io::Write) -> io::Result<()> {
+		text::write_to(self, width, out)
+	}
+}
+
+impl<'t> From<Root<'t>> for TmDoc<'t> {
+	fn from(root: Root<'t>) -> Self { Self(root) }
 }
 
 // 1:1 Markdown conversion
-impl IntoMarkdownAst for TmDoc {
-	fn into_markdown_ast(self, _: MarkdownFlavor) -> Result<Node, InternalError<!>> {
+#[cfg(feature = "markdown")]
+impl IntoMarkdownAst for TmDoc<'_> {
+	fn into_markdown_ast(self, _: MarkdownFlavor) -> Result<Node> {
 		Ok(self.to_md())
 	}
 }
 
-impl IntoMarkdownText for TmDoc {
+#[cfg(feature = "markdown")]
+impl IntoMarkdownText for TmDoc<'_> {
 	fn into_markdown_text(self) -> String {
 		self.to_md_text()
 	}
+
+	fn write_markdown_text(self, out: &mut impl io::Write) -> io::Result<()> {
+		markdown::write_to(&self, out)
+	}
 }
 
-impl IntoBBCodeAst for TmDoc {
-	fn into_bbcode_ast(self) -> Result<TmDoc, InternalError<BbError>> { Ok(self) }
+#[cfg(feature = "bbcode")]
+impl<'t> IntoBBCodeAst<'t> for TmDoc<'t> {
+	fn into_bbcode_ast(self, _: Dialect) -> Result<TmDoc<'t>> { Ok(self) }
 }
 
-impl IntoBBCodeText for TmDoc {
-	fn into_bbcode_text(self) -> String {
-		self.to_bb_text()
+#[cfg(feature = "bbcode")]
+impl IntoBBCodeText for TmDoc<'_> {
+	fn into_bbcode_text(self, dialect: Dialect) -> String {
+		self.to_bb_text(dialect)
+	}
+
+	fn write_bbcode_text(self, dialect: Dialect, out: &mut impl io::Write) -> io::Result<()> {
+		bbcode::write_to(&self, dialect, out)
 	}
 }
 
-impl<'d> IntoHtmlDom<'d> for TmDoc {
-	fn into_html_dom(self) -> Result<VDom<'d>, InternalError<TlError>> { Ok(self.to_html()) }
+#[cfg(feature = "html")]
+impl IntoHtmlDomOwned for TmDoc<'_> {
+	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard> {
+		self.to_html()
+	}
 }
 
-impl IntoHtmlText for TmDoc {
+#[cfg(feature = "html")]
+impl IntoHtmlText for TmDoc<'_> {
 	fn into_html_text(self) -> String {
 		self.to_html_text()
 	}
+
+	fn write_html_text(self, out: &mut impl io::Write) -> io::Result<()> {
+		html::write_to(&self, out)
+	}
+}
+
+#[cfg(feature = "text")]
+impl IntoPlainText for TmDoc<'_> {
+	fn into_plain_text(self) -> String {
+		self.to_plain_text()
+	}
+
+	fn write_plain_text(self, out: &mut impl io::Write) -> io::Result<()> {
+		self.write_plain_text_wrapped(Some(text::DEFAULT_WIDTH), out)
+	}
+}
+
+#[cfg(all(test, feature = "bbcode", feature = "markdown"))]
+mod tests {
+	use crate::MarkdownFlavor;
+
+	use super::TmDoc;
+	use super::bbcode::Dialect;
+
+	#[test]
+	fn text_content() {
+		let doc = TmDoc::parse_markdown(
+			"# A \\*title\\*\n\n> Some `code` &amp; [a link](/url)\n\n- one\n- two\n\n```\nblock\n```",
+			MarkdownFlavor::GFM
+		).expect("parse failed");
+
+		assert_eq!(doc.text_content(), "A *title*Some code & a linkonetwoblock");
+
+		let doc = TmDoc::parse_bbcode("[b]Bold[/b] and [url=/url]a link[/url]", Dialect::Generic).expect("parse failed");
+
+		assert_eq!(doc.text_content(), "Bold and a link");
+	}
+
+	#[test]
+	#[cfg(all(feature = "html", feature = "text"))]
+	fn streaming() {
+		use std::io;
+
+		use crate::{IntoBBCodeText, IntoHtmlText, IntoMarkdownText, IntoPlainText};
+
+		/// Records each write, to check output arrives as it's written.
+		#[derive(Default)]
+		struct Chunks(Vec<Vec<u8>>);
+
+		impl io::Write for Chunks {
+			fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+				self.0.push(buf.to_vec());
+				Ok(buf.len())
+			}
+
+			fn flush(&mut self) -> io::Result<()> { Ok(()) }
+		}
+
+		struct Closed;
+
+		impl io::Write for Closed {
+			fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+				Err(io::Error::other("closed"))
+			}
+
+			fn flush(&mut self) -> io::Result<()> { Ok(()) }
+		}
+
+		let doc = TmDoc::parse_markdown(
+			"# Title\n\nSome *text*[^1].\n\n- One\n- Two\n\n[^1]: A note.\n",
+			MarkdownFlavor::GFM
+		).expect("parse failed");
+
+		fn check(write: impl FnOnce(&mut Chunks) -> io::Result<()>, expected: String) {
+			let mut out = Chunks::default();
+			write(&mut out).expect("write failed");
+
+			assert!(out.0.len() > 1, "written at once");
+			assert_eq!(String::from_utf8(out.0.concat()).unwrap(), expected);
+		}
+
+		check(|out| doc.clone().write_markdown_text(out), doc.clone().into_markdown_text());
+		check(|out| doc.clone().write_bbcode_text(Dialect::Generic, out), doc.clone().into_bbcode_text(Dialect::Generic));
+		check(|out| doc.clone().write_html_text(out), doc.clone().into_html_text());
+		check(|out| doc.clone().write_plain_text(out), doc.clone().into_plain_text());
+
+		assert_eq!(doc.write_html_text(&mut Closed).unwrap_err().to_string(), "closed");
+	}
 }
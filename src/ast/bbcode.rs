@@ -14,521 +14,278 @@
  * limitations under the License.
  */
 
-//! A basic BBCode parser implementation, parsing directly to the common AST.
+//! A basic BBCode parser implementation, parsing directly to the common AST, and
+//! a writer serializing the common AST back to BBCode.
 
-mod tag_builders;
+mod dialect;
 mod parser;
+mod registry;
 mod tokenizer;
+mod writer;
 
-use std::collections::HashMap;
-use std::num::ParseIntError;
-use std::ops::Range;
-use std::vec;
-
-use fancy_regex::{Regex, Error as RegexError, Match};
-use lazy_static::lazy_static;
-use markdown::mdast::Root;
-use regex_macro::regex;
+use std::borrow::Cow;
+use std::io;
 
 use crate::TmDoc;
-
-use super::{NodeBuilder, BlockNode};
-
-lazy_static! {
-	static ref BLOCK_REGEX: Regex =
-		Regex::new(r#"(?six)
-		\[
-			(?<params>
-				(?<tag>[a-z]+)
-				(=\S+?)? # Single parameter, i.e. =800x600
-				(
-					\s+     # Separating whitespace
-					[a-z]+  # Parameter key
-					=
-					"[^"]+" # Parameter value
-				)*
-			)
-		]
-		(?<inner>.*)
-		\[/\k<tag>] # End tag
-		"#).unwrap();
+use crate::util::{collect, stream};
+
+pub use dialect::*;
+pub use parser::{parse, parse_lenient, Error, ErrorKind, NodeTag};
+pub use registry::{TagContent, TagHandler, TagKind, TagRegistry};
+pub(crate) use parser::BUILT_IN_TAGS;
+pub(crate) use tokenizer::tag_regex;
+use writer::Writer;
+
+/// BBCode as a [markup](crate::markup) language, read and written in the generic
+/// dialect. BBCode is parsed straight to the common AST, so its AST is a
+/// document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BBCode(pub TmDoc<'static>);
+
+/// Writes a [TmDoc] as BBCode. Formatting without a BBCode equivalent is dropped,
+/// keeping its text.
+pub fn write(doc: &TmDoc, dialect: Dialect) -> String {
+	collect(|emit| Writer::new(dialect.options()).write(&doc.0, emit))
 }
 
-use ErrorKind::*;
-
-#[derive(Debug)]
-pub enum ErrorKind<'t> {
-	UnclosedTag(&'t str),
-	UnopenedTag(&'t str),
-	UnknownTag(&'t str),
-	UnexpectedTag(&'t str),
-	TagParamParse {
-		tag: &'t str,
-		key: &'t str,
-		val: &'t str,
-		err: ParseIntError
-	},
-	TagParamInvalid {
-		tag: &'t str,
-		key: &'t str,
-		val: &'t str,
-		err: String
-	},
-	TagParamMissing {
-		tag: &'t str,
-		key: &'t str
-	},
-	MatchFailed(RegexError),
+/// Writes a [TmDoc] as BBCode to `out`, as [write] does, without building the
+/// whole document in memory.
+pub fn write_to(doc: &TmDoc, dialect: Dialect, out: &mut (impl io::Write + ?Sized)) -> io::Result<()> {
+	stream(out, |emit| Writer::new(dialect.options()).write(&doc.0, emit))
 }
 
-#[derive(Debug)]
-pub struct Error<'t> {
-	pub range: Range<usize>,
-	pub kind: ErrorKind<'t>,
+/// Escapes text that would otherwise be read as a tag, in an [EscapeStyle].
+pub(crate) fn escape_bbcode<'t>(text: &'t str, style: &EscapeStyle) -> Cow<'t, str> {
+	match style {
+		EscapeStyle::Tag(tag)       => tag_regex().replace_all(text, format!("[{tag}]$0[/{tag}]")),
+		EscapeStyle::ZeroWidthSpace => tag_regex().replace_all(text, |caps: &regex::Captures| caps[0].replacen('[', "[\u{200B}", 1)),
+		EscapeStyle::CharRef        => tag_regex().replace_all(text, |caps: &regex::Captures| caps[0].replacen('[', "&#91;", 1)),
+	}
 }
 
-impl<'t> Error<'t> {
-	fn new(pos: usize, len: usize, kind: ErrorKind<'t>) -> Self {
-		Self::new_range(pos..pos + len, kind)
-	}
+#[cfg(test)]
+mod tests {
+	#[cfg(feature = "markdown")]
+	use markdown::ParseOptions;
 
-	fn new_range(range: Range<usize>, kind: ErrorKind<'t>) -> Self {
-		Self { range, kind }
-	}
+	use crate::{IntoBBCodeText, TmDoc};
+	#[cfg(feature = "markdown")]
+	use crate::MarkdownFlavor;
+	#[cfg(feature = "markdown")]
+	use crate::tmast::{Content, FlowContent};
 
-	fn unclosed_tag(range: Range<usize>, tag: &'t str) -> Self {
-		Self::new_range(range, UnclosedTag(tag))
-	}
-
-	fn unopened_tag(range: Range<usize>, tag: &'t str) -> Self {
-		Self::new_range(range, UnopenedTag(tag))
-	}
+	use super::{Dialect, DialectOptions, EscapeStyle};
 
-	fn unknown_tag(range: Range<usize>, tag: &'t str) -> Self {
-		Self::new_range(range, UnknownTag(tag))
+	#[cfg(feature = "markdown")]
+	fn write(markdown: &str) -> String {
+		TmDoc::parse_markdown(markdown, MarkdownFlavor::Custom(ParseOptions::gfm()))
+			.expect("parse failed")
+			.into_bbcode_text(Dialect::Generic)
 	}
 
-	fn unexpected_tag(range: Range<usize>, tag: &'t str) -> Self {
-		Self::new_range(range, UnexpectedTag(tag))
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_formatting() {
+		assert_eq!(
+			write("# Changelog\n\nSome **bold**, *italic*, ~~struck~~ and `code`.\\\nNext line.\n\n---\n"),
+			"[h1]Changelog[/h1]\n\n\
+			Some [b]bold[/b], [i]italic[/i], [s]struck[/s] and [pre]code[/pre].\nNext line.\n\n\
+			[hr]\n"
+		);
 	}
 
-	fn param_missing(
-		range: Range<usize>,
-		tag: &'t str,
-		key: &'t str
-	) -> Self {
-		Self::new_range(
-			range,
-			TagParamMissing { tag, key }
-		)
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_links_and_images() {
+		assert_eq!(
+			write("[Forum](https://example.com/forum) <https://example.com> ![Logo](https://example.com/logo.png)\n"),
+			"[url=https://example.com/forum]Forum[/url] [url]https://example.com[/url] \
+			[img]https://example.com/logo.png[/img]\n"
+		);
 	}
 
-	fn param_parse(
-		range: Range<usize>,
-		tag: &'t str,
-		key: &'t str,
-		val: &'t str,
-		err: ParseIntError
-	) -> Self {
-		Self::new_range(
-			range,
-			TagParamParse { tag, key, val, err }
-		)
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_blocks() {
+		assert_eq!(
+			write("- One\n- Two\n\n1. First\n\n> Quoted\n>\n> —Jo Doe\n\n```rust\nfn main() {}\n```\n"),
+			"[list]\n[*]One\n[*]Two\n[/list]\n\n\
+			[list=1]\n[*]First\n[/list]\n\n\
+			[quote]\nQuoted\n\n—Jo Doe\n[/quote]\n\n\
+			[code=rust]\nfn main() {}\n[/code]\n"
+		);
 	}
 
-	fn param_invalid(
-		range: Range<usize>,
-		tag: &'t str,
-		key: &'t str,
-		val: &'t str,
-		err: &'t str
-	) -> Self {
-		Self::new_range(
-			range,
-			TagParamInvalid { tag, key, val, err: err.to_string() }
-		)
-	}
-}
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_code_end_tags() {
+		let bbcode = write("```\n[/code][b]x[/b]\n```\n\n`[/PRE]`\n");
 
-pub fn parse(value: &str) -> Result<TmDoc, Error> {
-	// Wat?
-	/*Ok(split(value, 0..value.len())?
-		.into_iter()
-		.fold(
-			Ok(NodeBuilder::<Root>::new()),
-			|nb, block| parse_block(nb?, block)
-		)?.build())*/
-	todo!()
-}
+		assert_eq!(bbcode, "[code]\n[\u{200B}/code][b]x[/b]\n[/code]\n\n[pre][\u{200B}/PRE][/pre]\n");
 
-/*pub(super) enum Block<'t> {
-	Text(&'t str),
-	Tag(Match<'t>, Option<Match<'t>>, (Range<usize>, Vec<Block<'t>>))
-}
+		let doc = TmDoc::parse_bbcode(bbcode.as_str(), Dialect::Generic).expect("parse failed");
+		let [Content::Flow(FlowContent::Code(code)), Content::Flow(_)] = doc.0.children.as_slice() else {
+			panic!("expected a code block and a paragraph, got {:?}", doc.0.children)
+		};
 
-impl<'t> Block<'t> {
-	fn text(self) -> Result<&'t str, Error<'t>> {
-		match self {
-			Block::Text(value) => Ok(value),
-			Block::Tag(tag, _, _) => Err(
-				Error::unexpected_tag(tag.range(), tag.as_str())
-			)
-		}
+		assert_eq!(code.value, "[\u{200B}/code][b]x[/b]");
 	}
-}
 
-fn split(value: &str, range: Range<usize>) -> Result<Vec<Block<'_>>, Error> {
-	let to_err = |error, pos|
-		Error::new(
-			range.start + pos,
-			value.len(),
-			MatchFailed(error)
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_url_brackets() {
+		assert_eq!(
+			write("<https://example.com/[/url][b]> ![](https://example.com/a.png?[/img][b]x)\n"),
+			"[url]https://example.com/%5B/url%5D%5Bb%5D[/url] [img]https://example.com/a.png?%5B/img%5D%5Bb%5Dx[/img]\n"
 		);
+	}
 
-	let mut blocks = Vec::with_capacity(16);
-	let mut pos = 0;
-
-	for block_res in BLOCK_REGEX.captures_iter(value) {
-		let block_caps = block_res.map_err(|e| to_err(e, pos))?;
-		let block_start = block_caps.get(0).unwrap().start();
-
-		if block_start > pos {
-			let text = &value[pos..block_start];
-
-			if let Some(unclosed_tag) = regex!(r"(?i)\[\w+]").find(text) {
-				let start = range.start + unclosed_tag.start();
-				let end   = range.end   + unclosed_tag.end();
-
-				let mut tag = unclosed_tag.as_str();
-						tag = &tag[1..tag.len() - 1];
-
-				Err(Error::unclosed_tag(start..end, tag))?
-			}
-
-			if let Some(unopened_tag) = regex!(r"(?i)\[/\w+]").find(text) {
-				let start = range.start + unopened_tag.start();
-				let end   = range.end   + unopened_tag.end();
-
-				let mut tag = unopened_tag.as_str();
-						tag = &tag[2..tag.len() - 1];
-
-				Err(Error::unopened_tag(start..end, tag))?
-			}
-
-			blocks.push(Block::Text(text));
-		}
-
-		let tag   = block_caps.name("tag"  ).unwrap();
-		let param = block_caps.name("param");
-		let inner = block_caps.name("inner").unwrap();
-
-		let inner_value = inner.as_str();
-		let inner_range = inner.range();
-
-		fn is_verbatim(tag: &str, param: Option<Match>) -> bool {
-			tag.eq_ignore_ascii_case("code"   ) ||
-			tag.eq_ignore_ascii_case("img"    ) ||
-			tag.eq_ignore_ascii_case("pre"    ) ||
-			tag.eq_ignore_ascii_case("youtube") ||
-			tag.eq_ignore_ascii_case("url") && param.is_none()
-		}
-
-		blocks.push(
-			Block::Tag(
-				tag,
-				param,
-				if inner_range.is_empty() {
-					(inner_range, vec![])
-				} else if is_verbatim(tag.as_str(), param) {
-					(inner_range, vec![ Block::Text(inner_value) ])
-				} else {
-					(inner_range.clone(), split(inner_value, inner_range)?)
-				}
-			)
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_table() {
+		assert_eq!(
+			write("| A | B |\n|---|---|\n| 1 | 2 |\n"),
+			"[table]\n[tr][th]A[/th][th]B[/th][/tr]\n[tr][td]1[/td][td]2[/td][/tr]\n[/table]\n"
 		);
-
-		pos += block_caps.len();
 	}
 
-	Ok(blocks)
-}
-
-pub(self) fn parse_block<N : BlockNode>(
-	builder: NodeBuilder<N>,
-	block: Block<'_>
-) -> Result<NodeBuilder<N>, Error> {
-	Ok(match block {
-    	Block::Text(value) => builder.text(value.to_string()),
-		Block::Tag(tag, param, (inner_range, inner_blocks)) => {
-			let parameters = param
-				.map(TagParameters::split)
-				.unwrap_or_else(||
-					TagParameters::empty(tag.end())
-				);
-			
-			Tag::parse(tag.as_str(), tag.range(), parameters)?
-				.build(builder, inner_range, inner_blocks)?
-		}
-	})
-}
-
-struct TagParameters<'t> {
-	full_range: Range<usize>,
-	params: HashMap<&'t str, &'t str>,
-	ranges: HashMap<&'t str, Range<usize>>
-}
+	#[test]
+	fn table_round_trip() {
+		let bbcode = "[table]\n[tr][th align=center]A[/th][th]B[/th][/tr]\n[tr][td]1[/td][td]2[/td][/tr]\n[/table]\n\n\
+					  [table]\n[tr]\n[td]1[/td]\n[/tr]\n[/table]\n";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::Generic).expect("parse failed");
 
-impl<'t> TagParameters<'t> {
-	fn empty(off: usize) -> Self {
-		Self {
-			full_range: off..off,
-			params: HashMap::new(),
-			ranges: HashMap::new()
-		}
+		assert_eq!(doc.into_bbcode_text(Dialect::Generic), bbcode);
 	}
 
-	fn split(parameters: Match) -> Self {
-		let pos = parameters.start();
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn escape_tags() {
+		assert_eq!(
+			write("Use a[i] and [b]not bold[/b], but [not a tag.\n"),
+			"Use a[noparse][i][/noparse] and [noparse][b][/noparse]not bold[noparse][/b][/noparse], but [not a tag.\n"
+		);
+	}
 
-		let pairs = parameters.as_str().split(' ');
+	#[test]
+	fn round_trip() {
+		let bbcode = "[b]Bold[/b] [u]under[/u] [color=red]red[/color] and a[noparse][i][/noparse]\n\n\
+					  [quote=\"Jo Doe\"]\nQuoted\n[/quote]\n\n\
+					  [list]\n[*]One\n[*][url=https://example.com]Two[/url]\n[/list]\n";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::Generic).expect("parse failed");
 
-		let params: HashMap<_, _> = pairs
-			.filter(|s| !s.is_empty())
-			.map(|pair| pair.split_once('=').unwrap())
-			.collect();
-		let ranges: HashMap<_, _> = pairs
-			.map(|p| {
-				let range = pos..pos + p.len();
+		assert_eq!(doc.into_bbcode_text(Dialect::Generic), bbcode);
+	}
 
-				pos += p.len() + 1;
+	#[test]
+	#[cfg(feature = "html")]
+	fn html_round_trip() {
+		use crate::IntoHtmlText;
 
-				(p, range)
-			}).filter(|(_, r)| !r.is_empty())
-				.collect();
+		let bbcode = "[center]\nCentered [u]under[/u], [color=red]red[/color], [size=5]big[/size], x[sup]2[/sup] and x[sub]i[/sub]\n[/center]\n\n\
+					  [spoiler=Plot]\nHidden\n[/spoiler]\n\n\
+					  [youtube]abc[/youtube]\n";
+		let html = TmDoc::parse_bbcode(bbcode, Dialect::Generic).expect("parse failed").into_html_text();
+		let doc = TmDoc::parse_html(html.as_str()).expect("parse failed");
 
-		Self {
-			full_range: parameters.range(),
-			params,
-			ranges
-		}
+		assert_eq!(doc.into_bbcode_text(Dialect::Generic), bbcode);
 	}
 
-	fn get_single(&self) -> Option<&str> {
-		self.get("")
+	#[test]
+	fn php_bb_to_steam() {
+		let bbcode = "[quote=\"Jo Doe\" post_id=12 user_id=3]Quoted[/quote]\n\
+					  [list=1][*]One[*]Two[/list]\n\
+					  [s]gone[/s] [size=150]big[/size]";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::PhpBB).expect("parse failed");
+
+		assert_eq!(
+			doc.into_bbcode_text(Dialect::Steam),
+			"[quote=\"Jo Doe\"]\nQuoted\n[/quote]\n\n\
+			[olist]\n[*]One\n[*]Two\n[/olist]\n\n\
+			[strike]gone[/strike] big\n"
+		);
 	}
 
-	fn get(&self, key: &str) -> Option<&str> {
-		self.params
-			.get(key)
-			.cloned()
+	#[test]
+	#[cfg(feature = "markdown")]
+	fn write_steam() {
+		assert_eq!(
+			TmDoc::parse_markdown("# Title\n\n#### Deep\n\n`code`\n\n---\n", MarkdownFlavor::GFM)
+				.expect("parse failed")
+				.into_bbcode_text(Dialect::Steam),
+			"[h1]Title[/h1]\n\n[h3]Deep[/h3]\n\ncode\n\n[hr][/hr]\n"
+		);
 	}
 
-	fn get_single_strict(
-		&self,
-		err: impl FnOnce() -> Error<'t>
-	) -> Result<&str, Error> {
-		self.get_strict("", err)
+	#[test]
+	fn write_php_bb() {
+		let bbcode = "[h2]Title[/h2]\n\
+					  [table][tr][th]A[/th][th]B[/th][/tr][tr][td]1[/td][td]2[/td][/tr][/table]\n\
+					  [color=red]red[/color] [size=20]big[/size] [center]mid[/center] [noparse][b][/noparse] [spoiler]hidden[/spoiler]";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::Generic).expect("parse failed");
+
+		assert_eq!(
+			doc.into_bbcode_text(Dialect::PhpBB),
+			"[b]Title[/b]\n\n\
+			A | B\n1 | 2\n\n\
+			[color=red]red[/color] [size=125]big[/size]\n\n\
+			mid\n\n\
+			[\u{200B}b]\n\n\
+			hidden\n"
+		);
 	}
 
-	fn get_strict(
-		&self,
-		key: &str,
-		err: impl FnOnce() -> Error<'t>
-	) -> Result<&str, Error> {
-		self.get(key).ok_or_else(err)
+	#[test]
+	fn write_escape_styles() {
+		let doc = TmDoc::parse_bbcode("[noparse]a [b] c[/noparse]", Dialect::Generic).expect("parse failed");
+		let write = |escape| {
+			let options = DialectOptions { escape, ..DialectOptions::default() };
+			doc.clone().into_bbcode_text(Dialect::Custom(Box::new(options)))
+		};
+
+		assert_eq!(write(EscapeStyle::Tag("plain".to_string())), "a [plain][b][/plain] c\n");
+		assert_eq!(write(EscapeStyle::ZeroWidthSpace), "a [\u{200B}b] c\n");
+		assert_eq!(write(EscapeStyle::CharRef), "a &#91;b] c\n");
 	}
 
-	fn get_single_range(&self) -> Range<usize> {
-		self.get_range("")
-	}
+	#[test]
+	fn xen_foro_round_trip() {
+		let bbcode = "[heading=2]Title[/heading]\n\n\
+					  [plain][b][/plain] [icode]code[/icode] [size=5]big[/size]\n";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::XenForo).expect("parse failed");
 
-	fn get_range(&self, key: &str) -> Range<usize> {
-		self.ranges
-			.get(key)
-			.cloned()
-			.unwrap_or(self.full_range)
+		assert_eq!(doc.into_bbcode_text(Dialect::XenForo), bbcode);
 	}
-}
 
-enum Tag<'t> {
-	Bold,
-	Center,
-	Code(Option<&'t str>),
-	Color(&'t str),
-	Image {
-		value : Option<(u32, u32)>,
-		width : Option<u32>,
-		height: Option<u32>,
-	},
-	Italic,
-	Left,
-	List(bool),
-	ListItem,
-	Pre,
-	Quote(Option<&'t str>),
-	Right,
-	Size(Option<u32>),
-	Spoiler(Option<&'t str>),
-	Strikethrough,
-	Style {
-		color: Option<&'t str>,
-		size : Option<u32>,
-	},
-	Table,
-	TableRow,
-	TableCell(bool),
-	Underline,
-	Url(Option<&'t str>),
-	Youtube,
-}
+	#[test]
+	fn read_xen_foro_media() {
+		let doc = TmDoc::parse_bbcode("[media=youtube]abc[/media]", Dialect::XenForo).expect("parse failed");
 
-impl<'t> Tag<'t> {
-	fn parse(
-		tag: &'t str,
-		range: Range<usize>,
-		parameters: TagParameters<'t>
-	) -> Result<Self, Error> {
-		let missing = |key|
-			Error::param_missing(
-				parameters.full_range,
-				tag,
-				key
-			);
-		let parse_val = |key, val: &str|
-			val.parse::<u32>().map_err(|err|
-				Error::param_parse(
-					parameters.get_range(key),
-					tag,
-					key,
-					val,
-					err
-				)
-			);
-		let parse = |key|
-			parameters
-				.get(key)
-				.map(|val| parse_val(key, val))
-				.swap();
-				
-
-		Ok(match tag.to_ascii_lowercase().as_str() {
-			"b"       => Tag::Bold,
-			"center"  => Tag::Center,
-			"code"    => Tag::Code(parameters.get_single()),
-			"color"   => Tag::Color(
-				parameters.get_single_strict(|| missing("color"))?
-			),
-			"img"     => Tag::Image {
-				value: {
-					// This got ridiculous...
-					parameters.get_single()
-						.map(|dim|
-							dim.split_once('x')
-								.ok_or_else(||
-									Error::param_invalid(
-										parameters.get_single_range(),
-										tag,
-										"img",
-										dim,
-										"no dimension delimiter 'x' found"
-									)
-								)
-						).swap()?
-						.map(|(w, h)|
-							Ok((
-								parse_val("img", w)?,
-								parse_val("img", h)?
-							))
-						)
-						.swap()?
-				},
-				width : parse("width" )?,
-				height: parse("height")?
-			},
-			"i"       => Tag::Italic,
-			"left"    => Tag::Left,
-			"list" |
-			"ul"      => Tag::List(false),
-			"ol"      => Tag::List(true),
-			"li"      => Tag::ListItem,
-			"pre"     => Tag::Pre,
-			"quote"   => Tag::Quote(parameters.get_single()),
-			"right"   => Tag::Right,
-			"size"    => Tag::Size(parse("")?),
-			"spoiler" => Tag::Spoiler(parameters.get_single()),
-			"s"       => Tag::Strikethrough,
-			"style"   => Tag::Style {
-				color: parameters.get("color"),
-				size : parse("size")?
-			},
-			"table"   => Tag::Table,
-			"tr"      => Tag::TableRow,
-			"th"      => Tag::TableCell(true),
-			"td"      => Tag::TableCell(false),
-			"u"       => Tag::Underline,
-			"url"     => Tag::Url(parameters.get_single()),
-			"youtube" => Tag::Youtube,
-			_         => return Err(Error::unknown_tag(range, tag))
-		})
+		assert_eq!(doc.into_bbcode_text(Dialect::Generic), "[youtube]abc[/youtube]\n");
 	}
 
-	fn build<N : BlockNode>(
-		self,
-		builder: NodeBuilder<N>,
-		inner_range: Range<usize>,
-		inner_blocks: Vec<Block<'t>>
-	) -> Result<NodeBuilder<N>, Error> {
-		fn build_block<'t, B : BlockNode>(
-			nb: NodeBuilder<B>,
-			inner_blocks: Vec<Block<'t>>
-		) -> Result<NodeBuilder<B>, Error<'t>> {
-			for block in inner_blocks {
-				nb = parse_block(nb, block)?;
-			}
-
-			Ok(nb)
-		}
-
-		match self {
-			Tag::Bold => builder.strong(|nb| build_generic(nb, inner_blocks)),
-			Tag::Center => todo!(),
-			Tag::Code(lang) => builder.code(|nb| build_code(nb, lang, inner_blocks)),
-			Tag::Color(color) => todo!(),
-			Tag::Image { value, width, height } => todo!(),
-			Tag::Italic => builder.emphasis(|nb| build_block(nb, inner_blocks)),
-			Tag::Left => build_block(builder, inner_blocks),
-			Tag::List(ordered) => todo!(),
-			Tag::ListItem => todo!(),
-			Tag::Pre => todo!(),
-			Tag::Quote(name) => todo!(),
-			Tag::Right => todo!(),
-			Tag::Size(size) => todo!(),
-			Tag::Spoiler(name) => todo!(),
-			Tag::Strikethrough => todo!(),
-			Tag::Style { color, size } => todo!(),
-			Tag::Table => todo!(),
-			Tag::TableRow => todo!(),
-			Tag::TableCell(alignment) => todo!(),
-			Tag::Underline => todo!(),
-			Tag::Url(url) => todo!(),
-			Tag::Youtube => todo!(),
-		}
+	#[test]
+	fn read_v_bulletin() {
+		let bbcode = "[hide]secret[/hide]\n\
+					  [quote=Jo;123]Quoted[/quote]\n\
+					  [video=youtube;abc]https://youtube.com/watch?v=abc[/video]";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::VBulletin).expect("parse failed");
+
+		assert_eq!(
+			doc.into_bbcode_text(Dialect::Generic),
+			"[spoiler]\nsecret\n[/spoiler]\n\n\
+			[quote=Jo]\nQuoted\n[/quote]\n\n\
+			[youtube]abc[/youtube]\n"
+		);
 	}
-}
 
-trait Swap<T, E> {
-	fn swap(self) -> Result<Option<T>, E>;
-}
+	#[test]
+	fn smf_round_trip() {
+		let bbcode = "[quote author=Jo]\nQuoted\n[/quote]\n\n\
+					  [list type=decimal]\n[*]One\n[/list]\n\n\
+					  [nobbc][b][/nobbc] [size=12pt]big[/size]\n";
+		let doc = TmDoc::parse_bbcode(bbcode, Dialect::Smf).expect("parse failed");
 
-// Wat?
-impl<T, E> Swap<T, E> for Option<Result<T, E>> {
-	fn swap(self) -> Result<Option<T>, E> {
-		Ok(
-			if let Some(result) = self {
-				Some(result?)
-			} else {
-				None
-			}
-		)
+		assert_eq!(doc.into_bbcode_text(Dialect::Smf), bbcode);
 	}
-}*/
+}
@@ -0,0 +1,352 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use super::TagRegistry;
+
+/// A BBCode dialect. Forum engines disagree on tag names and parameters, so a
+/// document can be read as one dialect and written out as another.
+#[derive(Clone, Debug)]
+pub enum Dialect {
+	/// Every tag the parser understands, written as-is.
+	Generic,
+	PhpBB,
+	VBulletin,
+	XenForo,
+	Smf,
+	Steam,
+	Custom(Box<DialectOptions>),
+}
+
+impl Dialect {
+	/// Returns the built-in dialect with a name, ignoring case: `generic`, `phpbb`,
+	/// `vbulletin`, `xenforo`, `smf` or `steam`.
+	pub fn from_name(name: &str) -> Option<Self> {
+		Some(
+			match name.to_ascii_lowercase().as_str() {
+				"generic"   => Self::Generic,
+				"phpbb"     => Self::PhpBB,
+				"vbulletin" => Self::VBulletin,
+				"xenforo"   => Self::XenForo,
+				"smf"       => Self::Smf,
+				"steam"     => Self::Steam,
+				_ => return None
+			}
+		)
+	}
+
+	pub fn options(self) -> DialectOptions {
+		match self {
+			Self::Generic     => DialectOptions::default(),
+			Self::PhpBB       => DialectOptions::php_bb(),
+			Self::VBulletin   => DialectOptions::v_bulletin(),
+			Self::XenForo     => DialectOptions::xen_foro(),
+			Self::Smf         => DialectOptions::smf(),
+			Self::Steam       => DialectOptions::steam(),
+			Self::Custom(opt) => *opt,
+		}
+	}
+}
+
+/// The tags and parameters of a BBCode dialect.
+#[derive(Clone, Debug)]
+pub struct DialectOptions {
+	/// Tag names read as another tag, such as `hide` for `spoiler`. Keys are
+	/// lowercase.
+	pub aliases: HashMap<String, String>,
+	/// How headings are written and read.
+	pub headings: HeadingStyle,
+	/// The tag written for strikethrough text, or `None` to drop the formatting.
+	pub strikethrough: Option<String>,
+	/// The start tag written for ordered lists, without brackets, such as
+	/// `list=1` or `olist`. The end tag is the first word.
+	pub ordered_list: String,
+	/// Whether code blocks can have a language parameter.
+	pub code_lang: bool,
+	/// The tag written for inline code, or `None` to write it as text.
+	pub inline_code: Option<String>,
+	/// How the author of a quote is written.
+	pub quote: QuoteStyle,
+	/// The tag written for spoilers, or `None` to drop them, keeping the text.
+	pub spoiler: Option<String>,
+	/// How YouTube embeds are read.
+	pub video: VideoStyle,
+	/// Whether tables are supported. Without tables, each row is written as a
+	/// line of text.
+	pub tables: bool,
+	/// Whether `[color]` is supported.
+	pub color: bool,
+	/// The unit of `[size]` values, or `None` if sizes aren't supported.
+	pub size: Option<SizeUnit>,
+	/// Whether the `[left]`, `[center]`, and `[right]` tags are supported.
+	pub align: bool,
+	/// Whether the `[sup]` and `[sub]` tags are supported.
+	pub scripts: bool,
+	/// The text written for a thematic break, such as `[hr]`.
+	pub rule: String,
+	/// How text that would be read as a tag is escaped.
+	pub escape: EscapeStyle,
+	/// Custom tags, read before the built-in tags.
+	pub tags: TagRegistry,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeadingStyle {
+	/// `[h1]` to `[hN]`, with deeper headings written at the maximum depth.
+	Tags(u8),
+	/// A single tag with the depth as its parameter, such as `[heading=1]`, up
+	/// to the maximum depth.
+	Param(String, u8),
+	/// Bold text, for dialects without headings.
+	Bold,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuoteStyle {
+	/// `[quote="author"]`
+	Param,
+	/// `[quote key=author]`
+	Key(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VideoStyle {
+	/// `[youtube]id[/youtube]`
+	Tag,
+	/// `[media=youtube]id[/media]`
+	Media,
+	/// `[video=youtube;id]url[/video]`
+	Video,
+	/// Videos are plain links.
+	Link,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EscapeStyle {
+	/// Wrapped in a tag whose content isn't parsed, such as `[noparse]`.
+	Tag(String),
+	/// A zero-width space after the opening bracket, which stops the text being
+	/// read as a tag without changing how it looks.
+	ZeroWidthSpace,
+	/// The opening bracket as the `&#91;` character reference, for boards that
+	/// decode them.
+	CharRef,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SizeUnit {
+	Pixels,
+	Percent,
+	Points,
+	/// Sizes from 1 to 7, like the HTML `<font>` tag.
+	Scale,
+}
+
+/// Pixel sizes of each step on the 1 to 7 scale.
+const SCALE_PIXELS: [f32; 7] = [10.0, 13.0, 16.0, 18.0, 24.0, 32.0, 48.0];
+
+impl SizeUnit {
+	/// Converts a size parameter to CSS. Sizes with units are kept as-is.
+	pub(super) fn to_css(self, size: &str) -> String {
+		let Ok(value) = size.parse::<f32>() else {
+			return size.to_string()
+		};
+
+		match self {
+			Self::Pixels  => format!("{value}px"),
+			Self::Percent => format!("{value}%"),
+			Self::Points  => format!("{value}pt"),
+			Self::Scale   => {
+				let step = (value as usize).clamp(1, SCALE_PIXELS.len());
+				format!("{}px", SCALE_PIXELS[step - 1])
+			}
+		}
+	}
+
+	/// Converts a CSS font size to a size parameter, or `None` if the size can't
+	/// be read.
+	pub(super) fn param_from_css(self, size: &str) -> Option<String> {
+		let size = size.trim();
+		let split = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+		let value = size[..split].parse::<f32>().ok()?;
+		let pixels = match &size[split..] {
+			"" | "px" => value,
+			"%"       => value / 100.0 * 16.0,
+			"pt"      => value * 4.0 / 3.0,
+			"em"     |
+			"rem"     => value * 16.0,
+			_         => return None
+		};
+
+		Some(
+			match self {
+				Self::Pixels  => format!("{}", pixels.round()),
+				Self::Percent => format!("{}", (pixels / 16.0 * 100.0).round()),
+				Self::Points  => format!("{}pt", (pixels * 3.0 / 4.0).round()),
+				Self::Scale   => {
+					let step = SCALE_PIXELS
+						.iter()
+						.enumerate()
+						.min_by(|(_, a), (_, b)|
+							(*a - pixels).abs().total_cmp(&(*b - pixels).abs())
+						)
+						.map(|(i, _)| i + 1)
+						.unwrap();
+					step.to_string()
+				}
+			}
+		)
+	}
+}
+
+impl Default for DialectOptions {
+	fn default() -> Self {
+		Self {
+			aliases: HashMap::new(),
+			headings: HeadingStyle::Tags(6),
+			strikethrough: Some("s".to_string()),
+			ordered_list: "list=1".to_string(),
+			code_lang: true,
+			inline_code: Some("pre".to_string()),
+			quote: QuoteStyle::Param,
+			spoiler: Some("spoiler".to_string()),
+			video: VideoStyle::Tag,
+			tables: true,
+			color: true,
+			size: Some(SizeUnit::Pixels),
+			align: true,
+			scripts: true,
+			rule: "[hr]".to_string(),
+			escape: EscapeStyle::Tag("noparse".to_string()),
+			tags: TagRegistry::default(),
+		}
+	}
+}
+
+impl DialectOptions {
+	/// phpBB 3, with its default tags.
+	pub fn php_bb() -> Self {
+		Self {
+			headings: HeadingStyle::Bold,
+			strikethrough: None,
+			inline_code: None,
+			spoiler: None,
+			tables: false,
+			size: Some(SizeUnit::Percent),
+			align: false,
+			scripts: false,
+			rule: "---".to_string(),
+			escape: EscapeStyle::ZeroWidthSpace,
+			..Self::default()
+		}
+	}
+
+	/// vBulletin 4 and 5.
+	pub fn v_bulletin() -> Self {
+		Self {
+			aliases: aliases(&[("hide", "spoiler")]),
+			headings: HeadingStyle::Bold,
+			strikethrough: Some("strike".to_string()),
+			code_lang: false,
+			inline_code: None,
+			spoiler: Some("hide".to_string()),
+			video: VideoStyle::Video,
+			size: Some(SizeUnit::Scale),
+			scripts: false,
+			rule: "---".to_string(),
+			..Self::default()
+		}
+	}
+
+	/// XenForo 2.
+	pub fn xen_foro() -> Self {
+		Self {
+			aliases: aliases(&[("plain", "noparse"), ("ispoiler", "spoiler"), ("icode", "pre")]),
+			headings: HeadingStyle::Param("heading".to_string(), 3),
+			inline_code: Some("icode".to_string()),
+			video: VideoStyle::Media,
+			size: Some(SizeUnit::Scale),
+			scripts: false,
+			rule: "---".to_string(),
+			escape: EscapeStyle::Tag("plain".to_string()),
+			..Self::default()
+		}
+	}
+
+	/// Simple Machines Forum 2.
+	pub fn smf() -> Self {
+		Self {
+			aliases: aliases(&[("nobbc", "noparse")]),
+			headings: HeadingStyle::Bold,
+			ordered_list: "list type=decimal".to_string(),
+			code_lang: false,
+			quote: QuoteStyle::Key("author".to_string()),
+			spoiler: None,
+			video: VideoStyle::Link,
+			size: Some(SizeUnit::Points),
+			escape: EscapeStyle::Tag("nobbc".to_string()),
+			..Self::default()
+		}
+	}
+
+	/// Steam community posts and guides.
+	pub fn steam() -> Self {
+		Self {
+			aliases: aliases(&[("olist", "ol")]),
+			headings: HeadingStyle::Tags(3),
+			strikethrough: Some("strike".to_string()),
+			ordered_list: "olist".to_string(),
+			code_lang: false,
+			inline_code: None,
+			video: VideoStyle::Link,
+			color: false,
+			size: None,
+			align: false,
+			scripts: false,
+			rule: "[hr][/hr]".to_string(),
+			..Self::default()
+		}
+	}
+
+	/// Returns the tag a tag name and its single parameter are read as.
+	pub(super) fn canonical_tag(&self, name: &str, param: Option<&str>) -> String {
+		let name = name.to_ascii_lowercase();
+
+		if let Some(alias) = self.aliases.get(&name) {
+			return alias.clone()
+		}
+
+		match (&self.headings, &self.video, param) {
+			(HeadingStyle::Param(key, _), _, Some(depth)) if *key == name => {
+				format!("h{depth}")
+			}
+			(_, VideoStyle::Media, Some(site)) if name == "media" && site.eq_ignore_ascii_case("youtube") => {
+				"youtube".to_string()
+			}
+			(_, VideoStyle::Video, Some(param)) if name == "video" && param.to_ascii_lowercase().starts_with("youtube") => {
+				"youtube".to_string()
+			}
+			_ => name
+		}
+	}
+}
+
+fn aliases(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+	pairs.iter()
+		 .map(|(from, to)| (from.to_string(), to.to_string()))
+		 .collect()
+}
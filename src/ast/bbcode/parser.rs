@@ -14,128 +14,276 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt::write;
-use std::hash::Hasher;
-use std::marker::PhantomData;
-use std::num::ParseIntError;
+use std::error::Error as StdError;
 use std::result::Result as StdResult;
 use std::{fmt, mem};
 use std::ops::Range;
 
-use lazy_static::lazy_static;
-use markdown::mdast::{Root, Html, Code, Link, Image, BlockQuote, List, Strong};
+use crate::tmast::{
+	AlignKind,
+	Break,
+	Content,
+	FlowContent,
+	List,
+	ListItem,
+	Paragraph,
+	PhrasingContent,
+	Root,
+	StaticPhrasingContent,
+	Text,
+	TextContent,
+	ThematicBreak,
+};
+use crate::tmast::unist::{LineIndex, Node as _, Position};
 use regex_macro::regex;
 
-use crate::util::Zip;
-use crate::{TmDoc, IntoMarkdownText};
-use crate::ast::{NodeBuilder, BlockNode, TextNode, BuildFn, AsNode};
-use crate::markdown_text::escape_markdown;
+use crate::TmDoc;
+use crate::util::{escape_html, map_cow};
+use crate::ast::{NodeBuilder, BlockNode};
+use crate::ast::html;
 
+use super::dialect::{Dialect, DialectOptions, QuoteStyle, SizeUnit};
+use super::registry::{TagContent, TagKind};
 use super::tokenizer::{Fragment, FragmentStream, split_fragments, TextFragment, Tag};
 
 type Result<T> = StdResult<T, Error>;
 
+/// Tags whose contents are taken as-is, without parsing any tags inside.
+const VERBATIM_TAGS: &[&str] = &["code", "img", "noparse", "pre", "youtube"];
+/// Tags without an end tag.
+const VOID_TAGS: &[&str] = &["*", "br", "hr"];
+/// Tags built as nodes that only hold phrasing content.
+const INLINE_TAGS: &[&str] = &[
+	"b", "color", "h1", "h2", "h3", "h4", "h5", "h6", "i", "s", "size", "strike",
+	"style", "sub", "sup", "u", "url",
+];
+/// Tags built as flow content, which can't be placed in inline tags.
+const BLOCK_TAGS: &[&str] = &[
+	"center", "code", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "left", "list",
+	"ol", "quote", "right", "spoiler", "table", "ul",
+];
+/// Tags built without a custom handler.
+pub(crate) const BUILT_IN_TAGS: &[&str] = &[
+	"*", "b", "br", "center", "code", "color", "h1", "h2", "h3", "h4", "h5", "h6",
+	"hr", "i", "img", "left", "li", "list", "noparse", "ol", "pre", "quote",
+	"right", "s", "size", "spoiler", "strike", "style", "sub", "sup", "table",
+	"td", "th", "tr", "u", "ul", "url", "youtube",
+];
+
+/// Parses BBCode text written in a [Dialect] into a [TmDoc].
+pub fn parse(input: &str, dialect: Dialect) -> Result<TmDoc<'_>> {
+	let dialect = dialect.options();
+	let mut cx = Context::new(input, &dialect, false);
+	parse_with(&mut cx)
+}
+
+/// Parses BBCode text written in a [Dialect] into a [TmDoc], recovering from
+/// errors instead of failing. Tags left open are closed at the end of their
+/// parent, and stray end tags, tags that can't be read, and block tags inside
+/// inline tags are kept as text. The recovered errors are returned with the
+/// document.
+pub fn parse_lenient(input: &str, dialect: Dialect) -> (TmDoc<'_>, Vec<Error>) {
+	let dialect = dialect.options();
+	let mut cx = Context::new(input, &dialect, true);
+	// Errors are recovered from where they're found, so none should reach here.
+	// If one does, the input is kept as text rather than lost.
+	let doc = parse_with(&mut cx).unwrap_or_else(|error| {
+		let text = cx.text(0..input.len());
+		cx.recovered.get_or_insert_default().push(error);
+		finish(NodeBuilder::default().append(text), &cx)
+	});
+	let mut recovered = cx.recovered.unwrap_or_default();
+	// Errors are found while parsing, then while building.
+	recovered.sort_by_key(|error| error.range.start);
+	(doc, recovered)
+}
+
+fn parse_with<'t>(cx: &mut Context<'t, '_>) -> Result<TmDoc<'t>> {
+	let mut fragments = split_fragments(cx.input);
+	let mut root = Node::root();
+	root.parse(&mut fragments, cx)?;
+
+	let node = root.build(NodeBuilder::default(), cx)?;
+	Ok(finish(node, cx))
+}
+
+/// Builds the document, spanning the whole input.
+fn finish<'t>(node: NodeBuilder<Root<'t>>, cx: &Context<'t, '_>) -> TmDoc<'t> {
+	let mut doc = node.build();
+	doc.0.position = cx.position(0..cx.input.len());
+	normalize(&mut doc.0, cx);
+	doc
+}
+
+/// State shared while parsing.
+struct Context<'t, 'd> {
+	input: &'t str,
+	/// Line starts in the input, for positioning nodes.
+	lines: LineIndex,
+	dialect: &'d DialectOptions,
+	/// Errors recovered from in lenient mode, or `None` if errors fail parsing.
+	recovered: Option<Vec<Error>>,
+	/// Names of the open tags, innermost last.
+	open: Vec<&'t str>,
+	/// Whether tags are being built inside an inline tag, where block tags are
+	/// misplaced.
+	inline: bool,
+}
+
+impl<'t, 'd> Context<'t, 'd> {
+	fn new(input: &'t str, dialect: &'d DialectOptions, lenient: bool) -> Self {
+		Self {
+			input,
+			lines: LineIndex::new(input),
+			dialect,
+			recovered: lenient.then(Vec::new),
+			open: Vec::new(),
+			inline: false,
+		}
+	}
+
+	fn is_lenient(&self) -> bool {
+		self.recovered.is_some()
+	}
+
+	/// Returns the [Position] of a byte range in the input.
+	fn position(&self, range: Range<usize>) -> Option<Position> {
+		Some(self.lines.position(range))
+	}
+
+	/// Returns a text node for a byte range in the input.
+	fn text(&self, range: Range<usize>) -> Text<'t> {
+		Text::new(&self.input[range.clone()], self.position(range))
+	}
+
+	/// Records an error in lenient mode, or returns it.
+	fn recover(&mut self, error: Error) -> Result<()> {
+		match &mut self.recovered {
+			Some(recovered) => {
+				recovered.push(error);
+				Ok(())
+			}
+			None => Err(error)
+		}
+	}
+
+	/// Returns whether an end tag closes a tag outside the innermost open tag.
+	fn closes_outer(&self, end_tag: &str) -> bool {
+		self.open
+			.iter()
+			.rev()
+			.skip(1)
+			.any(|name| name.eq_ignore_ascii_case(end_tag))
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(super) enum ErrorKind {
+pub enum ErrorKind {
 	UnknownTag,
 	UnopenedTag,
 	UnclosedTag,
 	UnexpectedTag(String, Range<usize>),
+	UnexpectedText,
+	MisplacedTag,
 	MissingParam(String),
 	MissingInner,
 }
 
 impl fmt::Display for ErrorKind {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		match *self {
+		match self {
 			Self::UnknownTag  => write!(f, "unknown tag" ),
 			Self::UnopenedTag => write!(f, "unopened tag"),
 			Self::UnclosedTag => write!(f, "unclosed tag"),
-			Self::UnexpectedTag(
-				name,
-				Range {
-					start,
-					end
-				}
-			) => write!(f, "unexpected tag inside {name} tag from {start} to {end}"),
+			Self::UnexpectedTag(name, _) => write!(f, "unexpected tag {name}"),
+			Self::UnexpectedText     => write!(f, "unexpected text"),
+			Self::MisplacedTag       => write!(f, "misplaced tag"),
 			Self::MissingParam(name) => write!(f, "missing required parameter {name}"),
 			Self::MissingInner       => write!(f, "missing required inner text"),
 		}
 	}
 }
 
+/// A BBCode parse error, with the tag name and its byte range in the input.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(super) struct Error {
-	value: String,
-	range: Range<usize>,
-	kind: ErrorKind,
+pub struct Error {
+	pub value: String,
+	pub range: Range<usize>,
+	pub kind: ErrorKind,
+}
+
+impl Error {
+	fn new(value: &str, range: Range<usize>, kind: ErrorKind) -> Self {
+		Self { value: value.to_string(), range, kind }
+	}
 }
 
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let Self {
-			value,
-			range: Range { start, end },
-			kind
-		} = self;
-
-		write!(f, "BBCode parse error (\"{value}\" from {start} to {end}): {kind}.")
+		// Byte ranges are left to diagnostics, which give the line and column.
+		write!(f, "invalid BBCode at \"{}\": {}", self.value, self.kind)
 	}
 }
 
+impl StdError for Error { }
+
 #[derive(Clone, Default)]
 enum Inner<'t> {
 	#[default]
 	None,
-	Text(&'t str),
+	Text(TextFragment<'t>),
 	Tree(Vec<Node<'t>>)
 }
 
 impl<'t> Inner<'t> {
-	fn text(self) -> Option<&'t str> {
-		if let Self::Text(value) = self {
-			Some(value)
-		} else {
-			None
-		}
-	}
-
-	fn tree(self) -> Option<Vec<Node<'t>>> {
-		if let Self::Tree(value) = self {
-			Some(value)
-		} else {
-			None
-		}
-	}
-
 	fn take(&mut self) -> Self {
 		mem::take(self)
 	}
 
-	fn take_text(&mut self) -> Option<&'t str> {
-		self.take().text()
-	}
-
-	fn take_tree(&mut self) -> Option<Vec<Node<'t>>> {
-		self.take().tree()
+	fn push(&mut self, node: Node<'t>) {
+		*self = match self.take() {
+			Inner::None => match node {
+				Node { tag: None, inner: Inner::Text(value), .. } => Inner::Text(value),
+				node => Inner::Tree(vec![node])
+			},
+			Inner::Text(existing) => Inner::Tree(vec![Node::text(existing), node]),
+			Inner::Tree(mut tree) => {
+				tree.push(node);
+				Inner::Tree(tree)
+			}
+		}
 	}
 
-	fn replace_text(&mut self, value: &'t str) -> Self {
-		mem::replace(self, Self::Text(value))
+	/// The range of the content in the input, or `None` if there's no content.
+	fn range(&self) -> Option<Range<usize>> {
+		match self {
+			Inner::None        => None,
+			Inner::Text(value) => Some(value.1.clone()),
+			Inner::Tree(value) => {
+				let start = value.first()?.range().start;
+				let end   = value.last ()?.range().end;
+				Some(start..end)
+			}
+		}
 	}
 
-	fn replace_tree(&mut self, value: Vec<Node<'t>>) -> Self {
-		mem::replace(self, Self::Tree(value))
+	fn into_nodes(self) -> Vec<Node<'t>> {
+		match self {
+			Inner::None        => Vec::new(),
+			Inner::Text(value) => vec![Node::text(value)],
+			Inner::Tree(value) => value,
+		}
 	}
 
-	fn build<N : BlockNode>(self, mut node: NodeBuilder<N>) -> Result<NodeBuilder<N>> {
+	fn build<N : BlockNode<'t>>(self, mut node: NodeBuilder<N>, cx: &mut Context<'t, '_>) -> Result<NodeBuilder<N>> {
 		Ok(match self {
 			Inner::None => node,
-			Inner::Text(value) => node.text(escape_markdown(value)),
+			Inner::Text(value) => node.append(cx.text(value.1)),
 			Inner::Tree(value) => {
 				for inner_node in value {
-					node = inner_node.build(node)?
+					node = inner_node.build(node, cx)?
 				}
 
 				node
@@ -144,454 +292,1023 @@ impl<'t> Inner<'t> {
 	}
 }
 
+/// A parsed start tag, with its parameters.
 #[derive(Clone)]
-struct NodeTag<'t> {
+pub struct NodeTag<'t> {
 	name: &'t str,
+	/// The lowercase tag this is read as in the dialect.
+	canonical: String,
 	name_range: Range<usize>,
-	params: HashMap<&'t str, &'t str>,
+	params: HashMap<String, &'t str>,
 	param_range: Range<usize>,
 }
 
 impl<'t> NodeTag<'t> {
-	fn from_fragment(tag: Tag<'t>) -> Self {
+	fn from_fragment(tag: Tag<'t>, dialect: &DialectOptions) -> Self {
 		let Tag {
 			name : TextFragment(name,  name_range),
 			param: TextFragment(param, param_range)
 		} = tag;
 
-		Self::new(name, name_range, param, param_range)
+		Self::new(name, name_range, param, param_range, dialect)
 	}
 
 	fn new(
 		name: &'t str,
 		name_range: Range<usize>,
 		param: &'t str,
-		param_range: Range<usize>
+		param_range: Range<usize>,
+		dialect: &DialectOptions
 	) -> Self {
+		let mut params = Self::split(param);
+		let key = name.to_ascii_lowercase();
+		let canonical = dialect.canonical_tag(name, params.get(&key).copied());
+
+		// The single parameter is keyed by the tag it's read as.
+		if let Some(value) = params.remove(&key) {
+			params.insert(canonical.clone(), value);
+		}
+
 		Self {
 			name,
+			canonical,
 			name_range,
-			params: Self::split(param),
+			params,
 			param_range
 		}
 	}
 
-	fn split(param: &'t str) -> HashMap<&'t str, &'t str> {
-		param.split(' ')
-			.filter(|p| !p.is_empty())
-			.filter_map(|pair|
-				pair.split_once('=')
-					.and_then(|(k, v)|
-						Some((
-							k,
-							v.strip_prefix('"')?
-							 .strip_suffix('"')?
-						))
-					)
-			)
+	/// Splits parameters into key-value pairs. The single parameter, as in
+	/// `[url=...]`, is keyed by the tag name.
+	fn split(param: &'t str) -> HashMap<String, &'t str> {
+		regex!(r#"(\w+)=(?:"([^"]*)"|([^\s"]+))"#)
+			.captures_iter(param)
+			.map(|caps| {
+				let key = caps.get(1).unwrap().as_str().to_ascii_lowercase();
+				let val = caps.get(2).or(caps.get(3)).unwrap().as_str();
+				(key, val)
+			})
 			.collect()
 	}
 
-	fn name(&self) -> (&'t str, Range<usize>) { (self.name, self.name_range.clone()) }
+	/// The tag name, as written.
+	pub fn name(&self) -> &'t str {
+		self.name
+	}
+
+	/// The single parameter, as in `[url=...]`.
+	pub fn value(&self) -> Option<&'t str> {
+		self.param(&self.canonical)
+	}
+
+	/// A parameter by its lowercase key.
+	pub fn param(&self, key: &str) -> Option<&'t str> {
+		self.params.get(key).copied()
+	}
+
+	/// Returns an error of `kind` at this tag.
+	pub fn error(&self, kind: ErrorKind) -> Error {
+		Error::new(self.name, self.name_range.clone(), kind)
+	}
+
+	pub fn missing_param(&self, key: &str) -> Error {
+		Error::new(self.name, self.param_range.clone(), ErrorKind::MissingParam(key.to_string()))
+	}
+
+	fn is(&self, name: &str) -> bool {
+		self.canonical == name
+	}
+
+	/// The range of the start tag, including brackets.
+	fn range(&self) -> Range<usize> {
+		self.name_range.start - 1..self.param_range.end + 1
+	}
+
+	/// Returns the kind of a custom tag, or `None` for built-in tags.
+	fn custom_kind(&self, dialect: &DialectOptions) -> Option<TagKind> {
+		dialect.tags.get(&self.canonical).map(|tag| tag.kind)
+	}
+
+	fn is_closed_by(&self, end_tag: &str) -> bool {
+		self.name.eq_ignore_ascii_case(end_tag)
+	}
+
+	fn is_any(&self, names: &[&str]) -> bool {
+		names.iter().any(|name| self.is(name))
+	}
+
 }
 
 #[derive(Clone)]
 struct Node<'t> {
 	tag: Option<NodeTag<'t>>,
-	inner: Inner<'t>
+	inner: Inner<'t>,
+	/// The range of the end tag, or `None` if the tag has no end tag.
+	end: Option<Range<usize>>,
 }
 
 impl<'t> Node<'t> {
-	fn tag(tag: Tag) -> Self {
+	fn tag(tag: Tag<'t>, dialect: &DialectOptions) -> Self {
 		Self {
-			tag: Some(NodeTag::from_fragment(tag)),
-			inner: Inner::None
+			tag: Some(NodeTag::from_fragment(tag, dialect)),
+			inner: Inner::None,
+			end: None,
 		}
 	}
 
-	fn text(value: &'t str) -> Self {
-		Self { tag: None, inner: Inner::Text(value) }
+	fn text(value: TextFragment<'t>) -> Self {
+		Self { tag: None, inner: Inner::Text(value), end: None }
 	}
 
 	fn root() -> Self {
-		Self { tag: None, inner: Inner::Tree(Vec::with_capacity(8)) }
+		Self { tag: None, inner: Inner::Tree(Vec::with_capacity(8)), end: None }
 	}
 
-	fn put_text(&mut self, value: &'t str) {
-		match self.inner {
-			Inner::None => {
-				self.inner.replace_text(value);
-			}
-			Inner::Text(existing) => {
-				let tree = Vec::new();
-				tree.push(Self::text(existing));
-				tree.push(Self::text(value));
+	fn is_tag(&self, name: &str) -> bool {
+		self.tag.as_ref().is_some_and(|tag| tag.is(name))
+	}
 
-				self.inner.replace_tree(tree);
-			}
-			Inner::Tree(tree) => {
-				tree.push(Self::text(value));
+	fn is_blank(&self) -> bool {
+		matches!(self, Node { tag: None, inner: Inner::Text(TextFragment(value, _)), .. } if value.trim().is_empty())
+	}
+
+	/// The range of the node in the input, from its start tag to its end tag, or
+	/// to the end of its content if it has no end tag.
+	fn range(&self) -> Range<usize> {
+		let content = self.inner.range();
+
+		let Some(tag) = &self.tag else {
+			return content.unwrap_or_default()
+		};
+
+		let start = tag.range();
+		let end = self.end
+			.as_ref()
+			.map(|end| end.end)
+			.or(content.map(|content| content.end))
+			.unwrap_or(start.end);
+		start.start..end
+	}
+
+	/// Parses fragments into the tree until the end tag of this node is reached.
+	/// In lenient mode, an end tag closing an outer tag closes this tag and is
+	/// returned for its parent.
+	fn parse(&mut self, fragments: &mut FragmentStream<'t>, cx: &mut Context<'t, '_>) -> Result<Option<TextFragment<'t>>> {
+		let Some(tag) = &self.tag else {
+			return self.parse_inner(fragments, cx)
+		};
+
+		match tag.custom_kind(cx.dialect) {
+			Some(TagKind::Nested) => { }
+			Some(TagKind::Verbatim) => return self.parse_verbatim(fragments, cx),
+			Some(TagKind::Void) => return Ok(None),
+			None if tag.is_any(VOID_TAGS) => return Ok(None),
+			None if tag.is_any(VERBATIM_TAGS) || tag.is("url") && tag.param("url").is_none() => {
+				return self.parse_verbatim(fragments, cx)
 			}
+			None => { }
 		}
+
+		cx.open.push(tag.name);
+		let result = self.parse_inner(fragments, cx);
+		cx.open.pop();
+		result
 	}
 
-	fn parse(&mut self, mut fragments: FragmentStream<'t>) -> Result<FragmentStream<'t>> {
-		let end_tag = self.tag.as_ref().map(NodeTag::name);
+	fn parse_inner(&mut self, fragments: &mut FragmentStream<'t>, cx: &mut Context<'t, '_>) -> Result<Option<TextFragment<'t>>> {
+		// Some dialects close void tags, as in [hr][/hr].
+		let mut last_void = None;
+		// An end tag closing this tag, returned by a child left open.
+		let mut pending = None;
 
-		while let Some(fragment) = fragments.next() {
+		while let Some(fragment) = pending.take().map(Fragment::EndTag).or_else(|| fragments.next()) {
 			match fragment {
-				Fragment::Text(TextFragment(value, _)) => {
-					self.put_text(value);
+				Fragment::Text(text) => {
+					last_void = None;
+					self.inner.push(Self::text(text));
 				}
 				Fragment::StartTag(tag) => {
-					fragments = Self::tag(tag).parse(fragments)?;
+					let mut node = Self::tag(tag, cx.dialect);
+					pending = node.parse(fragments, cx)?;
+
+					last_void = node.tag
+						.as_ref()
+						.filter(|tag| match tag.custom_kind(cx.dialect) {
+							Some(kind) => kind == TagKind::Void,
+							None       => tag.is_any(VOID_TAGS),
+						})
+						.map(|tag| tag.name);
+					self.inner.push(node);
 				}
-				Fragment::EndTag(
-					TextFragment(value, range)
-				) => if let Some((tag, range)) = end_tag {
-					if value == tag {
-						break
-					} else {
-						return Err(
-							Error {
-								value: tag.to_string(),
-								range,
-								kind: ErrorKind::UnclosedTag
-							}
-						)
-					}
-				} else {
-					return Err(
-						Error {
-							value: value.to_string(),
-							range,
-							kind: ErrorKind::UnopenedTag
+				Fragment::EndTag(TextFragment(value, _)) if last_void.is_some_and(|name| name.eq_ignore_ascii_case(value)) => {
+					last_void = None;
+				}
+				Fragment::EndTag(TextFragment(value, range)) => {
+					let tag_range = range.start - 2..range.end + 1; // Include [/ and ]
+
+					match &self.tag {
+						Some(tag) if tag.is_closed_by(value) => {
+							self.end = Some(tag_range);
+							return Ok(None)
 						}
-					)
+						Some(tag) if !cx.is_lenient() || cx.closes_outer(value) => {
+							cx.recover(Error::new(tag.name, tag.name_range.clone(), ErrorKind::UnclosedTag))?;
+							return Ok(Some(TextFragment(value, range)))
+						}
+						_ => {
+							cx.recover(Error::new(value, range, ErrorKind::UnopenedTag))?;
+							last_void = None;
+							self.inner.push(Self::text(TextFragment(&cx.input[tag_range.clone()], tag_range)));
+						}
+					}
 				}
 			}
 		}
 
-		Ok(fragments)
+		if let Some(tag) = &self.tag {
+			cx.recover(Error::new(tag.name, tag.name_range.clone(), ErrorKind::UnclosedTag))?;
+		}
+
+		Ok(None)
 	}
 
-	fn build<N : BlockNode>(&self, node: NodeBuilder<N>) -> Result<NodeBuilder<N>> {
-		if let Some(
-			NodeTag {
-				name,
-				name_range,
-				params,
-				param_range
+	/// Takes the input up to the end tag as text, ignoring any tags inside. In
+	/// lenient mode, an unclosed tag takes the rest of the input.
+	fn parse_verbatim(&mut self, fragments: &mut FragmentStream<'t>, cx: &mut Context<'t, '_>) -> Result<Option<TextFragment<'t>>> {
+		let Some(tag) = &self.tag else { return Ok(None) };
+		let input = fragments.input();
+		let start = tag.param_range.end + 1; // Skip ]
+
+		for fragment in fragments.by_ref() {
+			if let Fragment::EndTag(TextFragment(value, range)) = fragment {
+				if tag.is_closed_by(value) {
+					let end = range.start - 2; // Skip [/
+
+					if end > start {
+						self.inner = Inner::Text(TextFragment(&input[start..end], start..end));
+					}
+
+					self.end = Some(end..range.end + 1);
+					return Ok(None)
+				}
 			}
-		) = self.tag {
-			let mut style = |style: String| {
-				params.clear();
-				params.insert("style", &style);
+		}
+
+		cx.recover(Error::new(tag.name, tag.name_range.clone(), ErrorKind::UnclosedTag))?;
+
+		if input.len() > start {
+			self.inner = Inner::Text(TextFragment(&input[start..], start..input.len()));
+		}
+
+		Ok(None)
+	}
+
+	fn build<N : BlockNode<'t>>(self, node: NodeBuilder<N>, cx: &mut Context<'t, '_>) -> Result<NodeBuilder<N>> {
+		let Some(tag) = &self.tag else {
+			return self.inner.build(node, cx)
+		};
+
+		// Tags are checked before their content is built, so tags that can't be
+		// built are kept as text with their content built only once.
+		if let Err(error) = tag.check(&self.inner, cx) {
+			cx.recover(error)?;
+			return build_literal(node, tag, self.end.clone(), cx, |node, cx| self.inner.build(node, cx))
+		}
+
+		let inline = cx.inline;
+		cx.inline |= tag.custom_kind(cx.dialect).is_none() && tag.is_any(INLINE_TAGS);
+		let result = self.build_tag(node, cx);
+		cx.inline = inline;
+		result
+	}
+
+	fn build_tag<N : BlockNode<'t>>(self, node: NodeBuilder<N>, cx: &mut Context<'t, '_>) -> Result<NodeBuilder<N>> {
+		let position = cx.position(self.range());
+		let Some(tag) = self.tag else {
+			return self.inner.build(node, cx)
+		};
+
+		let inner = self.inner;
+		let dialect = cx.dialect;
+
+		if let Some(custom) = dialect.tags.get(&tag.canonical) {
+			let (content, inner) = match custom.kind {
+				TagKind::Nested   => (TagContent::Nodes(inner.build(NodeBuilder::<Root>::default(), cx)?.node().children), Inner::None),
+				TagKind::Verbatim => (TagContent::Text(tag.inner_text(&inner)?.0), inner),
+				TagKind::Void     => (TagContent::None, inner),
 			};
 
-			let align = |alignment| style(format!("text-align: {alignment};"));
-			let color = |color    | style(format!("color: {color};"         ));
-			let size  = |size     | style(format!("font-size: {size};"      ));
-	
-			let color_size = |color, size| style(
-				format!("color: {color}; font-size: {size};")
-			);
+			// Handlers can only fail once the content is built, so in lenient mode
+			// the built nodes are kept to place inside the tag's text instead.
+			let built = match &content {
+				TagContent::Nodes(nodes) if cx.is_lenient() => nodes.clone(),
+				_ => Vec::new(),
+			};
 
-			fn build_html<N : BlockNode>(
-				node: NodeBuilder<N>,
-				tag: &str,
-				inner: Inner,
-				params: HashMap<&str, &str>,
-				build_header: impl BuildFn<Html, Error>
-			) -> Result<NodeBuilder<N>> {
-				node.html(|node|
-					build_header(node)?
-						.build_value(tag, params, inner)
-				)
-			}
-	
-			let build_span_html = || build_html(node, "span", self.inner, params, Ok);
-
-			match name {
-				"b"       => node.strong(|nb| self.build(nb)),
-				"center" |
-				"left"   |
-				"right"   => {
-					align(name);
-					build_span_html()
+			return match (custom.handler)(&tag, content, NodeBuilder::default()) {
+				// Nodes from handlers are placed at the tag, unless positioned already.
+				Ok(root) => Ok(
+					root.node()
+						.children
+						.into_iter()
+						.map(|mut child| {
+							if child.position().is_none() {
+								child.set_position(position.clone());
+							}
+
+							child
+						})
+						.fold(node, NodeBuilder::append)
+				),
+				Err(error) => {
+					cx.recover(error)?;
+					build_literal(node, &tag, self.end, cx, |node, cx|
+						inner.build(built.into_iter().fold(node, NodeBuilder::append), cx)
+					)
 				}
-				"code"    => self.build_code(node, self.inner, params),
-				"color"   => if let Some(_) = params.remove("color").map(color) {
-					build_span_html()
-				} else {
-					self.inner.build(node)
-				},
-				"i"       => node.emphasis(self.inner),
-				"img"     => self.build_img(node, self.inner, params),
-				"ol"      => self.build_list(node, self.inner, true),
-				"pre"     => node.inline_code(|nb| self.build_text(nb, self.inner)),
-				"quote"   => {
-					node = node.block_quote(self.inner)?;
+			}
+		}
+
+		let name = tag.canonical.clone();
+		let size_unit = dialect.size.unwrap_or(SizeUnit::Pixels);
+
+		match name.as_str() {
+			"b"       => node.strong(|nb| inner.build(nb.set_position(position), cx)),
+			"br"      => Ok(node.append(Break::new(position))),
+			"center" |
+			"left"   |
+			"right"   => {
+				let align = match name.as_str() {
+					"left"  => AlignKind::Left,
+					"right" => AlignKind::Right,
+					_       => AlignKind::Center,
+				};
+				node.align(|nb| inner.build(nb.set_align(align).set_position(position), cx))
+			}
+			"code"    => {
+				let code = tag.inner_text(&inner)?.0;
+				let lang = tag.param("code");
 
+				node.code(|nb|
 					Ok(
-						if let Some(attribution) = params.remove("quote").map(escape_markdown) {
-							node.text(format!("—{attribution}"))
-						} else {
-							node
-						}
+						nb.set_value(trim_newlines(code))
+						  .set_lang(lang)
+						  .set_position(position)
 					)
+				)
+			}
+			"color"   => {
+				let color = tag.param("color").ok_or_else(|| tag.missing_param("color"))?;
+				node.color(|nb| inner.build(nb.set_color(color).set_position(position), cx))
+			}
+			"h1" | "h2" | "h3" |
+			"h4" | "h5" | "h6" => {
+				let depth = name[1..].parse().unwrap();
+				node.heading(|nb| inner.build(nb.set_depth(depth).set_position(position), cx))
+			}
+			"hr"      => Ok(node.append(ThematicBreak::new(position))),
+			"i"       => node.emphasis(|nb| inner.build(nb.set_position(position), cx)),
+			"img"     => tag.build_img(node, &inner, position),
+			"li"      => inner.build(node, cx),
+			"list"   |
+			"ol"     |
+			"ul"      => {
+				let ordered = name == "ol" ||
+					tag.param("list").is_some() ||
+					tag.param("type").is_some_and(|kind|
+						!matches!(kind.to_ascii_lowercase().as_str(), "disc" | "circle" | "square" | "none")
+					);
+				build_list(node, inner, ordered, position, cx)
+			}
+			"noparse" => inner.build(node, cx),
+			"pre"     => {
+				let code = tag.inner_text(&inner)?.0;
+				node.inline_code(|nb| Ok(nb.set_value(code).set_position(position)))
+			}
+			"quote"   => node.block_quote(|mut nb| {
+				nb = inner.build(nb.set_position(position), cx)?;
+
+				let author = match &dialect.quote {
+					QuoteStyle::Key(key) => tag.param(key),
+					QuoteStyle::Param    => None,
+				};
+
+				// Post and member ids may follow the name, as in "name;123" or
+				// "name, post: 123".
+				let attribution = tag.param("quote")
+					.or(author)
+					.or(tag.param("author"))
+					.and_then(|name| name.split([';', ',']).next())
+					.map(str::trim)
+					.filter(|name| !name.is_empty());
+
+				Ok(nb.set_author(attribution))
+			}),
+			"s"      |
+			"strike"  => node.delete(|nb| inner.build(nb.set_position(position), cx)),
+			"size"    => {
+				let size = tag.param("size").ok_or_else(|| tag.missing_param("size"))?;
+				node.font_size(|nb| inner.build(nb.set_size(size_unit.to_css(size)).set_position(position), cx))
+			}
+			"spoiler" => node.spoiler(|nb|
+				inner.build(nb.set_summary(tag.param("spoiler")).set_position(position), cx)
+			),
+			"style"   => {
+				let color = tag.param("color");
+				let size  = tag.param("size").map(|size| size_unit.to_css(size));
+
+				match (color, size) {
+					(Some(color), Some(size)) => node.color(|nb|
+						nb.set_color(color)
+						  .set_position(position.clone())
+						  .font_size(|nb| inner.build(nb.set_size(size).set_position(position), cx))
+					),
+					(Some(color), None) => node.color(|nb| inner.build(nb.set_color(color).set_position(position), cx)),
+					(None, Some(size))  => node.font_size(|nb| inner.build(nb.set_size(size).set_position(position), cx)),
+					(None, None)        => inner.build(node, cx),
 				}
-				"s"       => node.delete(|nb| self.build(nb)),
-				"size"    => if let Some(_) = params.remove("size").map(size) {
-					build_span_html()
+			}
+			"sub"     => node.subscript(|nb| inner.build(nb.set_position(position), cx)),
+			"sup"     => node.superscript(|nb| inner.build(nb.set_position(position), cx)),
+			"table"   => build_table(node, inner, position, cx),
+			"td"     |
+			"th"     |
+			"tr"      => Err(Error::new(tag.name, tag.name_range, ErrorKind::MisplacedTag)),
+			"u"       => node.underline(|nb| inner.build(nb.set_position(position), cx)),
+			"url"     => {
+				if let Some(url) = tag.param("url") {
+					node.link(|nb| inner.build(nb.set_url(url).set_position(position), cx))
 				} else {
-					self.inner.build(node)
-				},
-				"spoiler" => {
-					build_html(
-						node,
-						"details",
-						self.inner,
-						HashMap::new(),
-						|node: NodeBuilder<Html>|
-							Ok(
-								if let Some(summary) = params
-									.remove("spoiler")
-									.map(escape_markdown) {
-									node.append_value(
-										format!("<summary>{summary}</summary>")
-									)
-								} else {
-									node
-								}
-							)
+					let TextFragment(url, range) = tag.url_text(&inner)?;
+
+					node.link(|nb|
+						Ok(
+							nb.set_url(url)
+							  .set_position(position)
+							  .append(cx.text(range))
+						)
 					)
 				}
-				"style"   => {
-					let color_val = params.remove("color");
-					let  size_val = params.remove("size" );
-
-					if let Some(color_val) = color_val {
-						if let Some(size_val) = size_val {
-							color_size(color_val, size_val);
-						} else {
-							color(color_val);
-						}
-					} else {
-						if let Some(size_val) = size_val {
-							size(size_val);
-						} else { }
-					}
+			}
+			"youtube" => {
+				let id = tag.url_text(&inner)?.0;
 
-					build_span_html()
-				}
-				"table"   => todo!(),
-				"td"      => todo!(),
-				"th"      => todo!(),
-				"tr"      => todo!(),
-				"u"       => build_html(node, "u", self.inner, HashMap::new(), Ok),
-				"ul" |
-				"list"    => self.build_list(node, self.inner, false),
-				"url"     => self.build_url(node, self.inner, params, |s| s),
-				"youtube" => self.build_url(node, self.inner, HashMap::new(), |id|
-					format!("https://youtube.com/watch?v={id}")
-				),
-				_         => Err(
-					Error {
-						value: name.to_string(),
-						range: name_range,
-						kind: ErrorKind::UnknownTag
-					}
-				)
+				let url: Cow<str> = if id.starts_with("http://") || id.starts_with("https://") {
+					id.into()
+				} else {
+					format!("https://youtube.com/watch?v={id}").into()
+				};
+				node.embed(|nb| Ok(nb.set_url(url).set_position(position)))
 			}
-		} else {
-			self.inner.build(node)
+			// List items are split by build_list, and are ignored anywhere else.
+			"*"       => Ok(node),
+			_         => Err(Error::new(tag.name, tag.name_range, ErrorKind::UnknownTag))
 		}
 	}
+}
 
-	fn get_inner_text(&self, inner: Inner) -> Result<String> {
-		Ok (match inner {
-			Inner::None        => "".to_string(),
-			Inner::Text(value) => value.to_string(),
-			Inner::Tree(value) => {
-				let NodeTag {
-					name: tag,
-					name_range: range,
-					params: _,
-					param_range: _
-				} = self.tag.unwrap();
-
-				let NodeTag {
-					name: unexp_tag,
-					name_range: unexp_range,
-					params: _,
-					param_range: _
-				} = value.iter().find_map(|v| v.tag).unwrap();
-
-				return Err(
-					Error {
-						value: tag.to_string(),
-						range,
-						kind: ErrorKind::UnexpectedTag(
-							unexp_tag.to_string(),
-							unexp_range
-						)
-					}
-				)
+impl<'t> NodeTag<'t> {
+	/// Checks that the tag can be built from its content and in its place, before
+	/// the content is built.
+	fn check(&self, inner: &Inner<'t>, cx: &Context<'t, '_>) -> Result<()> {
+		if let Some(kind) = self.custom_kind(cx.dialect) {
+			if kind == TagKind::Verbatim {
+				self.inner_text(inner)?;
 			}
-		})
-	}
 
-	fn build_text<N : TextNode>(
-		&self,
-		node: NodeBuilder<N>,
-		inner: Inner
-	) -> Result<NodeBuilder<N>> {
-		let text = self.get_inner_text(inner)?;
+			return Ok(())
+		}
 
-		Ok(node.set_value(text))
-	}
+		// Inline nodes would flatten block content into their text.
+		if cx.inline && self.is_any(BLOCK_TAGS) {
+			return Err(self.error(ErrorKind::MisplacedTag))
+		}
 
-	fn build_into_html(
-		&self,
-		node: NodeBuilder<Html>,
-		inner: Inner,
-		prefix: &str,
-		suffix: &str
-	) -> Result<NodeBuilder<Html>> {
-		let md = match inner {
-			Inner::None        => "".to_string(),
-			Inner::Text(value) => escape_markdown(value),
-			Inner::Tree(value) => {
-				let mut fake_root = NodeBuilder::new();
+		match self.canonical.as_str() {
+			"code" | "pre"         => { self.inner_text(inner)?; }
+			"color" | "size"       => { self.param(&self.canonical).ok_or_else(|| self.missing_param(&self.canonical))?; }
+			"img" | "youtube"      => { self.url_text(inner)?; }
+			"url" if self.param("url").is_none() => { self.url_text(inner)?; }
+			"table"                => check_table(self, inner)?,
+			"td" | "th" | "tr"     => return Err(self.error(ErrorKind::MisplacedTag)),
+			name if !BUILT_IN_TAGS.contains(&name) => return Err(self.error(ErrorKind::UnknownTag)),
+			_                      => { }
+		}
 
-				for node in value {
-					fake_root = node.build(fake_root)?;
-				}
+		Ok(())
+	}
 
-				fake_root.build().into_markdown_text()
+	/// Returns the trimmed text inside the tag, and its range in the input.
+	fn inner_text(&self, inner: &Inner<'t>) -> Result<TextFragment<'t>> {
+		Ok(match inner {
+			Inner::None        => {
+				let end = self.range().end;
+				TextFragment("", end..end)
 			}
-		};
+			Inner::Text(TextFragment(value, range)) => {
+				let start = range.start + value.len() - value.trim_start().len();
+				let value = value.trim();
+				TextFragment(value, start..start + value.len())
+			}
+			Inner::Tree(value) => {
+				let error = match value.iter().find_map(|node| node.tag.as_ref()) {
+					Some(tag) => unexpected_tag(self, tag),
+					None      => self.error(ErrorKind::UnexpectedText),
+				};
 
-		Ok(node.set_value(format!("{prefix}\n{md}\n{suffix}")))
+				return Err(error)
+			}
+		})
 	}
 
-	fn build_code<N : BlockNode>(
-		&self,
-		node: NodeBuilder<N>,
-		inner: Inner,
-		mut params: HashMap<&str, &str>
-	) -> Result<NodeBuilder<N>> {
-		let code = self.get_inner_text(inner)?;
-		let lang = params.remove("code").map(String::from);
+	/// Returns the URL inside the tag, which can't be empty.
+	fn url_text(&self, inner: &Inner<'t>) -> Result<TextFragment<'t>> {
+		let text = self.inner_text(inner)?;
+
+		if text.0.is_empty() {
+			return Err(self.error(ErrorKind::MissingInner))
+		}
 
-		node.code(|node| Ok(node.set_value(code).set_lang(lang)))
+		Ok(text)
 	}
+}
 
-	fn build_url<N : BlockNode>(
+impl<'t> NodeTag<'t> {
+	fn build_img<N : BlockNode<'t>>(
 		&self,
 		node: NodeBuilder<N>,
-		inner: Inner,
-		mut params: HashMap<&str, &str>,
-		map_url: impl FnOnce(String) -> String
+		inner: &Inner<'t>,
+		position: Option<Position>
 	) -> Result<NodeBuilder<N>> {
-		let inner = self.get_inner_text(inner)?;
-		let mut title = Some(inner);
-		let mut url = if let Some(url) = params.remove("url").map(String::from) {
-			url
-		} else {
-			title.take().unwrap()
-		};
-		url = map_url(url);
+		let url = self.url_text(inner)?.0;
 
-		node.link(|node| Ok(node.set_url(url).set_title(title)))
-	}
+		let alt   = self.param("alt"  ).unwrap_or_default();
+		let title = self.param("title");
+		let dim   = self.param("img")
+			.and_then(|p| p.split_once(['x', 'X']))
+			.or_else(|| self.param("width").zip(self.param("height")));
 
-	fn build_img<N : BlockNode>(
-		&self,
-		node: NodeBuilder<N>,
-		inner: Inner,
-		mut params: HashMap<&str, &str>
-	) -> Result<NodeBuilder<N>> {
-		let url    = self.get_inner_text(inner)?;
-		let alt    = params.get("alt"   ).map(ToString::to_string);
-		let title  = params.get("title" ).map(ToString::to_string);
-		let width  = params.get("width" ).cloned();
-		let height = params.get("height").cloned();
-		let dim    = params.remove("img")
-			.and_then(|p| p.split_once('x'))
-			.or_else(||
-				width.zip(height)
-					.or_else(||
-						height.zip_with(
-							width,
-							|h, w| (w, h)
-						)
-					)
+		if let Some((width, height)) = dim {
+			// Markdown images can't be sized, so these are kept as HTML, sanitized as
+			// the HTML writer would.
+			let mut value = format!(
+				"<img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\"",
+				escape_html(html::sanitize_url(url, html::SAFE_PROTOCOLS)),
+				escape_html(alt),
+				escape_html(width),
+				escape_html(height)
 			);
 
-		if let Some((w, h)) = dim {
-			params.insert("width",  w);
-			params.insert("height", h);
-			params.insert("src", &url);
+			if let Some(title) = title {
+				value.push_str(&format!(" title=\"{}\"", escape_html(title)));
+			}
 
-			node.link(|nb|
-				Ok(
-					nb.set_title_html(
-						NodeBuilder::default()
-							.build_value("img", params, Ok)?
-					).set_url(url)
-				)
-			)
+			value.push_str(" />");
+			node.html(|nb| Ok(nb.set_value(value).set_position(position)))
 		} else {
 			node.image(|nb|
 				Ok(
 					nb.set_url(url)
-				  	  .set_alt(alt.unwrap_or(String::from("")))
-				  	  .set_title(title)
+					  .set_alt(alt)
+					  .set_title(title)
+					  .set_position(position)
 				)
 			)
 		}
 	}
+}
 
-	fn build_list<N : BlockNode>(
-		&self,
-		node: NodeBuilder<N>,
-		inner: Inner,
-		ordered: bool
-	) -> Result<NodeBuilder<N>> {
-		node.list(|mut node: NodeBuilder<List>| {
-			node =
-				node.set_ordered(ordered)
-					.set_start(ordered.then_some(1))
-					.set_spread(false);
-
-			let li_shorthand = regex!(r"^\s*\[*].*$");
-			let items = match inner {
-				Inner::None        => Vec::new(),
-				Inner::Text(value) => {
-					li_shorthand.find_iter(value)
-						.map(|s| (&s.as_str().trim()[3..]).trim_start())
-						.map(Node::text)
-						.collect()
+/// Builds a list, splitting items at `[*]` or `[li]` tags.
+fn build_list<'t, N : BlockNode<'t>>(
+	node: NodeBuilder<N>,
+	inner: Inner<'t>,
+	ordered: bool,
+	position: Option<Position>,
+	cx: &mut Context<'t, '_>
+) -> Result<NodeBuilder<N>> {
+	// Items span from their marker, or their first node, to their last node.
+	let mut items: Vec<(Range<usize>, Vec<Node>)> = Vec::new();
+
+	for child in inner.into_nodes() {
+		let range = child.range();
+
+		if child.is_tag("*") {
+			items.push((range, Vec::new()));
+		} else if child.is_tag("li") {
+			items.push((range, vec![child]));
+		} else if let Some((item_range, item)) = items.last_mut() {
+			item_range.end = range.end;
+			item.push(child);
+		} else if !child.is_blank() {
+			items.push((range, vec![child]));
+		}
+	}
+
+	node.list(|mut node: NodeBuilder<List<'t>>| {
+		node =
+			node.set_ordered(ordered)
+				.set_start(ordered.then_some(1))
+				.set_spread(false)
+				.set_position(position);
+
+		for (range, item) in items {
+			node = node.item(|mut node: NodeBuilder<ListItem<'t>>| {
+				node = node.set_position(cx.position(range));
+
+				for child in item {
+					node = child.build(node, cx)?;
 				}
-				Inner::Tree(value) => value
-			};
 
-			for item in items {
-				match item.inner {
-					Inner::None => { }
-					Inner::Text(value) => todo!(),
-					Inner::Tree(value) => todo!(),
+				Ok(node)
+			})?;
+		}
+
+		Ok(node)
+	})
+}
+
+struct TableCell<'t> {
+	tag: NodeTag<'t>,
+	children: Vec<Content<'t>>,
+	position: Option<Position>,
+}
+
+/// Builds a table, with `[th]` cells in the first row as the header. Tables GFM
+/// can't express, such as those without a header or with uneven rows, are kept
+/// as HTML.
+fn build_table<'t, N : BlockNode<'t>>(
+	node: NodeBuilder<N>,
+	inner: Inner<'t>,
+	position: Option<Position>,
+	cx: &mut Context<'t, '_>
+) -> Result<NodeBuilder<N>> {
+	let mut rows = Vec::new();
+
+	// The table was checked before building, so text between rows and cells is
+	// only whitespace.
+	for row in inner.into_nodes() {
+		let row_position = cx.position(row.range());
+		let Node { tag: Some(_), inner, .. } = row else { continue };
+		let mut cells = Vec::new();
+
+		for cell in inner.into_nodes() {
+			let cell_position = cx.position(cell.range());
+			let Node { tag: Some(cell_tag), inner, .. } = cell else { continue };
+
+			let mut root = inner.build(NodeBuilder::default(), cx)?.build().0;
+			normalize(&mut root, cx);
+			cells.push(TableCell { tag: cell_tag, children: root.children, position: cell_position });
+		}
+
+		rows.push((row_position, cells));
+	}
+
+	let columns = rows.first().map(|(_, row)| row.len()).unwrap_or(0);
+	let is_gfm = !rows.is_empty() &&
+		rows.iter().all(|(_, row)| row.len() == columns) &&
+		rows.iter().enumerate().all(|(i, (_, row))|
+			row.iter().all(|cell| cell.tag.is("th") == (i == 0))
+		) &&
+		rows.iter().flat_map(|(_, row)| row).all(|cell|
+			matches!(cell.children.as_slice(), [] | [Content::Flow(FlowContent::Content(TextContent::Paragraph(_)))])
+		);
+
+	if !is_gfm {
+		let rows = rows.into_iter().map(|(_, row)| row).collect();
+		return node.html_block(|nb| Ok(nb.set_value(table_html(rows)).set_position(position)))
+	}
+
+	let align = rows[0].1.iter().map(|cell|
+		match cell.tag.param("align").map(str::to_ascii_lowercase).as_deref() {
+			Some("left"  ) => AlignKind::Left,
+			Some("center") => AlignKind::Center,
+			Some("right" ) => AlignKind::Right,
+			_              => AlignKind::None,
+		}
+	).collect::<Vec<_>>();
+
+	node.table(|mut node| {
+		node = node.set_position(position);
+
+		for align in align {
+			node = node.align_column(align);
+		}
+
+		for (position, row) in rows {
+			node = node.row(|mut node| {
+				node = node.set_position(position);
+
+				for cell in row {
+					node = node.cell(|mut node| {
+						node = node.set_position(cell.position);
+
+						if let Some(Content::Flow(FlowContent::Content(TextContent::Paragraph(para)))) = cell.children.into_iter().next() {
+							for child in para.children {
+								node = node.append(child);
+							}
+						}
+
+						Ok(node)
+					})?;
 				}
+
+				Ok(node)
+			})?;
+		}
+
+		Ok(node)
+	})
+}
+
+/// Checks that a table contains only rows, and its rows only cells.
+fn check_table(tag: &NodeTag, inner: &Inner) -> Result<()> {
+	for (row_tag, row) in table_children(tag, inner)? {
+		if !row_tag.is("tr") {
+			return Err(unexpected_tag(tag, row_tag))
+		}
+
+		for (cell_tag, _) in table_children(row_tag, row)? {
+			if !cell_tag.is_any(&["td", "th"]) {
+				return Err(unexpected_tag(row_tag, cell_tag))
 			}
-			Ok(node)
-		})
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the tags inside a table or row with their content, ignoring
+/// whitespace between them.
+fn table_children<'a, 't>(tag: &NodeTag, inner: &'a Inner<'t>) -> Result<Vec<(&'a NodeTag<'t>, &'a Inner<'t>)>> {
+	let children = match inner {
+		Inner::None        => &[][..],
+		Inner::Text(value) if value.0.trim().is_empty() => &[][..],
+		Inner::Text(_)     => return Err(tag.error(ErrorKind::UnexpectedText)),
+		Inner::Tree(value) => value,
+	};
+
+	children
+		.iter()
+		.filter(|child| !child.is_blank())
+		.map(|child|
+			child.tag
+				 .as_ref()
+				 .map(|child_tag| (child_tag, &child.inner))
+				 .ok_or_else(|| tag.error(ErrorKind::UnexpectedText))
+		)
+		.collect()
+}
+
+/// Builds a tag as its text, with its content built by `content`.
+fn build_literal<'t, N : BlockNode<'t>>(
+	node: NodeBuilder<N>,
+	tag: &NodeTag,
+	end: Option<Range<usize>>,
+	cx: &mut Context<'t, '_>,
+	content: impl FnOnce(NodeBuilder<N>, &mut Context<'t, '_>) -> Result<NodeBuilder<N>>
+) -> Result<NodeBuilder<N>> {
+	let node = node.append(cx.text(tag.range()));
+	let node = content(node, cx)?;
+
+	Ok(
+		match end {
+			Some(end) => node.append(cx.text(end)),
+			None      => node
+		}
+	)
+}
+
+fn unexpected_tag(parent: &NodeTag, tag: &NodeTag) -> Error {
+	Error::new(
+		parent.name,
+		parent.name_range.clone(),
+		ErrorKind::UnexpectedTag(tag.name.to_string(), tag.name_range.clone())
+	)
+}
+
+fn table_html(rows: Vec<Vec<TableCell>>) -> String {
+	let mut value = String::from("<table>\n");
+
+	for row in rows {
+		value.push_str("<tr>\n");
+
+		for TableCell { tag, children, .. } in row {
+			let name = if tag.is("th") { "th" } else { "td" };
+			let html = html::write(&TmDoc(Root { children, position: None }));
+			let html = html.trim_end();
+			// Single paragraphs are unwrapped.
+			let html = html
+				.strip_prefix("<p>")
+				.and_then(|html| html.strip_suffix("</p>"))
+				.filter(|html| !html.contains("<p>"))
+				.unwrap_or(html);
+
+			value.push('<');
+			value.push_str(name);
+
+			if let Some(align) = tag.param("align") {
+				value.push_str(&format!(" align=\"{}\"", escape_html(align)));
+			}
+
+			value.push('>');
+			value.push_str(html);
+			value.push_str(&format!("</{name}>\n"));
+		}
+
+		value.push_str("</tr>\n");
+	}
+
+	value.push_str("</table>");
+	value
+}
+
+fn trim_newlines(text: &str) -> &str {
+	text.trim_start_matches(['\r', '\n'])
+		.trim_end()
+}
+
+/// Groups phrasing content in flow containers into paragraphs, splitting them at
+/// blank lines. Single newlines are line breaks in BBCode. Split text is placed
+/// within the input it borrows from.
+fn normalize(root: &mut Root, cx: &Context) {
+	for child in &mut root.children {
+		if let Content::Flow(node) = child {
+			normalize_flow(node, cx);
+		}
+	}
+
+	root.children = paragraphs(mem::take(&mut root.children), cx)
+		.into_iter()
+		.map(Content::Flow)
+		.collect();
+}
+
+fn normalize_flow(node: &mut FlowContent, cx: &Context) {
+	match node {
+		FlowContent::Align(align) => normalize_children(&mut align.children, cx),
+		FlowContent::Quote(quote) => normalize_children(&mut quote.children, cx),
+		FlowContent::Spoiler(spoiler) => normalize_children(&mut spoiler.children, cx),
+		FlowContent::List(list) =>
+			for item in &mut list.children {
+				normalize_children(&mut item.children, cx);
+			},
+		_ => { }
+	}
+}
+
+fn normalize_children(children: &mut Vec<FlowContent>, cx: &Context) {
+	children.iter_mut().for_each(|child| normalize_flow(child, cx));
+	*children = paragraphs(mem::take(children).into_iter().map(Content::Flow), cx);
+}
+
+/// Regroups content into blocks. Paragraphs are split again, since phrasing
+/// content added to flow containers is collected into their last paragraph.
+fn paragraphs<'t>(children: impl IntoIterator<Item = Content<'t>>, cx: &Context) -> Vec<FlowContent<'t>> {
+	let mut flow = Vec::new();
+	let mut run  = Vec::new();
+
+	for child in children {
+		match child {
+			Content::Flow(FlowContent::Content(TextContent::Paragraph(para))) => {
+				push_paragraph(&mut flow, &mut run, cx);
+
+				for child in para.children {
+					push_phrasing(&mut flow, &mut run, child, cx);
+				}
+
+				push_paragraph(&mut flow, &mut run, cx);
+			}
+			Content::Flow(child) => {
+				push_paragraph(&mut flow, &mut run, cx);
+				flow.push(child);
+			}
+			Content::Phrasing(child) => push_phrasing(&mut flow, &mut run, child, cx),
+		}
+	}
+
+	push_paragraph(&mut flow, &mut run, cx);
+	flow
+}
+
+/// Adds phrasing content to the run, splitting text at newlines.
+fn push_phrasing<'t>(
+	flow: &mut Vec<FlowContent<'t>>,
+	run: &mut Vec<PhrasingContent<'t>>,
+	child: PhrasingContent<'t>,
+	cx: &Context
+) {
+	let PhrasingContent::Static(StaticPhrasingContent::Text(text)) = child else {
+		run.push(child);
+		return
+	};
+
+	if !text.value.contains('\n') {
+		run.push(text.into());
+		return
+	}
+
+	for (i, para) in regex!(r"\n[ \t]*\n\s*").split(&text.value).enumerate() {
+		if i > 0 {
+			push_paragraph(flow, run, cx);
+		}
+
+		for (j, line) in para.split('\n').enumerate() {
+			let start = offset_in(cx.input, line);
+
+			if j > 0 {
+				let position = start.and_then(|start| cx.position(start - 1..start));
+				run.push(Break::new(position).into());
+			}
+
+			let position = start.and_then(|start| cx.position(start..start + line.len()));
+			run.push(Text::new(substr(&text.value, line), position).into());
+		}
+	}
+}
+
+/// Returns the offset of `part` in `input`, or `None` if it isn't a slice of the
+/// input.
+fn offset_in(input: &str, part: &str) -> Option<usize> {
+	let start = (part.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+	(start + part.len() <= input.len()).then_some(start)
+}
+
+/// Returns `part`, a slice of `value`, borrowing it if `value` is borrowed.
+fn substr<'t>(value: &Cow<'t, str>, part: &str) -> Cow<'t, str> {
+	match value {
+		Cow::Borrowed(value) => {
+			let start = part.as_ptr() as usize - value.as_ptr() as usize;
+			Cow::Borrowed(&value[start..start + part.len()])
+		}
+		Cow::Owned(_) => Cow::Owned(part.to_string())
 	}
 }
 
-#[cfg(test)]
+fn push_paragraph<'t>(flow: &mut Vec<FlowContent<'t>>, run: &mut Vec<PhrasingContent<'t>>, cx: &Context) {
+	let mut children = mem::take(run);
+	let len = children.len();
+	let is_break = |node: &PhrasingContent| matches!(node, PhrasingContent::Static(StaticPhrasingContent::Break(_)));
+
+	for i in 0..len {
+		let after_break = i == 0 || is_break(&children[i - 1]);
+		let before_break = i + 1 == len || is_break(&children[i + 1]);
+
+		if let PhrasingContent::Static(StaticPhrasingContent::Text(text)) = &mut children[i] {
+			let len = text.value.len();
+
+			if after_break {
+				text.value = map_cow(mem::take(&mut text.value), |value| value.trim_start().into());
+
+				if let Some(position) = &mut text.position {
+					let start = position.start.offset + len - text.value.len();
+					position.start = cx.lines.point(start);
+				}
+			}
+
+			let len = text.value.len();
+
+			if before_break {
+				text.value = map_cow(mem::take(&mut text.value), |value| value.trim_end().into());
+
+				if let Some(position) = &mut text.position {
+					let end = position.end.offset + text.value.len() - len;
+					position.end = cx.lines.point(end);
+				}
+			}
+		}
+	}
+
+	children.retain(|child| !matches!(child, PhrasingContent::Static(StaticPhrasingContent::Text(text)) if text.value.is_empty()));
+
+	// Breaks at the edges are left over from newlines around blocks.
+	while children.first().is_some_and(is_break) {
+		children.remove(0);
+	}
+
+	while children.last().is_some_and(is_break) {
+		children.pop();
+	}
+
+	if children.is_empty() {
+		return
+	}
+
+	let position = children.first()
+		.and_then(PhrasingContent::position)
+		.zip(children.last().and_then(PhrasingContent::position))
+		.map(|(first, last)| Position { start: first.start, end: last.end });
+	flow.push(Paragraph::new(children, position).into());
+}
+
+#[cfg(all(test, feature = "markdown"))]
 mod tests {
+	use markdown::mdast::*;
+
+	use crate::TmDoc;
+	use crate::ast::NodeBuilder;
+
+	use super::{parse, parse_lenient, Dialect, DialectOptions, ErrorKind, TagContent, TagKind};
+
 	const SIMPLE_BLOCK      : &str = r"[tag]text[/tag]";
 	const BLOCK_WITH_VALUE  : &str = r"[tag=value]text[/tag]";
 	const BLOCK_WITH_PARAMS : &str = r#"[tag abc="val1" def="val2"]text[/tag]"#;
@@ -601,4 +1318,498 @@ mod tests {
 	const TEXT_INFIX_BLOCK  : &str = "Paragraph 1\n[h1]Heading[/h1]\nParagraph 2";
 	const NESTED_BLOCK      : &str = r"[s]Stricken and [i]italicized[/i] text[/s]";
 	const OUT_OF_SCOPE_BLOCK: &str = r"[size=14]oops! Your [quote] is out of scope![/size][/quote]";
+
+	fn parse_nodes(input: &str) -> Vec<Node> {
+		children(parse(input, Dialect::Generic).expect("parse failed"))
+	}
+
+	/// Returns the root's children without positions, which are tested apart.
+	fn children(doc: TmDoc) -> Vec<Node> {
+		let mut node = Node::from(doc.0);
+		clear_positions(&mut node);
+
+		match node {
+			Node::Root(root) => root.children,
+			_ => panic!("no root")
+		}
+	}
+
+	fn clear_positions(node: &mut Node) {
+		node.position_set(None);
+
+		if let Some(children) = node.children_mut() {
+			children.iter_mut().for_each(clear_positions);
+		}
+	}
+
+	fn text(value: &str) -> Node {
+		Node::Text(Text { value: value.to_string(), position: None })
+	}
+
+	fn html(value: &str) -> Node {
+		Node::Html(Html { value: value.to_string(), position: None })
+	}
+
+	fn jsx(name: &str, attributes: &[(&str, &str)], children: Vec<Node>) -> Node {
+		Node::MdxJsxTextElement(MdxJsxTextElement {
+			children,
+			position: None,
+			name: Some(name.to_string()),
+			attributes: attributes.iter().map(|(name, value)|
+				AttributeContent::Property(MdxJsxAttribute {
+					name: name.to_string(),
+					value: Some(AttributeValue::Literal(value.to_string()))
+				})
+			).collect()
+		})
+	}
+
+	fn para(children: Vec<Node>) -> Node {
+		Node::Paragraph(Paragraph { children, position: None })
+	}
+
+	#[test]
+	fn unknown_tags() {
+		for input in [SIMPLE_BLOCK, BLOCK_WITH_VALUE, BLOCK_WITH_PARAMS, BLOCK_WITH_BOTH] {
+			let error = parse(input, Dialect::Generic).expect_err("no error");
+
+			assert_eq!(error.kind, ErrorKind::UnknownTag);
+			assert_eq!(error.value, "tag");
+			assert_eq!(error.range, 1..4);
+		}
+	}
+
+	#[test]
+	fn url() {
+		assert_eq!(parse_nodes("[url=https://example.com]text[/url]"), vec![
+			para(vec![
+				Node::Link(Link {
+					children: vec![text("text")],
+					position: None,
+					url: "https://example.com".to_string(),
+					title: None,
+				})
+			])
+		]);
+	}
+
+	#[test]
+	fn image() {
+		assert_eq!(parse_nodes(r#"[img alt="A cat" title="Cat"]cat.png[/img]"#), vec![
+			para(vec![
+				Node::Image(Image {
+					position: None,
+					alt: "A cat".to_string(),
+					url: "cat.png".to_string(),
+					title: Some("Cat".to_string()),
+				})
+			])
+		]);
+	}
+
+	#[test]
+	fn sized_image() {
+		assert_eq!(parse_nodes(r#"[img=800x600 alt="A cat"]cat.png[/img]"#), vec![
+			para(vec![html(r#"<img src="cat.png" alt="A cat" width="800" height="600" />"#)])
+		]);
+		assert_eq!(parse_nodes(r#"[img=10x10]javascript:alert("hi")[/img]"#), vec![
+			para(vec![html(r#"<img src="" alt="" width="10" height="10" />"#)])
+		]);
+	}
+
+	#[test]
+	fn text_prefix_block() {
+		assert_eq!(parse_nodes(TEXT_PREFIX_BLOCK), vec![
+			para(vec![
+				text("You can make "),
+				Node::Strong(Strong { children: vec![text("bold text!")], position: None }),
+			])
+		]);
+	}
+
+	#[test]
+	fn text_suffix_block() {
+		assert_eq!(parse_nodes(TEXT_SUFFIX_BLOCK), vec![
+			para(vec![
+				jsx("u", &[], vec![text("Underline")]),
+				text(" text with the 'u' tag!"),
+			])
+		]);
+	}
+
+	#[test]
+	fn text_infix_block() {
+		assert_eq!(parse_nodes(TEXT_INFIX_BLOCK), vec![
+			para(vec![text("Paragraph 1")]),
+			Node::Heading(Heading { depth: 1, children: vec![text("Heading")], position: None }),
+			para(vec![text("Paragraph 2")]),
+		]);
+	}
+
+	#[test]
+	fn nested_block() {
+		assert_eq!(parse_nodes(NESTED_BLOCK), vec![
+			para(vec![
+				Node::Delete(Delete {
+					children: vec![
+						text("Stricken and "),
+						Node::Emphasis(Emphasis { children: vec![text("italicized")], position: None }),
+						text(" text"),
+					],
+					position: None,
+				})
+			])
+		]);
+	}
+
+	#[test]
+	fn out_of_scope_block() {
+		let error = parse(OUT_OF_SCOPE_BLOCK, Dialect::Generic).expect_err("no error");
+
+		assert_eq!(error.kind, ErrorKind::UnclosedTag);
+		assert_eq!(error.value, "quote");
+	}
+
+	#[test]
+	fn unopened_tag() {
+		let error = parse("text[/b]", Dialect::Generic).expect_err("no error");
+
+		assert_eq!(error.kind, ErrorKind::UnopenedTag);
+		assert_eq!(error.range, 6..7);
+	}
+
+	#[test]
+	fn paragraphs_and_breaks() {
+		assert_eq!(parse_nodes("Line 1\nLine 2\n\nParagraph 2"), vec![
+			para(vec![text("Line 1"), Node::Break(Break { position: None }), text("Line 2")]),
+			para(vec![text("Paragraph 2")]),
+		]);
+	}
+
+	#[test]
+	fn lists() {
+		let item = |value| Node::ListItem(ListItem {
+			children: vec![para(vec![text(value)])],
+			position: None,
+			spread: false,
+			checked: None,
+		});
+
+		assert_eq!(parse_nodes("[list=1]\n[*]One\n[*]Two\n[/list]"), vec![
+			Node::List(List {
+				children: vec![item("One"), item("Two")],
+				position: None,
+				ordered: true,
+				start: Some(1),
+				spread: false,
+			})
+		]);
+	}
+
+	#[test]
+	fn code_and_quote() {
+		assert_eq!(parse_nodes("[code=rust]\nlet [b] = 1;\n[/code]\n[quote=\"Jo Doe\"]Hi[/quote]"), vec![
+			Node::Code(Code {
+				value: "let [b] = 1;".to_string(),
+				position: None,
+				lang: Some("rust".to_string()),
+				meta: None,
+			}),
+			Node::MdxJsxFlowElement(MdxJsxFlowElement {
+				children: vec![para(vec![text("Hi")])],
+				position: None,
+				name: Some("blockquote".to_string()),
+				attributes: vec![
+					AttributeContent::Property(MdxJsxAttribute {
+						name: "author".to_string(),
+						value: Some(AttributeValue::Literal("Jo Doe".to_string()))
+					})
+				],
+			}),
+		]);
+	}
+
+	#[test]
+	fn table() {
+		let row = |values: [&str; 2]| Node::TableRow(TableRow {
+			children: values.into_iter().map(|value|
+				Node::TableCell(TableCell { children: vec![text(value)], position: None })
+			).collect(),
+			position: None,
+		});
+
+		assert_eq!(
+			parse_nodes(
+				"[table]\n\
+				[tr][th align=center]Name[/th][th]Qty[/th][/tr]\n\
+				[tr][td] Apple [/td][td]3[/td][/tr]\n\
+				[/table]"
+			),
+			vec![
+				Node::Table(Table {
+					children: vec![row(["Name", "Qty"]), row(["Apple", "3"])],
+					position: None,
+					align: vec![AlignKind::Center, AlignKind::None],
+				})
+			]
+		);
+	}
+
+	#[test]
+	fn table_fallback() {
+		assert_eq!(
+			parse_nodes("[table][tr][td][b]1[/b][/td][td]2[/td][/tr][tr][td]3[/td][/tr][/table]"),
+			vec![html("<table>\n<tr>\n<td><strong>1</strong></td>\n<td>2</td>\n</tr>\n<tr>\n<td>3</td>\n</tr>\n</table>")]
+		);
+	}
+
+	#[test]
+	fn misplaced_table_tags() {
+		let error = parse("[tr][td]1[/td][/tr]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::MisplacedTag);
+		assert_eq!(error.value, "tr");
+
+		let error = parse("[table][tr]text[/tr][/table]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::UnexpectedText);
+
+		let error = parse("[table][td]1[/td][/table]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::UnexpectedTag("td".to_string(), 8..10));
+	}
+
+	#[test]
+	fn misplaced_block_tags() {
+		let error = parse("[b]x [quote]q[/quote] y[/b]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::MisplacedTag);
+		assert_eq!(error.value, "quote");
+
+		let error = parse("[b]x[list][*]a[/list][/b]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::MisplacedTag);
+		assert_eq!(error.value, "list");
+
+		let error = parse("[url=https://example.com][size=2][h1]a[/h1][/size][/url]", Dialect::Generic).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::MisplacedTag);
+		assert_eq!(error.value, "h1");
+	}
+
+	fn custom_dialect() -> Dialect {
+		let mut options = DialectOptions::default();
+		options.tags
+			.register("mention", TagKind::Nested, |tag, content, nb| {
+				let id = tag.value().ok_or_else(|| tag.missing_param("mention"))?;
+				let TagContent::Nodes(children) = content else { unreachable!() };
+
+				nb.link(|nb| Ok(children.into_iter().fold(nb.set_url(format!("/members/{id}")), NodeBuilder::append)))
+			})
+			.register("ticket", TagKind::Verbatim, |_, content, nb| {
+				let TagContent::Text(id) = content else { unreachable!() };
+
+				nb.link(|nb| Ok(nb.set_url(format!("/tickets/{id}")).text(id)))
+			})
+			.register("rank", TagKind::Void, |tag, _, nb| {
+				let rank = tag.value().unwrap_or("Member");
+				nb.strong(|nb| Ok(nb.text(rank.to_string())))
+			})
+			.register("u", TagKind::Nested, |_, content, nb| {
+				let TagContent::Nodes(children) = content else { unreachable!() };
+				nb.emphasis(|nb| Ok(children.into_iter().fold(nb, NodeBuilder::append)))
+			});
+
+		Dialect::Custom(Box::new(options))
+	}
+
+	#[test]
+	fn custom_tags() {
+		let link = |url: &str, children| Node::Link(Link {
+			children,
+			position: None,
+			url: url.to_string(),
+			title: None,
+		});
+		let strong = |children| Node::Strong(Strong { children, position: None });
+
+		let nodes = match parse("[MENTION=123]Jo [b]Doe[/b][/mention] filed [ticket]ABC-[1][/ticket] [rank=Admin]", custom_dialect()) {
+			Ok(doc) => children(doc),
+			_ => panic!("parse failed")
+		};
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				link("/members/123", vec![text("Jo "), strong(vec![text("Doe")])]),
+				text(" filed "),
+				link("/tickets/ABC-[1]", vec![text("ABC-[1]")]),
+				text(" "),
+				strong(vec![text("Admin")]),
+			])
+		]);
+	}
+
+	#[test]
+	fn custom_tag_overrides() {
+		let nodes = match parse("[u]under[/u]", custom_dialect()) {
+			Ok(doc) => children(doc),
+			_ => panic!("parse failed")
+		};
+
+		assert_eq!(nodes, vec![para(vec![Node::Emphasis(Emphasis { children: vec![text("under")], position: None })])]);
+
+		let error = parse("[mention]Jo[/mention]", custom_dialect()).expect_err("no error");
+		assert_eq!(error.kind, ErrorKind::MissingParam("mention".to_string()));
+		assert_eq!(error.value, "mention");
+
+		let (doc, errors) = parse_lenient("[mention]Jo [b]Doe[/b][/mention]", custom_dialect());
+
+		assert_eq!(children(doc), vec![
+			para(vec![
+				text("[mention]"),
+				text("Jo "),
+				Node::Strong(Strong { children: vec![text("Doe")], position: None }),
+				text("[/mention]"),
+			])
+		]);
+		assert_eq!(errors.len(), 1);
+	}
+
+	fn parse_lenient_nodes(input: &str) -> (Vec<Node>, Vec<ErrorKind>) {
+		let (doc, errors) = parse_lenient(input, Dialect::Generic);
+		(children(doc), errors.into_iter().map(|error| error.kind).collect())
+	}
+
+	#[test]
+	fn lenient_unclosed() {
+		let (nodes, errors) = parse_lenient_nodes("[b]bold [i]both");
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				Node::Strong(Strong {
+					children: vec![
+						text("bold "),
+						Node::Emphasis(Emphasis { children: vec![text("both")], position: None })
+					],
+					position: None
+				})
+			])
+		]);
+		assert_eq!(errors, vec![ErrorKind::UnclosedTag, ErrorKind::UnclosedTag]);
+
+		let (nodes, errors) = parse_lenient_nodes("[code]let a = 1;");
+
+		assert_eq!(nodes, vec![
+			Node::Code(Code { value: "let a = 1;".to_string(), position: None, lang: None, meta: None })
+		]);
+		assert_eq!(errors, vec![ErrorKind::UnclosedTag]);
+	}
+
+	#[test]
+	fn lenient_out_of_scope() {
+		let (nodes, errors) = parse_lenient_nodes(OUT_OF_SCOPE_BLOCK);
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				jsx("font", &[("size", "14px")], vec![text("oops! Your "), text("[quote]"), text(" is out of scope!")]),
+				text("[/quote]"),
+			]),
+		]);
+		assert_eq!(errors, vec![ErrorKind::UnclosedTag, ErrorKind::MisplacedTag, ErrorKind::UnopenedTag]);
+	}
+
+	#[test]
+	fn lenient_misplaced_block_tags() {
+		let (nodes, errors) = parse_lenient_nodes("[b]x [quote]q[/quote] y[/b]\n\n[i][list][*]a[/list][/i]");
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				Node::Strong(Strong {
+					children: vec![text("x "), text("[quote]"), text("q"), text("[/quote]"), text(" y")],
+					position: None
+				})
+			]),
+			para(vec![
+				Node::Emphasis(Emphasis {
+					// List markers are only read inside lists.
+					children: vec![text("[list]"), text("a"), text("[/list]")],
+					position: None
+				})
+			]),
+		]);
+		assert_eq!(errors, vec![ErrorKind::MisplacedTag, ErrorKind::MisplacedTag]);
+	}
+
+	#[test]
+	fn lenient_literal_tags() {
+		let (nodes, errors) = parse_lenient_nodes("[b]a [tag=value]b [i]c[/i][/tag][/b] [color]d[/color][/u]");
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				Node::Strong(Strong {
+					children: vec![
+						text("a "),
+						text("[tag=value]"),
+						text("b "),
+						Node::Emphasis(Emphasis { children: vec![text("c")], position: None }),
+						text("[/tag]"),
+					],
+					position: None
+				}),
+				text(" "),
+				text("[color]"),
+				text("d"),
+				text("[/color]"),
+				text("[/u]"),
+			])
+		]);
+		assert_eq!(errors, vec![
+			ErrorKind::UnknownTag,
+			ErrorKind::MissingParam("color".to_string()),
+			ErrorKind::UnopenedTag,
+		]);
+	}
+
+	#[test]
+	fn lenient_nested_literal_tags() {
+		let (nodes, errors) = parse_lenient_nodes("[x][y][b]a[/b][/y][/x]");
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				text("[x]"),
+				text("[y]"),
+				Node::Strong(Strong { children: vec![text("a")], position: None }),
+				text("[/y]"),
+				text("[/x]"),
+			])
+		]);
+		assert_eq!(errors, vec![ErrorKind::UnknownTag, ErrorKind::UnknownTag]);
+
+		// Each tag is built once, however deep the unknown tags are nested.
+		let input = "[x]".repeat(64) + &"[/x]".repeat(64);
+		let (_, errors) = parse_lenient_nodes(&input);
+
+		assert_eq!(errors.len(), 64);
+	}
+
+	#[test]
+	fn positions() {
+		fn range(node: &Node) -> (usize, usize) {
+			let position = node.position().expect("no position");
+			(position.start.offset, position.end.offset)
+		}
+
+		let input = "Some [b]bold[/b]\n[list][*]one[/list]";
+		let root = Node::from(parse(input, Dialect::Generic).expect("parse failed").0);
+		let children = root.children().unwrap();
+		let para = &children[0];
+		let para_children = para.children().unwrap();
+		let strong = &para_children[1];
+		let list = &children[1];
+		let item = &list.children().unwrap()[0];
+
+		assert_eq!(range(&root), (0, input.len()));
+		assert_eq!(range(para), (0, 16));
+		assert_eq!(range(&para_children[0]), (0, 5));
+		assert_eq!(range(strong), (5, 16));
+		assert_eq!(range(&strong.children().unwrap()[0]), (8, 12));
+		assert_eq!(range(list), (17, input.len()));
+		assert_eq!(range(item), (23, 29));
+		assert_eq!(range(&item.children().unwrap()[0]), (26, 29));
+		assert_eq!(root.position().unwrap().end.line, 2);
+	}
 }
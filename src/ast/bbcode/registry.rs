@@ -0,0 +1,96 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::NodeBuilder;
+use crate::tmast::{Content, Root};
+
+use super::{Error, NodeTag};
+
+/// Builds the nodes for a custom tag into a [NodeBuilder], from the tag and its
+/// content. Text may be borrowed from the input.
+pub trait TagHandler = for<'t> Fn(&NodeTag<'t>, TagContent<'t>, NodeBuilder<Root<'t>>) -> Result<NodeBuilder<Root<'t>>, Error> + Send + Sync;
+
+/// How the content of a custom tag is read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TagKind {
+	/// The content is parsed, and may contain other tags.
+	Nested,
+	/// The content is taken as-is up to the end tag, like `[code]`.
+	Verbatim,
+	/// The tag has no content or end tag, like `[hr]`.
+	Void,
+}
+
+/// The content of a custom tag, depending on its [TagKind].
+#[derive(Clone, Debug)]
+pub enum TagContent<'t> {
+	Nodes(Vec<Content<'t>>),
+	Text(&'t str),
+	None,
+}
+
+#[derive(Clone)]
+pub(super) struct CustomTag {
+	pub kind: TagKind,
+	pub handler: Arc<dyn TagHandler>,
+}
+
+/// Tags defined at runtime, in addition to or replacing the built-in tags.
+#[derive(Clone, Default)]
+pub struct TagRegistry {
+	tags: HashMap<String, CustomTag>,
+}
+
+impl TagRegistry {
+	/// Registers a tag, replacing any tag of the same name. Names are case-
+	/// insensitive, and are matched after the dialect's aliases.
+	pub fn register(&mut self, name: &str, kind: TagKind, handler: impl TagHandler + 'static) -> &mut Self {
+		self.tags.insert(
+			name.to_ascii_lowercase(),
+			CustomTag { kind, handler: Arc::new(handler) }
+		);
+		self
+	}
+
+	/// Removes a registered tag, returning whether it was registered.
+	pub fn unregister(&mut self, name: &str) -> bool {
+		self.tags.remove(&name.to_ascii_lowercase()).is_some()
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.tags.contains_key(&name.to_ascii_lowercase())
+	}
+
+	pub fn kind(&self, name: &str) -> Option<TagKind> {
+		self.get(&name.to_ascii_lowercase()).map(|tag| tag.kind)
+	}
+
+	pub(super) fn get(&self, name: &str) -> Option<&CustomTag> {
+		self.tags.get(name)
+	}
+}
+
+impl fmt::Debug for TagRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map()
+		 .entries(self.tags.iter().map(|(name, tag)| (name, tag.kind)))
+		 .finish()
+	}
+}
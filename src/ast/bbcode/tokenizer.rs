@@ -19,17 +19,17 @@ use std::ops::Range;
 use regex::{Match, Regex, Captures, CaptureMatches};
 use regex_macro::regex;
 
-fn tag_regex() -> &'static Regex {
+pub(crate) fn tag_regex() -> &'static Regex {
 	regex!(r#"(?ix)
 	\[(
 		(?P<param>
-			(?P<tag>\w+)
-			(=[\S&&[^\]]]+)? # Single parameter, i.e. =800x600
+			(?P<tag>\w+|\*)
+			(=("[^"]*"|[\S&&[^\]]]+))? # Single parameter, i.e. =800x600
 			(
 				\s+     # Separating whitespace
-				[a-z]+  # Parameter key
+				\w+     # Parameter key
 				=
-				"[^"]+" # Parameter value
+				("[^"]+"|[\S&&[^\]"]]+) # Parameter value
 			)*
 		)|
 		/(?P<endTag>\w+)
@@ -120,6 +120,11 @@ impl<'t> FragmentStream<'t> {
 		}
 	}
 
+	/// Returns the input being split.
+	pub fn input(&self) -> &'t str {
+		self.input
+	}
+
 	fn next_text(&mut self) -> Option<Fragment<'t>> {
 		let Self {
 			input,
@@ -128,7 +133,7 @@ impl<'t> FragmentStream<'t> {
 			tags,
 		} = self;
 
-		if let Some(_) = last_tag {
+		if last_tag.is_some() {
 			return None
 		}
 
@@ -278,7 +283,7 @@ mod tests {
 	fn generate(seq: ExpectedSequence) -> Vec<(String, Fragment<'static>)> {
 			let mut i = 0;
 
-			seq.into_iter().map(move |(label, expected)| {
+			seq.iter().map(move |(label, expected)| {
 			match expected {
 				Text(value) => {
 					let start = i;
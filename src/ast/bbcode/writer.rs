@@ -0,0 +1,666 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use crate::tmast::*;
+use crate::tmast::definitions::Definitions;
+use regex::Captures;
+use regex_macro::regex;
+
+use crate::util::{decode_entities, youtube_id, Emit};
+
+use super::dialect::{DialectOptions, HeadingStyle, QuoteStyle, VideoStyle};
+use super::escape_bbcode;
+
+pub(super) struct Writer<'n> {
+	dialect: DialectOptions,
+	out: String,
+	definitions: Definitions<'n>,
+	/// End tags for open HTML spans, written when the span is closed.
+	spans: Vec<String>,
+	/// Cells written in the current row, for dialects without tables.
+	cells: usize,
+	/// Whether any output has been emitted.
+	emitted: bool,
+}
+
+impl<'n> Writer<'n> {
+	pub fn new(dialect: DialectOptions) -> Self {
+		Self {
+			dialect,
+			out: String::new(),
+			definitions: Definitions::default(),
+			spans: Vec::new(),
+			cells: 0,
+			emitted: false,
+		}
+	}
+
+	/// Writes the document, emitting the output as each top-level block and
+	/// footnote is finished.
+	pub fn write(mut self, root: &'n Root<'n>, emit: &mut Emit) -> io::Result<()> {
+		self.definitions = Definitions::collect(root);
+
+		self.root(&root.children, emit)?;
+		self.footnote_section(emit)?;
+		self.flush(emit)?;
+
+		if self.emitted {
+			emit("\n")?;
+		}
+
+		Ok(())
+	}
+
+	/// Emits the output written so far, holding back trailing whitespace, which
+	/// is trimmed from the end of the document.
+	fn flush(&mut self, emit: &mut Emit) -> io::Result<()> {
+		let len = self.out.trim_end().len();
+
+		if len > 0 {
+			emit(&self.out[..len])?;
+			self.out.drain(..len);
+			self.emitted = true;
+		}
+
+		Ok(())
+	}
+
+	/// Writes the root content, separating blocks with a blank line. Adjacent
+	/// phrasing content is kept together.
+	fn root(&mut self, children: &'n [Content<'n>], emit: &mut Emit) -> io::Result<()> {
+		let mut prev = None;
+
+		for child in children {
+			match child {
+				Content::Flow(node) => {
+					self.separated(&mut prev, false, "\n\n", |w| w.block(node));
+					self.flush(emit)?;
+				}
+				Content::Phrasing(node) => self.separated(&mut prev, true, "\n\n", |w| w.inline(node)),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Writes flow content, separating blocks with `separator`.
+	fn blocks(&mut self, children: &'n [FlowContent<'n>], separator: &str) {
+		let mut prev = None;
+
+		for child in children {
+			self.separated(&mut prev, false, separator, |w| w.block(child));
+		}
+	}
+
+	/// Writes a child after `separator`, unless both it and the previous child
+	/// are phrasing content. The separator is dropped if nothing is written.
+	fn separated(&mut self, prev: &mut Option<bool>, phrasing: bool, separator: &str, write: impl FnOnce(&mut Self)) {
+		let start = self.out.len();
+
+		if prev.is_some_and(|prev| !prev || !phrasing) {
+			self.out.push_str(separator);
+		}
+
+		let content_start = self.out.len();
+		write(self);
+
+		if self.out.len() == content_start {
+			// Nothing was written, drop the separator.
+			self.out.truncate(start);
+		} else {
+			*prev = Some(phrasing);
+		}
+	}
+
+	fn block(&mut self, node: &'n FlowContent<'n>) {
+		match node {
+			FlowContent::Align(align) => self.align(align),
+			FlowContent::Quote(quote) => self.quote(quote),
+			FlowContent::Code(code) => {
+				self.out.push_str("[code");
+
+				if let Some(lang) = code.lang.as_ref().filter(|_| self.dialect.code_lang) {
+					self.out.push('=');
+					self.param(lang);
+				}
+
+				self.out.push_str("]\n");
+				self.verbatim("code", &code.value);
+				self.out.push_str("\n[/code]");
+			}
+			FlowContent::Heading(heading) => self.heading(heading),
+			FlowContent::Html(html) => self.html(&html.value),
+			FlowContent::List(list) => self.list(list),
+			FlowContent::Math(math) => {
+				self.out.push_str(if self.dialect.code_lang { "[code=math]\n" } else { "[code]\n" });
+				self.verbatim("code", &math.value);
+				self.out.push_str("\n[/code]");
+			}
+			FlowContent::Content(TextContent::Paragraph(para)) => self.inlines(&para.children),
+			FlowContent::Spoiler(spoiler) => self.spoiler(spoiler),
+			FlowContent::Table(table) => self.table(table),
+			FlowContent::ThematicBreak(_) => self.out.push_str(&self.dialect.rule),
+			// Links take their URL from these, and footnotes follow the rule at the end.
+			FlowContent::Content(TextContent::Definition(_)) |
+			FlowContent::FootnoteDef(_) => { }
+		}
+	}
+
+	fn inlines(&mut self, children: &'n [PhrasingContent<'n>]) {
+		for child in children {
+			self.inline(child);
+		}
+	}
+
+	fn inline(&mut self, node: &'n PhrasingContent<'n>) {
+		match node {
+			PhrasingContent::FootnoteRef(reference) => self.footnote_ref(reference),
+			PhrasingContent::Link(link) => self.link(&link.url, &link.children),
+			PhrasingContent::LinkRef(reference) => {
+				if let Some(def) = self.definitions.definition(&reference.identifier) {
+					self.link(&def.url, &reference.children);
+				} else {
+					self.static_inlines(&reference.children);
+				}
+			}
+			PhrasingContent::Static(node) => self.static_inline(node),
+		}
+	}
+
+	fn static_inlines(&mut self, children: &'n [StaticPhrasingContent<'n>]) {
+		for child in children {
+			self.static_inline(child);
+		}
+	}
+
+	fn static_inline(&mut self, node: &'n StaticPhrasingContent<'n>) {
+		match node {
+			StaticPhrasingContent::Break(_) => self.out.push('\n'),
+			StaticPhrasingContent::Color(color) => {
+				if self.dialect.color {
+					self.out.push_str("[color=");
+					self.param(&color.color);
+					self.out.push(']');
+					self.inlines(&color.children);
+					self.out.push_str("[/color]");
+				} else {
+					self.inlines(&color.children);
+				}
+			}
+			StaticPhrasingContent::Delete(delete) => {
+				if let Some(tag) = self.dialect.strikethrough.clone() {
+					self.wrap(&tag, &delete.children);
+				} else {
+					self.inlines(&delete.children);
+				}
+			}
+			StaticPhrasingContent::Embed(embed) => self.embed(&embed.url),
+			StaticPhrasingContent::Emphasis(emphasis) => self.wrap("i", &emphasis.children),
+			StaticPhrasingContent::FontSize(size) => {
+				if let Some(value) = self.dialect.size.and_then(|unit| unit.param_from_css(&size.size)) {
+					self.out.push_str("[size=");
+					self.param(&value);
+					self.out.push(']');
+					self.inlines(&size.children);
+					self.out.push_str("[/size]");
+				} else {
+					self.inlines(&size.children);
+				}
+			}
+			StaticPhrasingContent::Html(html) => self.html(&html.value),
+			StaticPhrasingContent::Image(image) => self.image(&image.url),
+			StaticPhrasingContent::ImageRef(reference) => {
+				if let Some(def) = self.definitions.definition(&reference.identifier) {
+					self.image(&def.url);
+				} else {
+					self.text(&reference.alt);
+				}
+			}
+			StaticPhrasingContent::InlineCode(InlineCode { value, .. }) |
+			StaticPhrasingContent::InlineMath(InlineMath { value, .. }) => {
+				if let Some(tag) = self.dialect.inline_code.clone() {
+					self.out.push_str(&format!("[{tag}]"));
+					self.verbatim(&tag, value);
+					self.out.push_str(&format!("[/{tag}]"));
+				} else {
+					self.text(value);
+				}
+			}
+			StaticPhrasingContent::Strong(strong) => self.wrap("b", &strong.children),
+			StaticPhrasingContent::Subscript(Subscript { children, .. }) => self.script("sub", children),
+			StaticPhrasingContent::Superscript(Superscript { children, .. }) => self.script("sup", children),
+			// Soft line breaks are spaces, newlines are hard breaks in BBCode.
+			StaticPhrasingContent::Text(text) => self.text(&text.value.replace('\n', " ")),
+			StaticPhrasingContent::Underline(underline) => self.wrap("u", &underline.children),
+		}
+	}
+
+	fn text(&mut self, text: &str) {
+		self.out.push_str(&escape_bbcode(text, &self.dialect.escape));
+	}
+
+	/// Writes the content of a tag read without parsing, such as `[code]`. Tags
+	/// inside aren't read, except the end tag, which is broken with a zero-width
+	/// space so it can't close the tag early.
+	fn verbatim(&mut self, tag: &str, value: &str) {
+		let value = regex!(r"\[/(\w+)]").replace_all(value, |caps: &Captures| {
+			if caps[1].eq_ignore_ascii_case(tag) {
+				format!("[\u{200B}/{}]", &caps[1])
+			} else {
+				caps[0].to_string()
+			}
+		});
+
+		self.out.push_str(&value);
+	}
+
+	/// Writes a URL as the content of a tag, percent-encoding brackets so it can't
+	/// end the tag or start another.
+	fn url(&mut self, url: &str) {
+		self.out.push_str(&url.replace('[', "%5B").replace(']', "%5D"));
+	}
+
+	/// Writes a tag parameter, quoting it if it contains spaces or brackets.
+	fn param(&mut self, value: &str) {
+		let value = value.replace(['"', '\n'], "");
+
+		if value.contains(char::is_whitespace) || value.contains(['[', ']']) {
+			self.out.push('"');
+			self.out.push_str(&value);
+			self.out.push('"');
+		} else {
+			self.out.push_str(&value);
+		}
+	}
+
+	fn wrap(&mut self, tag: &str, children: &'n [PhrasingContent<'n>]) {
+		self.out.push('[');
+		self.out.push_str(tag);
+		self.out.push(']');
+		self.inlines(children);
+		self.out.push_str("[/");
+		self.out.push_str(tag);
+		self.out.push(']');
+	}
+
+	/// Writes superscript or subscript text, or just the text if the dialect
+	/// doesn't support it.
+	fn script(&mut self, tag: &str, children: &'n [PhrasingContent<'n>]) {
+		if self.dialect.scripts {
+			self.wrap(tag, children);
+		} else {
+			self.inlines(children);
+		}
+	}
+
+	fn heading(&mut self, heading: &'n Heading<'n>) {
+		match self.dialect.headings.clone() {
+			HeadingStyle::Tags(max) => {
+				let tag = format!("h{}", heading.depth.clamp(1, max.max(1)));
+				self.wrap(&tag, &heading.children);
+			}
+			HeadingStyle::Param(tag, max) => {
+				let depth = heading.depth.clamp(1, max.max(1));
+
+				self.out.push_str(&format!("[{tag}={depth}]"));
+				self.inlines(&heading.children);
+				self.out.push_str(&format!("[/{tag}]"));
+			}
+			HeadingStyle::Bold => self.wrap("b", &heading.children),
+		}
+	}
+
+	fn link(&mut self, url: &str, children: &'n [StaticPhrasingContent<'n>]) {
+		if let [StaticPhrasingContent::Text(Text { value, .. })] = children {
+			if value == url {
+				self.out.push_str("[url]");
+				self.url(url);
+				self.out.push_str("[/url]");
+				return
+			}
+		}
+
+		self.out.push_str("[url=");
+		self.param(url);
+		self.out.push(']');
+		self.static_inlines(children);
+		self.out.push_str("[/url]");
+	}
+
+	fn image(&mut self, url: &str) {
+		self.out.push_str("[img]");
+		self.url(url);
+		self.out.push_str("[/img]");
+	}
+
+	/// Writes an embedded YouTube video in the dialect's video style. Other
+	/// embeds, and videos in dialects without embeds, are written as links.
+	fn embed(&mut self, url: &str) {
+		match (&self.dialect.video, youtube_id(url)) {
+			(VideoStyle::Tag,   Some(id)) => self.out.push_str(&format!("[youtube]{id}[/youtube]")),
+			(VideoStyle::Media, Some(id)) => self.out.push_str(&format!("[media=youtube]{id}[/media]")),
+			(VideoStyle::Video, Some(id)) => {
+				self.out.push_str(&format!("[video=youtube;{id}]"));
+				self.url(url);
+				self.out.push_str("[/video]");
+			}
+			_ => {
+				self.out.push_str("[url]");
+				self.url(url);
+				self.out.push_str("[/url]");
+			}
+		}
+	}
+
+	fn align(&mut self, align: &'n Align<'n>) {
+		let tag = match align.align {
+			AlignKind::Left   => "left",
+			AlignKind::Right  => "right",
+			AlignKind::Center => "center",
+			AlignKind::None   => "",
+		};
+
+		if tag.is_empty() || !self.dialect.align {
+			self.blocks(&align.children, "\n\n");
+			return
+		}
+
+		self.out.push_str(&format!("[{tag}]\n"));
+		self.blocks(&align.children, "\n\n");
+		self.out.push_str(&format!("\n[/{tag}]"));
+	}
+
+	/// Writes a spoiler, or its summary and content if the dialect has no
+	/// spoilers.
+	fn spoiler(&mut self, spoiler: &'n Spoiler<'n>) {
+		let Some(tag) = self.dialect.spoiler.clone() else {
+			let mut prev = None;
+
+			if let Some(summary) = &spoiler.summary {
+				self.separated(&mut prev, false, "\n\n", |w| w.text(summary));
+			}
+
+			for child in &spoiler.children {
+				self.separated(&mut prev, false, "\n\n", |w| w.block(child));
+			}
+
+			return
+		};
+
+		self.out.push('[');
+		self.out.push_str(&tag);
+
+		if let Some(summary) = &spoiler.summary {
+			self.out.push('=');
+			self.param(summary);
+		}
+
+		self.out.push_str("]\n");
+		self.blocks(&spoiler.children, "\n\n");
+		self.out.push_str(&format!("\n[/{tag}]"));
+	}
+
+	fn quote(&mut self, quote: &'n Quote<'n>) {
+		self.out.push_str("[quote");
+
+		if let Some(name) = &quote.author {
+			if let QuoteStyle::Key(key) = &self.dialect.quote {
+				self.out.push(' ');
+				self.out.push_str(key);
+			}
+
+			self.out.push('=');
+			self.param(name);
+		}
+
+		self.out.push_str("]\n");
+		self.blocks(&quote.children, "\n\n");
+		self.out.push_str("\n[/quote]");
+	}
+
+	fn list(&mut self, list: &'n List<'n>) {
+		let open = if list.ordered { self.dialect.ordered_list.as_str() } else { "list" };
+		// The end tag is the name of the start tag, without parameters.
+		let close = open.split([' ', '=']).next().unwrap_or(open).to_string();
+
+		self.out.push('[');
+		self.out.push_str(open);
+		self.out.push_str("]\n");
+
+		for item in &list.children {
+			self.out.push_str("[*]");
+
+			if let Some(checked) = item.checked {
+				self.out.push_str(if checked { "☑ " } else { "☐ " });
+			}
+
+			self.blocks(&item.children, "\n");
+
+			self.out.push('\n');
+		}
+
+		self.out.push_str(&format!("[/{close}]"));
+	}
+
+	fn table(&mut self, table: &'n Table<'n>) {
+		if !self.dialect.tables {
+			self.table_text(table);
+			return
+		}
+
+		self.out.push_str("[table]\n");
+
+		for (i, row) in table.children.iter().enumerate() {
+			if i == 0 {
+				self.table_row(row, "th", &table.align);
+			} else {
+				self.table_row(row, "td", &[]);
+			}
+
+			self.out.push('\n');
+		}
+
+		self.out.push_str("[/table]");
+	}
+
+	/// Writes each row of a table as a line of text, separating cells with `|`.
+	fn table_text(&mut self, table: &'n Table<'n>) {
+		for (i, row) in table.children.iter().enumerate() {
+			if i > 0 {
+				self.out.push('\n');
+			}
+
+			for (j, cell) in row.children.iter().enumerate() {
+				if j > 0 {
+					self.out.push_str(" | ");
+				}
+
+				self.inlines(&cell.children);
+			}
+		}
+	}
+
+	fn table_row(&mut self, row: &'n TableRow<'n>, tag: &str, align: &[AlignKind]) {
+		self.out.push_str("[tr]");
+
+		for (i, cell) in row.children.iter().enumerate() {
+			self.out.push('[');
+			self.out.push_str(tag);
+
+			match align.get(i) {
+				Some(AlignKind::Left  ) => self.out.push_str(" align=left"),
+				Some(AlignKind::Right ) => self.out.push_str(" align=right"),
+				Some(AlignKind::Center) => self.out.push_str(" align=center"),
+				_                       => { }
+			}
+
+			self.out.push(']');
+
+			self.inlines(&cell.children);
+
+			self.out.push_str("[/");
+			self.out.push_str(tag);
+			self.out.push(']');
+		}
+
+		self.out.push_str("[/tr]");
+	}
+
+	/// Converts the HTML written by the BBCode parser back into tags. Any other
+	/// tags are dropped, keeping their text.
+	fn html(&mut self, html: &str) {
+		let tags = regex!(r#"(?is)<!--.*?-->|<(/?)([a-z][a-z0-9]*)((?:[^>"]|"[^"]*")*)>"#);
+		let mut pos = 0;
+
+		for caps in tags.captures_iter(html) {
+			let tag = caps.get(0).unwrap();
+			self.text(&decode_entities(&html[pos..tag.start()]));
+			pos = tag.end();
+
+			if caps.get(2).is_some() {
+				self.html_tag(&caps);
+			}
+		}
+
+		self.text(&decode_entities(&html[pos..]));
+	}
+
+	fn html_tag(&mut self, caps: &Captures) {
+		let end   = !caps[1].is_empty();
+		let name  = caps[2].to_ascii_lowercase();
+		let attrs = &caps[3];
+		let attr  = |key: &str| {
+			regex!(r#"(?i)([a-z-]+)\s*=\s*"([^"]*)""#)
+				.captures_iter(attrs)
+				.find(|caps| caps[1].eq_ignore_ascii_case(key))
+				.map(|caps| decode_entities(&caps[2]).into_owned())
+		};
+
+		match (name.as_str(), end) {
+			("br", _) => self.out.push('\n'),
+			("u", false) => self.out.push_str("[u]"),
+			("u", true ) => self.out.push_str("[/u]"),
+			("table", _) if !self.dialect.tables => { }
+			("tr", false) if !self.dialect.tables => self.cells = 0,
+			("tr", true ) if !self.dialect.tables => self.out.push('\n'),
+			("td" | "th", false) if !self.dialect.tables => {
+				if self.cells > 0 {
+					self.out.push_str(" | ");
+				}
+
+				self.cells += 1;
+			}
+			("table" | "tr" | "td" | "th", _) => {
+				self.out.push('[');
+
+				if end {
+					self.out.push('/');
+				}
+
+				self.out.push_str(&name);
+
+				if let Some(align) = attr("align").filter(|_| !end) {
+					self.out.push_str(" align=");
+					self.param(&align);
+				}
+
+				self.out.push(']');
+			}
+			("details", _) => {
+				if let Some(tag) = &self.dialect.spoiler {
+					self.out.push_str(&format!("[{}{tag}]", if end { "/" } else { "" }));
+				}
+			}
+			("img", _) => {
+				if let Some(src) = attr("src") {
+					self.image(&src);
+				}
+			}
+			("span", false) => {
+				let style = attr("style").unwrap_or_default();
+				let mut close = String::new();
+
+				for decl in style.split(';') {
+					let Some((prop, value)) = decl.split_once(':') else { continue };
+					let value = value.trim();
+
+					let tag = match prop.trim().to_ascii_lowercase().as_str() {
+						"text-align" if self.dialect.align && matches!(value, "left" | "center" | "right") => {
+							self.out.push_str(&format!("[{value}]"));
+							value.to_string()
+						}
+						"color" if self.dialect.color => {
+							self.out.push_str("[color=");
+							self.param(value);
+							self.out.push(']');
+							"color".to_string()
+						}
+						"font-size" => {
+							let Some(size) = self.dialect.size.and_then(|unit| unit.param_from_css(value)) else {
+								continue
+							};
+
+							self.out.push_str("[size=");
+							self.param(&size);
+							self.out.push(']');
+							"size".to_string()
+						}
+						_ => continue
+					};
+
+					close.insert_str(0, &format!("[/{tag}]"));
+				}
+
+				self.spans.push(close);
+			}
+			("span", true) => {
+				if let Some(close) = self.spans.pop() {
+					self.out.push_str(&close);
+				}
+			}
+			_ => { }
+		}
+	}
+
+	fn footnote_ref(&mut self, reference: &FootnoteRef) {
+		match self.definitions.reference_footnote(&reference.identifier) {
+			Some((index, _)) => self.out.push_str(&format!("({})", index + 1)),
+			None             => self.text(&format!("[^{}]", reference.identifier)),
+		}
+	}
+
+	fn footnote_section(&mut self, emit: &mut Emit) -> io::Result<()> {
+		if self.definitions.footnote(0).is_none() {
+			return Ok(())
+		}
+
+		self.out.push_str("\n\n");
+		self.out.push_str(&self.dialect.rule);
+
+		let mut i = 0;
+
+		while let Some((_, def, _)) = self.definitions.footnote(i) {
+			self.out.push_str(&format!("\n({}) ", i + 1));
+			self.blocks(&def.children, "\n");
+			self.flush(emit)?;
+			i += 1;
+		}
+
+		Ok(())
+	}
+}
@@ -17,59 +17,91 @@
 mod node_traits;
 pub(crate) use node_traits::*;
 
-use std::assert_matches::assert_matches;
+use std::assert_matches;
+use std::borrow::Cow;
+#[cfg(feature = "markdown")]
 use std::collections::HashMap;
 
-use markdown::mdast::*;
-
+use crate::tmast::*;
 use crate::TmDoc;
 
-pub trait BuildFn<N : AsNode, E> = FnOnce(NodeBuilder<N>) -> Result<NodeBuilder<N>, E>;
+pub trait BuildFn<N, E> = FnOnce(NodeBuilder<N>) -> Result<NodeBuilder<N>, E>;
 
 /// Populates a [NodeBuilder].
-pub trait Populate<N : AsNode, E> {
+pub trait Populate<N, E> {
 	/// Returns a populated [NodeBuilder] wrapped in a [Result].
 	fn populate(self) -> Result<NodeBuilder<N>, E>;
 }
 
-impl<N : AsNode, E, P> Populate<N, E> for P where P : FnOnce() -> Result<NodeBuilder<N>, E> {
+impl<N, E, P> Populate<N, E> for P where P : FnOnce() -> Result<NodeBuilder<N>, E> {
 	fn populate(self) -> Result<NodeBuilder<N>, E> { self() }
 }
 
-pub struct NodeBuilder<N : AsNode> { node: N }
+pub struct NodeBuilder<N> { node: N }
 
-impl NodeBuilder<Root> {
-	fn build_fake_root<E>(build: impl BuildFn<Root, E>) -> Result<TmDoc, E> {
+impl<'t> NodeBuilder<Root<'t>> {
+	#[cfg(feature = "markdown")]
+	fn build_fake_root<E>(build: impl BuildFn<Root<'t>, E>) -> Result<TmDoc<'t>, E> {
 		Ok(build(Self::default())?.build())
 	}
 
-	pub fn build(self) -> TmDoc {
-		TmDoc(self.node.as_node())
+	pub fn build(self) -> TmDoc<'t> {
+		TmDoc(self.node)
+	}
+}
+
+impl NodeBuilder<Align<'_>> {
+	pub fn set_align(mut self, align: AlignKind) -> Self {
+		self.node.align = align;
+		self
+	}
+}
+
+impl<'t> NodeBuilder<Code<'t>> {
+	pub fn set_lang<S : Into<Cow<'t, str>>>(mut self, lang: Option<S>) -> Self {
+		self.node.lang = lang.map(Into::into);
+		self
 	}
 }
 
-impl NodeBuilder<Code> {
-	pub fn set_lang(mut self, lang: Option<String>) -> Self {
-		self.node.lang = lang;
+impl<'t> NodeBuilder<Color<'t>> {
+	pub fn set_color(mut self, color: impl Into<Cow<'t, str>>) -> Self {
+		self.node.color = color.into();
 		self
 	}
 }
 
-impl NodeBuilder<Heading> {
+impl<'t> NodeBuilder<Embed<'t>> {
+	pub fn set_url(mut self, url: impl Into<Cow<'t, str>>) -> Self {
+		self.node.url = url.into();
+		self
+	}
+}
+
+impl<'t> NodeBuilder<FontSize<'t>> {
+	/// Sets the font size, as a CSS length.
+	pub fn set_size(mut self, size: impl Into<Cow<'t, str>>) -> Self {
+		self.node.size = size.into();
+		self
+	}
+}
+
+impl NodeBuilder<Heading<'_>> {
 	pub fn set_depth(mut self, depth: u8) -> Self {
-		assert_matches!(depth, 1..6);
+		assert_matches!(depth, 1..=6);
 
 		self.node.depth = depth;
 		self
 	}
 }
 
-impl NodeBuilder<Html> {
+impl<'t> NodeBuilder<Html<'t>> {
+	#[cfg(feature = "markdown")]
 	pub fn build_value<E>(
-		mut self,
+		self,
 		tag: &str,
 		params: HashMap<&str, &str>,
-		build_inner: impl BuildFn<Root, E>
+		build_inner: impl BuildFn<Root<'t>, E>
 	) -> Result<Self, E> {
 		let fake_root = NodeBuilder::build_fake_root(build_inner)?;
 		let inner     = fake_root.to_md_text();
@@ -105,7 +137,7 @@ impl NodeBuilder<Html> {
 	}
 }
 
-impl NodeBuilder<List> {
+impl<'t> NodeBuilder<List<'t>> {
 	pub fn set_ordered(mut self, ordered: bool) -> Self {
 		self.node.ordered = ordered;
 		self
@@ -121,17 +153,12 @@ impl NodeBuilder<List> {
 		self
 	}
 
-	fn append(mut self, node: impl AsNode) -> Self {
-		self.node.children.push(node.as_node());
-		self
-	}
-
-	pub fn item<E>(self, build: impl BuildFn<ListItem, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_list_item())?.node()))
+	pub fn item<E>(self, build: impl BuildFn<ListItem<'t>, E>) -> Result<Self, E> {
+		Ok(ParentNodeBuilder::append(self, build(NodeBuilder::default())?.node()))
 	}
 }
 
-impl NodeBuilder<ListItem> {
+impl NodeBuilder<ListItem<'_>> {
 	pub fn set_spread(mut self, spread: bool) -> Self {
 		self.node.spread = spread;
 		self
@@ -143,513 +170,302 @@ impl NodeBuilder<ListItem> {
 	}
 }
 
-impl NodeBuilder<Table> {
+impl<'t> NodeBuilder<Quote<'t>> {
+	pub fn set_author<S : Into<Cow<'t, str>>>(mut self, author: Option<S>) -> Self {
+		self.node.author = author.map(Into::into);
+		self
+	}
+}
+
+impl<'t> NodeBuilder<Spoiler<'t>> {
+	pub fn set_summary<S : Into<Cow<'t, str>>>(mut self, summary: Option<S>) -> Self {
+		self.node.summary = summary.map(Into::into);
+		self
+	}
+}
+
+impl<'t> NodeBuilder<Table<'t>> {
 	pub fn align_column(mut self, alignment: AlignKind) -> Self {
 		self.node.align.push(alignment);
 		self
 	}
 
-	pub fn row<E>(self, build: impl BuildFn<TableRow, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_table_row())?.node()))
+	pub fn row<E>(self, build: impl BuildFn<TableRow<'t>, E>) -> Result<Self, E> {
+		Ok(ParentNodeBuilder::append(self, build(NodeBuilder::default())?.node()))
 	}
 }
 
-impl NodeBuilder<TableRow> {
-	pub fn row<E>(self, build: impl BuildFn<TableCell, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_table_cell())?.node()))
+impl<'t> NodeBuilder<TableRow<'t>> {
+	pub fn cell<E>(self, build: impl BuildFn<TableCell<'t>, E>) -> Result<Self, E> {
+		Ok(ParentNodeBuilder::append(self, build(NodeBuilder::default())?.node()))
 	}
 }
 
-impl<N : AsNode> NodeBuilder<N> {
+impl<N> NodeBuilder<N> {
 	pub fn node(self) -> N { self.node }
 }
 
-impl<N : BlockNode> NodeBuilder<N> {
-	pub fn append(mut self, node: impl AsNode) -> Self {
-		self.node.append(node.as_node());
+impl<N : unist::Node> NodeBuilder<N> {
+	pub fn set_position(mut self, position: Option<unist::Position>) -> Self {
+		self.node.set_position(position);
 		self
 	}
+}
+
+impl<'t, N : BlockNode<'t>> NodeBuilder<N> {
+	/// Appends a node. Flow content appended to a node that only accepts phrasing
+	/// content is flattened, and phrasing content appended to a node that only
+	/// accepts flow content is added to its last paragraph, or a new one.
+	pub fn append(mut self, node: impl Into<Content<'t>>) -> Self {
+		self.node.append(node.into());
+		self
+	}
+
+	pub fn align<E>(self, build: impl BuildFn<Align<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
 
-	pub fn block_quote<E>(self, build: impl BuildFn<BlockQuote, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_block_quote())?.node()))
+	pub fn block_quote<E>(self, build: impl BuildFn<Quote<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
 	pub fn line_break(self) -> Self {
-		self.append(new_break())
+		self.append(Break::new(None))
 	}
 
-	pub fn code<E>(self, build: impl BuildFn<Code, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_code())?.node()))
+	pub fn code<E>(self, build: impl BuildFn<Code<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn definition<E>(self, build: impl BuildFn<Definition, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_def())?.node()))
+	pub fn color<E>(self, build: impl BuildFn<Color<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn delete<E>(self, build: impl BuildFn<Delete, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_delete())?.node()))
+	pub fn definition<E>(self, build: impl BuildFn<Definition<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn emphasis<E>(self, build: impl BuildFn<Emphasis, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_emph())?.node()))
+	pub fn delete<E>(self, build: impl BuildFn<Delete<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn footnote_definition<E>(self, build: impl BuildFn<FootnoteDefinition, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_foot_def())?.node()))
+	pub fn embed<E>(self, build: impl BuildFn<Embed<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn footnote_reference<E>(self, build: impl BuildFn<FootnoteReference, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_foot_ref())?.node()))
+	pub fn emphasis<E>(self, build: impl BuildFn<Emphasis<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn heading<E>(self, build: impl BuildFn<Heading, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_heading())?.node()))
+	pub fn font_size<E>(self, build: impl BuildFn<FontSize<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn html<E>(self, build: impl BuildFn<Html, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_html())?.node()))
+	pub fn footnote_definition<E>(self, build: impl BuildFn<FootnoteDef<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn image<E>(self, build: impl BuildFn<Image, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_image())?.node()))
+	pub fn footnote_reference<E>(self, build: impl BuildFn<FootnoteRef<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn image_reference<E>(self, build: impl BuildFn<ImageReference, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_image_ref())?.node()))
+	pub fn heading<E>(self, build: impl BuildFn<Heading<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn inline_code<E>(self, build: impl BuildFn<InlineCode, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_inline_code())?.node()))
+	/// Appends inline HTML.
+	pub fn html<E>(self, build: impl BuildFn<Html<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn inline_math<E>(self, build: impl BuildFn<InlineMath, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_inline_math())?.node()))
+	/// Appends block HTML.
+	pub fn html_block<E>(self, build: impl BuildFn<Html<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(FlowContent::Html(build(NodeBuilder::default())?.node())))
 	}
 
-	pub fn link<E>(self, build: impl BuildFn<Link, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_link())?.node()))
+	pub fn image<E>(self, build: impl BuildFn<Image<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn link_reference<E>(self, build: impl BuildFn<LinkReference, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_link_ref())?.node()))
+	pub fn image_reference<E>(self, build: impl BuildFn<ImageReference<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn list<E>(self, build: impl BuildFn<List, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_list())?.node()))
+	pub fn inline_code<E>(self, build: impl BuildFn<InlineCode<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn math<E>(self, build: impl BuildFn<Math, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_math())?.node()))
+	pub fn inline_math<E>(self, build: impl BuildFn<InlineMath<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn paragraph<E>(self, build: impl BuildFn<Paragraph, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_para())?.node()))
+	pub fn link<E>(self, build: impl BuildFn<Link<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn strong<E>(self, build: impl BuildFn<Strong, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_strong())?.node()))
+	pub fn link_reference<E>(self, build: impl BuildFn<LinkReference<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn table<E>(self, build: impl BuildFn<Table, E>) -> Result<Self, E> {
-		Ok(self.append(build(new_table())?.node()))
+	pub fn list<E>(self, build: impl BuildFn<List<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 
-	pub fn text(self, value: String) -> Self {
-		self.append(new_text(value))
+	pub fn math<E>(self, build: impl BuildFn<Math<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn paragraph<E>(self, build: impl BuildFn<Paragraph<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn spoiler<E>(self, build: impl BuildFn<Spoiler<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn strong<E>(self, build: impl BuildFn<Strong<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn subscript<E>(self, build: impl BuildFn<Subscript<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn superscript<E>(self, build: impl BuildFn<Superscript<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	pub fn table<E>(self, build: impl BuildFn<Table<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
+	}
+
+	/// Appends text, borrowed from the source if given a `&str`.
+	pub fn text(self, value: impl Into<Cow<'t, str>>) -> Self {
+		self.append(Text::new(value, None))
 	}
 
 	pub fn thematic_break(self) -> Self {
-		self.append(new_them_break())
+		self.append(ThematicBreak::new(None))
+	}
+
+	pub fn underline<E>(self, build: impl BuildFn<Underline<'t>, E>) -> Result<Self, E> {
+		Ok(self.append(build(NodeBuilder::default())?.node()))
 	}
 }
 
 // Workaround for:
 // the type parameter `C` is not constrained by the impl trait, self type, or
 // predicates unconstrained type parameter
-pub trait ParentNodeBuilder<N : ParentNode<C>, C : AsNode> {
+pub trait ParentNodeBuilder<N : ParentNode<C>, C> {
 	fn append(self, node: C) -> Self;
 }
 
-impl<N : ParentNode<C>, C : AsNode> ParentNodeBuilder<N, C> for NodeBuilder<N> {
+impl<N : ParentNode<C>, C> ParentNodeBuilder<N, C> for NodeBuilder<N> {
 	fn append(mut self, node: C) -> Self {
 		self.node.append(node);
 		self
 	}
 }
 
-impl<N : TextNode> NodeBuilder<N> {
-	pub fn append_value(mut self, text: String) -> Self {
-		self.node.append_value(text);
+impl<'t, N : unist::Literal<'t>> NodeBuilder<N> {
+	pub fn append_value(mut self, text: &str) -> Self {
+		let value = self.node.value().to_string() + text;
+		self.node.set_value(value);
 		self
 	}
 
-	pub fn set_value(mut self, text: String) -> Self {
+	pub fn set_value(mut self, text: impl Into<Cow<'t, str>>) -> Self {
 		self.node.set_value(text);
 		self
 	}
 }
 
-impl<N : DefRefNode> NodeBuilder<N> {
-	pub fn set_id(mut self, id: String) -> Self {
-		self.node.set_id(id);
+impl<'t, N : Association<'t>> NodeBuilder<N> {
+	pub fn set_id(mut self, id: impl Into<Cow<'t, str>>) -> Self {
+		self.node.set_identifier(id);
 		self
 	}
 
-	pub fn set_label(mut self, label: Option<String>) -> Self {
-		self.node.set_label(label);
+	pub fn set_label<S : Into<Cow<'t, str>>>(mut self, label: Option<S>) -> Self {
+		self.node.set_label(label.map(Into::into));
 		self
 	}
 }
 
-impl<N : RefKindNode> NodeBuilder<N> {
+impl<'t, N : Reference<'t>> NodeBuilder<N> {
 	pub fn set_ref_kind(mut self, kind: ReferenceKind) -> Self {
-		self.node.set_ref_kind(kind);
+		self.node.set_reference_kind(kind);
 		self
 	}
 }
 
-impl<N : LinkNode> NodeBuilder<N> {
-	pub fn set_title_html(mut self, title: NodeBuilder<Html>) -> Self {
+impl<'t, N : Resource<'t>> NodeBuilder<N> {
+	pub fn set_title_html(self, title: NodeBuilder<Html<'t>>) -> Self {
 		self.set_title(Some(title.node.value))
 	}
 
-	pub fn set_title(mut self, title: Option<String>) -> Self {
-		self.node.set_title(title);
+	pub fn set_title<S : Into<Cow<'t, str>>>(mut self, title: Option<S>) -> Self {
+		self.node.set_title(title.map(Into::into));
 		self
 	}
 
-	pub fn set_url(mut self, url: String) -> Self {
+	pub fn set_url(mut self, url: impl Into<Cow<'t, str>>) -> Self {
 		self.node.set_url(url);
 		self
 	}
 }
 
-impl<N : AltNode> NodeBuilder<N> {
-	pub fn set_alt(mut self, alt: String) -> Self {
+impl<'t, N : Alternative<'t>> NodeBuilder<N> {
+	pub fn set_alt(mut self, alt: impl Into<Cow<'t, str>>) -> Self {
 		self.node.set_alt(alt);
 		self
 	}
 }
 
-impl Default for NodeBuilder<BlockQuote> {
-	fn default() -> Self { new_block_quote() }
-}
-
-impl Default for NodeBuilder<Break> {
-	fn default() -> Self { Self { node: new_break() } }
-}
-
-impl Default for NodeBuilder<Code> {
-	fn default() -> Self { new_code() }
-}
-
-impl Default for NodeBuilder<Definition> {
-	fn default() -> Self { new_def() }
-}
-
-impl Default for NodeBuilder<Delete> {
-	fn default() -> Self { new_delete() }
-}
-
-impl Default for NodeBuilder<Emphasis> {
-	fn default() -> Self { new_emph() }
-}
-
-impl Default for NodeBuilder<FootnoteDefinition> {
-	fn default() -> Self { new_foot_def() }
-}
-
-impl Default for NodeBuilder<Heading> {
-	fn default() -> Self { new_heading() }
-}
-
-impl Default for NodeBuilder<Html> {
-	fn default() -> Self { new_html() }
-}
-
-impl Default for NodeBuilder<Image> {
-	fn default() -> Self { new_image() }
-}
-
-impl Default for NodeBuilder<ImageReference> {
-	fn default() -> Self { new_image_ref() }
-}
-
-impl Default for NodeBuilder<InlineCode> {
-	fn default() -> Self { new_inline_code() }
-}
-
-impl Default for NodeBuilder<InlineMath> {
-	fn default() -> Self { new_inline_math() }
-}
-
-impl Default for NodeBuilder<Link> {
-	fn default() -> Self { new_link() }
-}
-
-impl Default for NodeBuilder<LinkReference> {
-	fn default() -> Self { new_link_ref() }
-}
-
-impl Default for NodeBuilder<List> {
-	fn default() -> Self { new_list() }
-}
-
-impl Default for NodeBuilder<ListItem> {
-	fn default() -> Self { new_list_item() }
-}
-
-impl Default for NodeBuilder<Math> {
-	fn default() -> Self { new_math() }
-}
-
-impl Default for NodeBuilder<Paragraph> {
-	fn default() -> Self { new_para() }
-}
-
-impl Default for NodeBuilder<Strong> {
-	fn default() -> Self { new_strong() }
-}
-
-impl Default for NodeBuilder<Root> {
-	fn default() -> Self {
-		Self { node: new_root() }
-	}
-}
-
-impl Default for NodeBuilder<Table> {
-	fn default() -> Self { new_table() }
-}
-
-impl Default for NodeBuilder<TableCell> {
-	fn default() -> Self { new_table_cell() }
-}
-
-impl Default for NodeBuilder<TableRow> {
-	fn default() -> Self { new_table_row() }
-}
-
-impl Default for NodeBuilder<Text> {
-	fn default() -> Self { Self { node: new_text(String::new()) } }
-}
-
-impl Default for NodeBuilder<ThematicBreak> {
-	fn default() -> Self { Self{ node: new_them_break() } }
-}
-
-fn new_root() -> Root {
-	Root { children: vec![], position: None }
-}
-
-fn new_block_quote() -> NodeBuilder<BlockQuote> {
-	NodeBuilder {
-		node: BlockQuote { children: vec![], position: None }
-	}
-}
-
-fn new_break() -> Break { Break { position: None } }
-
-fn new_code() -> NodeBuilder<Code> {
-	NodeBuilder {
-		node: Code {
-			value: String::new(),
-			position: None,
-			lang: None,
-			meta: None
-		}
-	}
-}
-
-fn new_def() -> NodeBuilder<Definition> {
-	NodeBuilder {
-		node: Definition {
-			position: None,
-			url: String::new(),
-			title: None,
-			identifier: String::new(),
-			label: None
-		}
-	}
-}
-
-fn new_delete() -> NodeBuilder<Delete> {
-	NodeBuilder {
-		node: Delete { children: vec![], position: None }
-	}
-}
-
-fn new_emph() -> NodeBuilder<Emphasis> {
-	NodeBuilder {
-		node: Emphasis { children: vec![], position: None }
-	}
-}
-
-fn new_foot_def() -> NodeBuilder<FootnoteDefinition> {
-	NodeBuilder {
-		node: FootnoteDefinition {
-			children: vec![],
-			position: None,
-			identifier: String::new(),
-			label: None
-		}
-	}
-}
-
-fn new_foot_ref() -> NodeBuilder<FootnoteReference> {
-	NodeBuilder {
-		node: FootnoteReference {
-			position: None,
-			identifier: String::new(),
-			label: None
-		}
-	}
-}
-
-fn new_heading() -> NodeBuilder<Heading> {
-	NodeBuilder {
-		node: Heading {
-			children: vec![],
-			position: None,
-			depth: 1
-		}
-	}
-}
-
-fn new_html() -> NodeBuilder<Html> {
-	NodeBuilder {
-		node: Html { value: String::new(), position: None }
-	}
-}
-
-fn new_image() -> NodeBuilder<Image> {
-	NodeBuilder {
-		node: Image {
-			position: None,
-			alt: String::new(),
-			url: String::new(),
-			title: None
-		}
-	}
-}
-
-fn new_image_ref() -> NodeBuilder<ImageReference> {
-	NodeBuilder {
-		node: ImageReference {
-			position: None,
-			alt: String::new(),
-			reference_kind: ReferenceKind::Shortcut,
-			identifier: String::new(),
-			label: None
-		}
-	}
-}
-
-fn new_inline_code() -> NodeBuilder<InlineCode> {
-	NodeBuilder {
-		node: InlineCode { value: String::new(), position: None }
-	}
-}
-
-fn new_inline_math() -> NodeBuilder<InlineMath> {
-	NodeBuilder {
-		node: InlineMath { value: String::new(), position: None }
-	}
-}
-
-fn new_link() -> NodeBuilder<Link> {
-	NodeBuilder {
-		node: Link {
-			children: vec![],
-			position: None,
-			url: String::new(),
-			title: None
-		}
-	}
-}
-
-fn new_link_ref() -> NodeBuilder<LinkReference> {
-	NodeBuilder {
-		node: LinkReference {
-			children: vec![],
-			position: None,
-			reference_kind: ReferenceKind::Shortcut,
-			identifier: String::new(),
-			label: None
-		}
-	}
-}
-
-fn new_list() -> NodeBuilder<List> {
-	NodeBuilder {
-		node: List {
-			children: vec![],
-			position: None,
-			ordered: false,
-			start: None,
-			spread: false
-		}
-	}
-}
-
-fn new_list_item() -> NodeBuilder<ListItem> {
-	NodeBuilder {
-		node: ListItem {
-			children: vec![],
-			position: None,
-			spread: false,
-			checked: None
-		}
-	}
-}
-
-fn new_math() -> NodeBuilder<Math> {
-	NodeBuilder {
-		node: Math {
-			value: String::new(),
-			position: None,
-			meta: None
-		}
-	}
-}
-
-fn new_para() -> NodeBuilder<Paragraph> {
-	NodeBuilder {
-		node: Paragraph { children: vec![], position: None }
-	}
-}
-
-fn new_strong() -> NodeBuilder<Strong> {
-	NodeBuilder {
-		node: Strong { children: vec![], position: None }
-	}
-}
-
-fn new_table() -> NodeBuilder<Table> {
-	NodeBuilder {
-		node: Table {
-			children: vec![],
-			position: None,
-			align: vec![]
-		}
-	}
-}
-
-fn new_table_cell() -> NodeBuilder<TableCell> {
-	NodeBuilder {
-		node: TableCell { children: vec![], position: None }
-	}
-}
-
-fn new_table_row() -> NodeBuilder<TableRow> {
-	NodeBuilder {
-		node: TableRow { children: vec![], position: None }
-	}
-}
-
-fn new_text(value: String) -> Text {
-	Text { value, position: None }
-}
-
-fn new_them_break() -> ThematicBreak {
-	ThematicBreak { position: None }
+macro_rules! impl_default {
+	($($name:ty => $node:expr;)+) => {
+		$(
+			impl Default for NodeBuilder<$name> {
+				fn default() -> Self { Self { node: $node } }
+			}
+		)+
+	};
+}
+
+impl_default! {
+	Align<'_>          => Align::new(AlignKind::None, vec![], None);
+	Break              => Break::new(None);
+	Code<'_>           => Code::new("", None, None, None);
+	Color<'_>          => Color::new("", vec![], None);
+	Definition<'_>     => Definition::new("", None, "", None, None);
+	Delete<'_>         => Delete::new(vec![], None);
+	Embed<'_>          => Embed::new("", None);
+	Emphasis<'_>       => Emphasis::new(vec![], None);
+	FontSize<'_>       => FontSize::new("", vec![], None);
+	FootnoteDef<'_>    => FootnoteDef::new("", None, vec![], None);
+	FootnoteRef<'_>    => FootnoteRef::new("", None, None);
+	Heading<'_>        => Heading::new(1, vec![], None);
+	Html<'_>           => Html::new("", None);
+	Image<'_>          => Image::new("", "", None, None);
+	ImageReference<'_> => ImageReference::new("", "", None, ReferenceKind::Shortcut, None);
+	InlineCode<'_>     => InlineCode::new("", None);
+	InlineMath<'_>     => InlineMath::new("", None);
+	Link<'_>           => Link::new("", None, vec![], None);
+	LinkReference<'_>  => LinkReference::new("", None, ReferenceKind::Shortcut, vec![], None);
+	List<'_>           => List::new(false, None, false, vec![], None);
+	ListItem<'_>       => ListItem::new(false, None, vec![], None);
+	Math<'_>           => Math::new("", None, None);
+	Paragraph<'_>      => Paragraph::new(vec![], None);
+	Quote<'_>          => Quote::new(None, vec![], None);
+	Root<'_>           => Root::default();
+	Spoiler<'_>        => Spoiler::new(None, vec![], None);
+	Strong<'_>         => Strong::new(vec![], None);
+	Subscript<'_>      => Subscript::new(vec![], None);
+	Superscript<'_>    => Superscript::new(vec![], None);
+	Table<'_>          => Table::new(vec![], vec![], None);
+	TableCell<'_>      => TableCell::new(vec![], None);
+	TableRow<'_>       => TableRow::new(vec![], None);
+	Text<'_>           => Text::new("", None);
+	ThematicBreak      => ThematicBreak::new(None);
+	Underline<'_>      => Underline::new(vec![], None);
 }
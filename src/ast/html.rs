@@ -0,0 +1,396 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! HTML support, reading a [tl] DOM into the common AST and writing the common
+//! AST back out as HTML. Reading requires the `html` feature; the writer is also
+//! built with `bbcode` and `markdown`, which use it for tables Markdown can't
+//! express.
+
+#[cfg(feature = "html")]
+mod reader;
+mod writer;
+
+use std::io;
+
+#[cfg(feature = "html")]
+use tl::VDom;
+
+use crate::TmDoc;
+use crate::util::{collect, stream};
+
+#[cfg(feature = "html")]
+use reader::Reader;
+use writer::Writer;
+
+/// Reads a parsed HTML document into a [TmDoc]. Elements without a Markdown
+/// equivalent are kept as raw HTML nodes.
+#[cfg(feature = "html")]
+pub fn parse<'d>(dom: &VDom<'d>) -> TmDoc<'d> {
+	Reader::new(dom.parser()).read(dom.children())
+}
+
+/// Writes a [TmDoc] as an HTML fragment. Text and attributes are escaped, while
+/// raw HTML nodes are written as-is.
+pub fn write(doc: &TmDoc) -> String {
+	collect(|emit| Writer::default().write(&doc.0, emit))
+}
+
+/// Writes a [TmDoc] as HTML to `out`, as [write] does, without building the whole
+/// document in memory.
+pub fn write_to(doc: &TmDoc, out: &mut (impl io::Write + ?Sized)) -> io::Result<()> {
+	stream(out, |emit| Writer::default().write(&doc.0, emit))
+}
+
+/// Protocols allowed in link and image URLs. Anything else, such as `javascript:`,
+/// is dropped.
+pub(crate) const SAFE_PROTOCOLS: &[&str] = &["http", "https", "mailto", "irc", "ircs", "xmpp"];
+
+/// Returns the URL if it's relative or its protocol is allowed, or an empty string
+/// otherwise.
+pub(crate) fn sanitize_url<'u>(url: &'u str, protocols: &[&str]) -> &'u str {
+	let scheme = url
+		.find([':', '/', '?', '#'])
+		.filter(|&i| url[i..].starts_with(':'))
+		.map(|i| &url[..i]);
+
+	match scheme {
+		Some(scheme) if !protocols.iter().any(|p| p.eq_ignore_ascii_case(scheme)) => "",
+		_ => url,
+	}
+}
+
+#[cfg(all(test, feature = "html"))]
+mod tests {
+	use crate::tmast::*;
+	use crate::{IntoHtmlText, TmDoc};
+
+	fn parse(html: &str) -> Vec<Content<'_>> {
+		TmDoc::parse_html(html).expect("parse failed").0.children
+	}
+
+	fn write(children: Vec<Content>) -> String {
+		TmDoc(Root::new(children, None)).into_html_text()
+	}
+
+	fn text(value: &str) -> PhrasingContent<'_> {
+		Text::new(value, None).into()
+	}
+
+	fn link_text(value: &str) -> StaticPhrasingContent<'_> {
+		Text::new(value, None).into()
+	}
+
+	fn para(children: Vec<PhrasingContent>) -> FlowContent {
+		Paragraph::new(children, None).into()
+	}
+
+	fn html(value: &str) -> Html<'_> {
+		Html::new(value, None)
+	}
+
+	#[test]
+	fn document() {
+		let nodes = parse(r#"
+<!DOCTYPE html>
+<html>
+	<head><title>Ignored</title></head>
+	<body>
+		<h1>Title!</h1>
+
+		<p>Some <i>meaningful</i> text.</p>
+	</body>
+</html>
+"#);
+
+		assert_eq!(nodes, vec![
+			FlowContent::from(Heading::new(1, vec![text("Title!")], None)).into(),
+			para(vec![
+				text("Some "),
+				Emphasis::new(vec![text("meaningful")], None).into(),
+				text(" text."),
+			]).into(),
+		]);
+	}
+
+	#[test]
+	fn phrasing() {
+		let nodes = parse(
+			r#"<p><b>a</b> <strong>b</strong><br>
+			<a href="https://example.com/?a=1&amp;b=2" title="Example">link</a>
+			<img src="cat.png" alt="A cat"> <code>x &lt; y</code> <del>no</del></p>"#
+		);
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				Strong::new(vec![text("a")], None).into(),
+				text(" "),
+				Strong::new(vec![text("b")], None).into(),
+				Break::new(None).into(),
+				Link::new("https://example.com/?a=1&b=2", Some("Example".into()), vec![link_text("link")], None).into(),
+				text(" "),
+				Image::new("A cat", "cat.png", None, None).into(),
+				text(" "),
+				InlineCode::new("x < y", None).into(),
+				text(" "),
+				Delete::new(vec![text("no")], None).into(),
+			]).into()
+		]);
+	}
+
+	#[test]
+	fn loose_text() {
+		let nodes = parse("Loose <b>text</b><hr>More &amp; more");
+
+		assert_eq!(nodes, vec![
+			para(vec![
+				text("Loose "),
+				Strong::new(vec![text("text")], None).into(),
+			]).into(),
+			FlowContent::from(ThematicBreak::new(None)).into(),
+			para(vec![text("More & more")]).into(),
+		]);
+	}
+
+	#[test]
+	fn non_breaking_spaces() {
+		let nodes = parse("<p>&nbsp;a&nbsp; \n b&nbsp;</p>");
+
+		assert_eq!(nodes, vec![para(vec![text("\u{A0}a\u{A0} b\u{A0}")]).into()]);
+	}
+
+	#[test]
+	fn lists() {
+		let nodes = parse(r#"
+<ol start="3">
+	<li>Three</li>
+	<li><input type="checkbox" checked> Four</li>
+</ol>
+<ul><li><input type="checkbox"> Todo</li></ul>
+"#);
+
+		let item = |checked, value| ListItem::new(false, checked, vec![para(vec![text(value)])], None);
+
+		assert_eq!(nodes, vec![
+			FlowContent::from(List::new(true, Some(3), false, vec![item(None, "Three"), item(Some(true), "Four")], None)).into(),
+			FlowContent::from(List::new(false, None, false, vec![item(Some(false), "Todo")], None)).into(),
+		]);
+	}
+
+	#[test]
+	fn code_and_quotes() {
+		let nodes = parse(
+			"<pre><code class=\"language-rust\">fn main() {\n\tprintln!(\"&lt;3\");\n}\n</code></pre>\
+			<blockquote><p>Quoted</p></blockquote>"
+		);
+
+		assert_eq!(nodes, vec![
+			FlowContent::from(Code::new("fn main() {\n\tprintln!(\"<3\");\n}", Some("rust".into()), None, None)).into(),
+			FlowContent::from(Quote::new(None, vec![para(vec![text("Quoted")])], None)).into(),
+		]);
+	}
+
+	#[test]
+	fn table() {
+		let nodes = parse(r#"
+<table>
+	<thead><tr><th align="left">A</th><th style="text-align: center">B</th><th>C</th></tr></thead>
+	<tbody><tr><td>1</td><td>2</td><td>3</td></tr></tbody>
+</table>
+"#);
+
+		let row = |values: [&'static str; 3]| TableRow::new(
+			values.into_iter().map(|value| TableCell::new(vec![text(value)], None)).collect(),
+			None
+		);
+
+		assert_eq!(nodes, vec![
+			FlowContent::from(
+				Table::new(
+					vec![AlignKind::Left, AlignKind::Center, AlignKind::None],
+					vec![row(["A", "B", "C"]), row(["1", "2", "3"])],
+					None
+				)
+			).into()
+		]);
+	}
+
+	#[test]
+	fn fallback() {
+		let nodes = parse(r#"<div class="note">Note</div><p>A <span>span</span></p>"#);
+
+		assert_eq!(nodes, vec![
+			FlowContent::Html(html(r#"<div class="note">Note</div>"#)).into(),
+			para(vec![text("A "), StaticPhrasingContent::from(html("<span>span</span>")).into()]).into(),
+		]);
+	}
+
+	#[test]
+	fn formatting() {
+		let nodes = parse(
+			r#"<div style="text-align: right"><p>Right</p></div>
+			<details><summary> The  plot </summary><p>Hidden</p></details>
+			<p><u>a</u><span style="color: red; font-size: 2em">b</span><sup>c</sup>
+			<iframe src="https://www.youtube.com/embed/abc"></iframe><iframe src="https://example.com"></iframe></p>"#
+		);
+
+		assert_eq!(nodes, vec![
+			FlowContent::from(Align::new(AlignKind::Right, vec![para(vec![text("Right")])], None)).into(),
+			FlowContent::from(Spoiler::new(Some("The plot".into()), vec![para(vec![text("Hidden")])], None)).into(),
+			para(vec![
+				Underline::new(vec![text("a")], None).into(),
+				Color::new("red", vec![FontSize::new("2em", vec![text("b")], None).into()], None).into(),
+				Superscript::new(vec![text("c")], None).into(),
+				text(" "),
+				Embed::new("https://youtube.com/watch?v=abc", None).into(),
+				StaticPhrasingContent::from(html(r#"<iframe src="https://example.com"></iframe>"#)).into(),
+			]).into(),
+		]);
+	}
+
+	#[test]
+	fn write_blocks() {
+		assert_eq!(
+			write(vec![
+				FlowContent::from(Heading::new(1, vec![text("Title")], None)).into(),
+				para(vec![
+					text("Some "),
+					Emphasis::new(vec![text("meaningful")], None).into(),
+					text(" "),
+					Strong::new(vec![text("text")], None).into(),
+					text(" & "),
+					InlineCode::new("x < y", None).into(),
+					text("."),
+				]).into(),
+				FlowContent::from(Quote::new(None, vec![para(vec![text("Quoted")])], None)).into(),
+				FlowContent::from(ThematicBreak::new(None)).into(),
+				FlowContent::from(Code::new("fn main() {}", Some("rust".into()), None, None)).into(),
+			]),
+			"<h1>Title</h1>\n\
+			<p>Some <em>meaningful</em> <strong>text</strong> &amp; <code>x &lt; y</code>.</p>\n\
+			<blockquote>\n<p>Quoted</p>\n</blockquote>\n\
+			<hr />\n\
+			<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+		);
+	}
+
+	#[test]
+	fn write_lists() {
+		let item = |checked, value| ListItem::new(false, checked, vec![para(vec![text(value)])], None);
+
+		assert_eq!(
+			write(vec![
+				FlowContent::from(List::new(true, Some(3), false, vec![item(None, "Three"), item(None, "Four")], None)).into(),
+				FlowContent::from(List::new(false, None, false, vec![item(Some(true), "Done"), item(Some(false), "Todo")], None)).into(),
+				FlowContent::from(List::new(false, None, true, vec![item(None, "Loose"), item(None, "List")], None)).into(),
+			]),
+			"<ol start=\"3\">\n<li>Three</li>\n<li>Four</li>\n</ol>\n\
+			<ul>\n\
+			<li><input type=\"checkbox\" disabled=\"\" checked=\"\" /> Done</li>\n\
+			<li><input type=\"checkbox\" disabled=\"\" /> Todo</li>\n\
+			</ul>\n\
+			<ul>\n<li>\n<p>Loose</p>\n</li>\n<li>\n<p>List</p>\n</li>\n</ul>\n"
+		);
+	}
+
+	#[test]
+	fn write_table() {
+		let row = |values: [&'static str; 3]| TableRow::new(
+			values.into_iter().map(|value| TableCell::new(vec![text(value)], None)).collect(),
+			None
+		);
+
+		assert_eq!(
+			write(vec![
+				FlowContent::from(
+					Table::new(
+						vec![AlignKind::Left, AlignKind::Center, AlignKind::None],
+						vec![row(["A", "B", "C"]), row(["1", "2", "3"])],
+						None
+					)
+				).into()
+			]),
+			"<table>\n\
+			<thead>\n<tr>\n<th align=\"left\">A</th>\n<th align=\"center\">B</th>\n<th>C</th>\n</tr>\n</thead>\n\
+			<tbody>\n<tr>\n<td align=\"left\">1</td>\n<td align=\"center\">2</td>\n<td>3</td>\n</tr>\n</tbody>\n\
+			</table>\n"
+		);
+	}
+
+	#[test]
+	fn write_links() {
+		assert_eq!(
+			write(vec![
+				para(vec![
+					Link::new("https://example.com", Some("Ex".into()), vec![link_text("a")], None).into(),
+					text(" "),
+					LinkReference::new("ref", Some("ref".into()), ReferenceKind::Full, vec![link_text("b")], None).into(),
+					text(" "),
+					Image::new("c", "cat.png", None, None).into(),
+					text(" "),
+					Link::new("javascript:alert(1)", None, vec![link_text("d")], None).into(),
+				]).into(),
+				FlowContent::from(TextContent::from(Definition::new("/page?a=1&b=2", None, "ref", Some("ref".into()), None))).into(),
+			]),
+			"<p><a href=\"https://example.com\" title=\"Ex\">a</a> \
+			<a href=\"/page?a=1&amp;b=2\">b</a> \
+			<img src=\"cat.png\" alt=\"c\" /> \
+			<a href=\"\">d</a></p>\n"
+		);
+	}
+
+	#[test]
+	fn write_math() {
+		assert_eq!(
+			write(vec![
+				para(vec![InlineMath::new("x", None).into()]).into(),
+				FlowContent::from(Math::new("y^2", None, None)).into(),
+			]),
+			"<p><code class=\"language-math math-inline\">x</code></p>\n\
+			<pre><code class=\"language-math math-display\">y^2\n</code></pre>\n"
+		);
+	}
+
+	#[test]
+	fn write_footnotes() {
+		let reference = || FootnoteRef::new("1", Some("1".into()), None).into();
+
+		assert_eq!(
+			write(vec![
+				para(vec![text("A"), reference(), text(" b"), reference(), text(".")]).into(),
+				FlowContent::from(FootnoteDef::new("1", Some("1".into()), vec![para(vec![text("Note.")])], None)).into(),
+			]),
+			"<p>A<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">1</a></sup> \
+			b<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1-2\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">1</a></sup>.</p>\n\
+			<section data-footnotes=\"\" class=\"footnotes\"><h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>\n\
+			<ol>\n\
+			<li id=\"user-content-fn-1\">\n\
+			<p>Note. \
+			<a href=\"#user-content-fnref-1\" data-footnote-backref=\"\" aria-label=\"Back to reference 1\" class=\"data-footnote-backref\">↩</a> \
+			<a href=\"#user-content-fnref-1-2\" data-footnote-backref=\"\" aria-label=\"Back to reference 1-2\" class=\"data-footnote-backref\">↩<sup>2</sup></a></p>\n\
+			</li>\n\
+			</ol>\n\
+			</section>\n"
+		);
+	}
+
+	#[test]
+	fn round_trip() {
+		let html = "<h2>Title</h2>\n<p>A <a href=\"https://example.com\">link</a> &amp; <code>code</code></p>\n";
+		let doc = TmDoc::parse_html(html).expect("parse failed");
+
+		assert_eq!(doc.into_html_text(), html);
+	}
+}
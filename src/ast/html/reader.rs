@@ -0,0 +1,525 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::borrow::Cow;
+use std::mem::take;
+use std::result::Result as StdResult;
+use std::slice;
+
+use regex_macro::regex;
+use tl::{Bytes, HTMLTag, Node as HtmlNode, NodeHandle, Parser};
+
+use crate::TmDoc;
+use crate::ast::{BlockNode, NodeBuilder};
+use crate::tmast::*;
+use crate::util::{decode_entities, map_cow, youtube_id};
+
+type Result<T> = StdResult<T, !>;
+
+/// Block-level elements without a Markdown equivalent. These are kept as raw HTML
+/// blocks rather than being wrapped in a paragraph.
+const HTML_BLOCKS: &[&str] = &[
+	"address", "article", "aside", "details", "dialog", "div", "dl", "fieldset",
+	"figcaption", "figure", "footer", "form", "header", "hgroup", "main", "nav",
+	"section", "script", "style", "template",
+];
+
+pub(super) struct Reader<'p, 'd> {
+	parser: &'p Parser<'d>,
+}
+
+impl<'p, 'd> Reader<'p, 'd> {
+	pub fn new(parser: &'p Parser<'d>) -> Self {
+		Self { parser }
+	}
+
+	pub fn read(&self, children: &[NodeHandle]) -> TmDoc<'d> {
+		let Ok(root) = self.flow(NodeBuilder::<Root>::default(), children);
+		let mut root = root.node();
+
+		for child in &mut root.children {
+			if let Content::Flow(node) = child {
+				normalize(node);
+			}
+		}
+
+		root.children.retain(|child| !is_empty_paragraph(child));
+		TmDoc(root)
+	}
+
+	fn nodes<'c>(&self, children: &'c [NodeHandle]) -> impl Iterator<Item = &'p HtmlNode<'d>> + 'c
+	where 'p : 'c {
+		children.iter().filter_map(|handle| handle.get(self.parser))
+	}
+
+	/// Appends flow content, wrapping runs of phrasing content in paragraphs.
+	fn flow<N : BlockNode<'d>>(
+		&self,
+		mut builder: NodeBuilder<N>,
+		children: &[NodeHandle]
+	) -> Result<NodeBuilder<N>> {
+		let mut para: Option<NodeBuilder<Paragraph>> = None;
+
+		for node in self.nodes(children) {
+			let tag = node.as_tag().map(|tag| (tag, tag_name(tag)));
+
+			match tag {
+				Some((_, name)) if name == "head" => { }
+				Some((tag, name)) if name == "html" || name == "body" => {
+					builder = flush(builder, para.take());
+					builder = self.flow(builder, &children_of(tag))?;
+				}
+				Some((tag, name)) if is_flow(&name) => {
+					builder = flush(builder, para.take());
+					builder = self.flow_tag(builder, tag, &name)?;
+				}
+				_ => {
+					para = Some(self.phrasing_node(para.unwrap_or_default(), node)?);
+				}
+			}
+		}
+
+		Ok(flush(builder, para))
+	}
+
+	fn flow_tag<N : BlockNode<'d>>(
+		&self,
+		builder: NodeBuilder<N>,
+		tag: &HTMLTag<'d>,
+		name: &str
+	) -> Result<NodeBuilder<N>> {
+		let children = &children_of(tag);
+
+		match name {
+			"h1" | "h2" | "h3" |
+			"h4" | "h5" | "h6" => {
+				let depth = name[1..].parse().unwrap();
+				builder.heading(|nb| self.phrasing(nb.set_depth(depth), children))
+			}
+			"blockquote" => builder.block_quote(|nb| self.flow(nb, children)),
+			"details"    => builder.spoiler(|nb| self.spoiler(nb, tag)),
+			"div" if alignment(tag) != AlignKind::None =>
+				builder.align(|nb| self.flow(nb.set_align(alignment(tag)), children)),
+			"hr"         => Ok(builder.thematic_break()),
+			"ol"         => builder.list(|nb| self.list(nb, tag, true)),
+			"p"          => builder.paragraph(|nb| self.phrasing(nb, children)),
+			"pre"        => builder.code(|nb| Ok(self.code_block(nb, tag))),
+			"table"      => builder.table(|nb| self.table(nb, tag)),
+			"ul"         => builder.list(|nb| self.list(nb, tag, false)),
+			_            => Ok(self.raw_block(builder, &HtmlNode::Tag(tag.clone()))),
+		}
+	}
+
+	fn phrasing<N : BlockNode<'d>>(
+		&self,
+		mut builder: NodeBuilder<N>,
+		children: &[NodeHandle]
+	) -> Result<NodeBuilder<N>> {
+		for node in self.nodes(children) {
+			builder = self.phrasing_node(builder, node)?;
+		}
+
+		Ok(builder)
+	}
+
+	fn phrasing_node<N : BlockNode<'d>>(
+		&self,
+		builder: NodeBuilder<N>,
+		node: &HtmlNode<'d>
+	) -> Result<NodeBuilder<N>> {
+		let tag = match node {
+			HtmlNode::Raw(text) => {
+				let text = map_cow(map_cow(utf8(text), decode_entities), collapse_whitespace);
+
+				return Ok(
+					if text.is_empty() {
+						builder
+					} else {
+						builder.text(text)
+					}
+				)
+			}
+			HtmlNode::Comment(_) => return Ok(self.raw(builder, node)),
+			HtmlNode::Tag(tag) => tag
+		};
+
+		let children = &children_of(tag);
+
+		match tag_name(tag).as_str() {
+			"a" if attr(tag, "href").is_some() => builder.link(|nb| {
+				let nb = nb.set_url(attr(tag, "href").unwrap_or_default())
+						   .set_title(attr(tag, "title"));
+				self.phrasing(nb, children)
+			}),
+			"b" | "strong"          => builder.strong  (|nb| self.phrasing(nb, children)),
+			"br"                    => Ok(builder.line_break()),
+			"code"                  => builder.inline_code(|nb| Ok(nb.set_value(self.text_of(tag)))),
+			"del" | "s" | "strike"  => builder.delete  (|nb| self.phrasing(nb, children)),
+			"em" | "i"              => builder.emphasis(|nb| self.phrasing(nb, children)),
+			"iframe"                => {
+				let Some(id) = attr(tag, "src").as_deref().and_then(youtube_id).map(str::to_string) else {
+					return Ok(self.raw(builder, node))
+				};
+
+				builder.embed(|nb| Ok(nb.set_url(format!("https://youtube.com/watch?v={id}"))))
+			}
+			"img"                   => builder.image(|nb|
+				Ok(
+					nb.set_url(attr(tag, "src").unwrap_or_default())
+					  .set_alt(attr(tag, "alt").unwrap_or_default())
+					  .set_title(attr(tag, "title"))
+				)
+			),
+			"span"                  => {
+				let style = attr(tag, "style").unwrap_or_default();
+				let color = style_value(&style, "color");
+				let size  = style_value(&style, "font-size");
+
+				match (color, size) {
+					(Some(color), Some(size)) => builder.color(|nb|
+						nb.set_color(color).font_size(|nb| self.phrasing(nb.set_size(size), children))
+					),
+					(Some(color), None) => builder.color    (|nb| self.phrasing(nb.set_color(color), children)),
+					(None, Some(size))  => builder.font_size(|nb| self.phrasing(nb.set_size(size), children)),
+					(None, None)        => Ok(self.raw(builder, node)),
+				}
+			}
+			"sub"                   => builder.subscript  (|nb| self.phrasing(nb, children)),
+			"sup"                   => builder.superscript(|nb| self.phrasing(nb, children)),
+			"u"                     => builder.underline  (|nb| self.phrasing(nb, children)),
+			_                       => Ok(self.raw(builder, node)),
+		}
+	}
+
+	fn code_block(&self, builder: NodeBuilder<Code<'d>>, pre: &HTMLTag<'d>) -> NodeBuilder<Code<'d>> {
+		// Code blocks are conventionally written as <pre><code class="language-*">,
+		// but a bare <pre> is also accepted.
+		let code = self.nodes(&children_of(pre))
+			.filter_map(HtmlNode::as_tag)
+			.find(|tag| tag_name(tag) == "code");
+		let lang = code
+			.and_then(|code| attr(code, "class"))
+			.and_then(|class|
+				class.split_whitespace()
+					 .find_map(|c|
+						 c.strip_prefix("language-")
+						  .or_else(|| c.strip_prefix("lang-"))
+					 )
+					 .map(String::from)
+			);
+		let mut value = self.text_of(code.unwrap_or(pre));
+
+		if value.ends_with('\n') {
+			value.pop();
+		}
+
+		builder.set_value(value).set_lang(lang)
+	}
+
+	/// Reads a `<details>` element, taking the summary from its `<summary>` child.
+	fn spoiler(&self, builder: NodeBuilder<Spoiler<'d>>, tag: &HTMLTag<'d>) -> Result<NodeBuilder<Spoiler<'d>>> {
+		let is_summary = |handle: &NodeHandle|
+			handle.get(self.parser)
+				  .and_then(HtmlNode::as_tag)
+				  .is_some_and(|tag| tag_name(tag) == "summary");
+		let (summary, children): (Vec<NodeHandle>, Vec<NodeHandle>) = children_of(tag).into_iter().partition(is_summary);
+		let summary = summary.first()
+			.and_then(|handle| handle.get(self.parser))
+			.and_then(HtmlNode::as_tag)
+			.map(|summary| collapse_whitespace(&self.text_of(summary)).trim_matches(HTML_WHITESPACE).to_string());
+
+		self.flow(builder.set_summary(summary), &children)
+	}
+
+	fn list(
+		&self,
+		mut builder: NodeBuilder<List<'d>>,
+		tag: &HTMLTag<'d>,
+		ordered: bool
+	) -> Result<NodeBuilder<List<'d>>> {
+		let start = ordered.then(||
+			attr(tag, "start")
+				.and_then(|start| start.trim().parse().ok())
+				.unwrap_or(1)
+		);
+
+		builder = builder.set_ordered(ordered)
+						 .set_start(start)
+						 .set_spread(false);
+
+		for handle in &children_of(tag) {
+			let Some(node) = handle.get(self.parser) else { continue };
+
+			builder = match node.as_tag() {
+				Some(li) if tag_name(li) == "li" => builder.item(|nb| self.list_item(nb, li))?,
+				// Stray content between items; only keep it if it's meaningful.
+				_ if is_blank(node) => builder,
+				_ => builder.item(|nb| self.flow(nb, slice::from_ref(handle)))?,
+			};
+		}
+
+		Ok(builder)
+	}
+
+	fn list_item(
+		&self,
+		mut builder: NodeBuilder<ListItem<'d>>,
+		li: &HTMLTag<'d>
+	) -> Result<NodeBuilder<ListItem<'d>>> {
+		let children = children_of(li);
+		let mut children = children.as_slice();
+
+		// Task list items start with a checkbox.
+		let first = children.iter().position(|handle|
+			handle.get(self.parser).is_some_and(|node| !is_blank(node))
+		);
+
+		if let Some(index) = first {
+			let checkbox = children[index]
+				.get(self.parser)
+				.and_then(HtmlNode::as_tag)
+				.filter(|tag|
+					tag_name(tag) == "input" &&
+					attr(tag, "type").is_some_and(|t| t.eq_ignore_ascii_case("checkbox"))
+				);
+
+			if let Some(checkbox) = checkbox {
+				builder = builder.set_checked(Some(checkbox.attributes().contains("checked")));
+				children = &children[index + 1..];
+			}
+		}
+
+		let spread = self.nodes(children)
+			.filter_map(HtmlNode::as_tag)
+			.filter(|tag| tag_name(tag) == "p")
+			.count() > 1;
+
+		self.flow(builder.set_spread(spread), children)
+	}
+
+	fn table(
+		&self,
+		mut builder: NodeBuilder<Table<'d>>,
+		tag: &HTMLTag<'d>
+	) -> Result<NodeBuilder<Table<'d>>> {
+		let mut rows = Vec::new();
+		self.collect_rows(tag, &mut rows);
+
+		let cells: Vec<Vec<&HTMLTag<'d>>> = rows.into_iter().map(|row|
+			self.nodes(&children_of(row))
+				.filter_map(HtmlNode::as_tag)
+				.filter(|cell| matches!(tag_name(cell).as_str(), "td" | "th"))
+				.collect()
+		).collect();
+
+		let columns = cells.iter().map(Vec::len).max().unwrap_or_default();
+
+		// Alignment is taken from the header row, falling back to none for any
+		// missing columns.
+		for column in 0..columns {
+			let align = cells.first()
+				.and_then(|header| header.get(column))
+				.map_or(AlignKind::None, |cell| alignment(cell));
+
+			builder = builder.align_column(align);
+		}
+
+		for row in cells {
+			builder = builder.row(|mut nb| {
+				for cell in row {
+					nb = nb.cell(|nb| self.phrasing(nb, &children_of(cell)))?;
+				}
+
+				Ok(nb)
+			})?;
+		}
+
+		Ok(builder)
+	}
+
+	fn collect_rows<'t>(&'t self, tag: &'t HTMLTag<'d>, rows: &mut Vec<&'t HTMLTag<'d>>)
+	where 'p : 't {
+		for child in self.nodes(&children_of(tag)).filter_map(HtmlNode::as_tag) {
+			match tag_name(child).as_str() {
+				"tr" => rows.push(child),
+				"thead" | "tbody" | "tfoot" => self.collect_rows(child, rows),
+				_ => { }
+			}
+		}
+	}
+
+	/// Appends a node as inline HTML.
+	fn raw<N : BlockNode<'d>>(&self, builder: NodeBuilder<N>, node: &HtmlNode<'d>) -> NodeBuilder<N> {
+		let value = node.outer_html(self.parser).into_owned();
+		let Ok(builder) = builder.html(|nb| Ok::<_, !>(nb.set_value(value)));
+		builder
+	}
+
+	/// Appends a node as block HTML.
+	fn raw_block<N : BlockNode<'d>>(&self, builder: NodeBuilder<N>, node: &HtmlNode<'d>) -> NodeBuilder<N> {
+		let value = node.outer_html(self.parser).into_owned();
+		let Ok(builder) = builder.html_block(|nb| Ok::<_, !>(nb.set_value(value)));
+		builder
+	}
+
+	fn text_of(&self, tag: &HTMLTag<'d>) -> String {
+		decode_entities(&tag.inner_text(self.parser)).into_owned()
+	}
+}
+
+fn flush<'d, N : BlockNode<'d>>(
+	builder: NodeBuilder<N>,
+	para: Option<NodeBuilder<Paragraph<'d>>>
+) -> NodeBuilder<N> {
+	if let Some(para) = para {
+		builder.append(para.node())
+	} else {
+		builder
+	}
+}
+
+fn tag_name(tag: &HTMLTag<'_>) -> String {
+	tag.name().as_utf8_str().to_ascii_lowercase()
+}
+
+fn children_of(tag: &HTMLTag<'_>) -> Vec<NodeHandle> {
+	tag.children().top().to_vec()
+}
+
+fn attr<'d>(tag: &HTMLTag<'d>, key: &'static str) -> Option<Cow<'d, str>> {
+	tag.attributes()
+		.get(key)
+		.flatten()
+		.map(|value| map_cow(utf8(value), decode_entities))
+}
+
+/// Returns the text of some bytes, borrowed from the source if possible.
+fn utf8<'d>(bytes: &Bytes<'d>) -> Cow<'d, str> {
+	match bytes.as_bytes_borrowed().map(String::from_utf8_lossy) {
+		Some(text) => text,
+		None => Cow::Owned(bytes.as_utf8_str().into_owned())
+	}
+}
+
+fn alignment(cell: &HTMLTag<'_>) -> AlignKind {
+	let align = attr(cell, "align").or_else(||
+		attr(cell, "style").and_then(|style|
+			regex!(r"(?i)text-align\s*:\s*(\w+)")
+				.captures(&style)
+				.map(|caps| Cow::Owned(caps[1].to_string()))
+		)
+	);
+
+	match align.map(|a| a.to_ascii_lowercase()).as_deref() {
+		Some("left"  ) => AlignKind::Left,
+		Some("right" ) => AlignKind::Right,
+		Some("center") => AlignKind::Center,
+		_              => AlignKind::None,
+	}
+}
+
+/// Returns the value of a CSS property in a `style` attribute.
+fn style_value(style: &str, property: &str) -> Option<String> {
+	style.split(';').find_map(|decl| {
+		let (name, value) = decl.split_once(':')?;
+		let value = value.trim();
+		(name.trim().eq_ignore_ascii_case(property) && !value.is_empty()).then(|| value.to_string())
+	})
+}
+
+fn is_flow(name: &str) -> bool {
+	matches!(
+		name,
+		"h1" | "h2" | "h3" | "h4" | "h5" | "h6" |
+		"blockquote" | "hr" | "ol" | "p" | "pre" | "table" | "ul"
+	) || HTML_BLOCKS.contains(&name)
+}
+
+fn is_blank(node: &HtmlNode<'_>) -> bool {
+	match node {
+		HtmlNode::Raw(text) => text.as_utf8_str().trim().is_empty(),
+		_ => false,
+	}
+}
+
+/// Whitespace in HTML text. Other Unicode whitespace, such as the non-breaking
+/// space from `&nbsp;`, is kept.
+const HTML_WHITESPACE: &[char] = &[' ', '\t', '\n', '\r', '\x0C'];
+
+/// Collapses runs of whitespace into a single space, as a browser would.
+fn collapse_whitespace(text: &str) -> Cow<'_, str> {
+	regex!(r"[ \t\n\r\x0C]+").replace_all(text, " ")
+}
+
+/// Trims whitespace at the edges of phrasing content and around line breaks, and
+/// drops paragraphs left empty.
+fn normalize(node: &mut FlowContent) {
+	match node {
+		FlowContent::Content(TextContent::Paragraph(Paragraph { children, .. })) |
+		FlowContent::Heading(Heading { children, .. }) => trim_phrasing(children),
+		FlowContent::Align(Align { children, .. }) |
+		FlowContent::FootnoteDef(FootnoteDef { children, .. }) |
+		FlowContent::Quote(Quote { children, .. }) |
+		FlowContent::Spoiler(Spoiler { children, .. }) => normalize_flow(children),
+		FlowContent::List(list) =>
+			for item in &mut list.children {
+				normalize_flow(&mut item.children)
+			},
+		FlowContent::Table(table) =>
+			for cell in table.children.iter_mut().flat_map(|row| &mut row.children) {
+				trim_phrasing(&mut cell.children)
+			},
+		_ => { }
+	}
+}
+
+fn normalize_flow(children: &mut Vec<FlowContent>) {
+	children.iter_mut().for_each(normalize);
+	children.retain(|child|
+		!matches!(child, FlowContent::Content(TextContent::Paragraph(para)) if para.children.is_empty())
+	);
+}
+
+fn is_empty_paragraph(content: &Content) -> bool {
+	matches!(
+		content,
+		Content::Flow(FlowContent::Content(TextContent::Paragraph(para))) if para.children.is_empty()
+	)
+}
+
+fn trim_phrasing(children: &mut Vec<PhrasingContent>) {
+	let len = children.len();
+	let is_break = |node: &PhrasingContent| matches!(node, PhrasingContent::Static(StaticPhrasingContent::Break(_)));
+
+	for i in 0..len {
+		let after_break = i == 0 || is_break(&children[i - 1]);
+		let before_break = i + 1 == len || is_break(&children[i + 1]);
+
+		if let PhrasingContent::Static(StaticPhrasingContent::Text(text)) = &mut children[i] {
+			if after_break {
+				text.value = map_cow(take(&mut text.value), |value| value.trim_start_matches(HTML_WHITESPACE).into());
+			}
+
+			if before_break {
+				text.value = map_cow(take(&mut text.value), |value| value.trim_end_matches(HTML_WHITESPACE).into());
+			}
+		}
+	}
+
+	children.retain(|child|
+		!matches!(child, PhrasingContent::Static(StaticPhrasingContent::Text(text)) if text.value.is_empty())
+	);
+}
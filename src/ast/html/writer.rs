@@ -0,0 +1,504 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use crate::tmast::*;
+use crate::tmast::definitions::Definitions;
+use crate::util::{escape_html, youtube_id, Emit};
+
+use super::{sanitize_url, SAFE_PROTOCOLS};
+
+#[derive(Default)]
+pub(super) struct Writer<'n> {
+	out: String,
+	definitions: Definitions<'n>,
+}
+
+impl<'n> Writer<'n> {
+	/// Writes the document, emitting the output as each top-level block and
+	/// footnote is finished.
+	pub fn write(mut self, root: &'n Root<'n>, emit: &mut Emit) -> io::Result<()> {
+		self.definitions = Definitions::collect(root);
+
+		self.root(&root.children, emit)?;
+		self.footnote_section(emit)
+	}
+
+	fn flush(&mut self, emit: &mut Emit) -> io::Result<()> {
+		if !self.out.is_empty() {
+			emit(&self.out)?;
+			self.out.clear();
+		}
+		Ok(())
+	}
+
+	/// Writes the root content. Runs of phrasing content not wrapped in a paragraph
+	/// are given one.
+	fn root(&mut self, children: &'n [Content<'n>], emit: &mut Emit) -> io::Result<()> {
+		let mut run = Vec::new();
+
+		for child in children {
+			match child {
+				Content::Phrasing(node) => run.push(node),
+				Content::Flow(node) => {
+					self.paragraph_run(&mut run);
+					self.block(node);
+					self.flush(emit)?;
+				}
+			}
+		}
+
+		self.paragraph_run(&mut run);
+		self.flush(emit)
+	}
+
+	fn paragraph_run(&mut self, run: &mut Vec<&'n PhrasingContent<'n>>) {
+		if run.is_empty() {
+			return
+		}
+
+		self.out.push_str("<p>");
+
+		for node in run.drain(..) {
+			self.inline(node);
+		}
+
+		self.out.push_str("</p>\n");
+	}
+
+	fn blocks(&mut self, children: &'n [FlowContent<'n>]) {
+		for child in children {
+			self.block(child);
+		}
+	}
+
+	fn block(&mut self, node: &'n FlowContent<'n>) {
+		match node {
+			FlowContent::Align(align) => {
+				let value = match align.align {
+					AlignKind::Left   => "left",
+					AlignKind::Right  => "right",
+					AlignKind::Center => "center",
+					AlignKind::None   => "",
+				};
+
+				if value.is_empty() {
+					self.blocks(&align.children);
+				} else {
+					self.out.push_str(&format!("<div style=\"text-align: {value};\">\n"));
+					self.blocks(&align.children);
+					self.out.push_str("</div>\n");
+				}
+			}
+			FlowContent::Quote(quote) => {
+				self.out.push_str("<blockquote>\n");
+				self.blocks(&quote.children);
+
+				if let Some(author) = &quote.author {
+					self.out.push_str("<p>—");
+					self.out.push_str(&escape_html(author));
+					self.out.push_str("</p>\n");
+				}
+
+				self.out.push_str("</blockquote>\n");
+			}
+			FlowContent::Code(code) => {
+				self.out.push_str("<pre><code");
+
+				if let Some(lang) = &code.lang {
+					self.out.push_str(" class=\"language-");
+					self.out.push_str(&escape_html(lang));
+					self.out.push('"');
+				}
+
+				self.out.push('>');
+				self.code_value(&code.value);
+				self.out.push_str("</code></pre>\n");
+			}
+			FlowContent::Heading(heading) => {
+				let depth = heading.depth.clamp(1, 6);
+
+				self.out.push_str(&format!("<h{depth}>"));
+				self.inlines(&heading.children);
+				self.out.push_str(&format!("</h{depth}>\n"));
+			}
+			FlowContent::Html(html) => {
+				self.out.push_str(&html.value);
+				self.out.push('\n');
+			}
+			FlowContent::List(list) => self.list(list),
+			FlowContent::Math(math) => {
+				self.out.push_str("<pre><code class=\"language-math math-display\">");
+				self.code_value(&math.value);
+				self.out.push_str("</code></pre>\n");
+			}
+			FlowContent::Content(TextContent::Paragraph(para)) => {
+				self.out.push_str("<p>");
+				self.inlines(&para.children);
+				self.out.push_str("</p>\n");
+			}
+			FlowContent::Spoiler(spoiler) => {
+				self.out.push_str("<details>\n");
+
+				if let Some(summary) = &spoiler.summary {
+					self.out.push_str("<summary>");
+					self.out.push_str(&escape_html(summary));
+					self.out.push_str("</summary>\n");
+				}
+
+				self.blocks(&spoiler.children);
+				self.out.push_str("</details>\n");
+			}
+			FlowContent::Table(table) => self.table(table),
+			FlowContent::ThematicBreak(_) => self.out.push_str("<hr />\n"),
+			// Read through self.definitions; footnotes go in the closing section.
+			FlowContent::Content(TextContent::Definition(_)) |
+			FlowContent::FootnoteDef(_) => { }
+		}
+	}
+
+	fn inlines(&mut self, children: &'n [PhrasingContent<'n>]) {
+		for child in children {
+			self.inline(child);
+		}
+	}
+
+	fn inline(&mut self, node: &'n PhrasingContent<'n>) {
+		match node {
+			PhrasingContent::FootnoteRef(reference) => self.footnote_ref(reference),
+			PhrasingContent::Link(link) => self.link(&link.url, link.title.as_deref(), &link.children),
+			PhrasingContent::LinkRef(reference) => {
+				if let Some(def) = self.definitions.definition(&reference.identifier) {
+					let Definition { url, title, .. } = def;
+					self.link(url, title.as_deref(), &reference.children);
+				} else {
+					self.out.push('[');
+					self.static_inlines(&reference.children);
+					self.out.push(']');
+				}
+			}
+			PhrasingContent::Static(node) => self.static_inline(node),
+		}
+	}
+
+	fn static_inlines(&mut self, children: &'n [StaticPhrasingContent<'n>]) {
+		for child in children {
+			self.static_inline(child);
+		}
+	}
+
+	fn static_inline(&mut self, node: &'n StaticPhrasingContent<'n>) {
+		match node {
+			StaticPhrasingContent::Break(_) => self.out.push_str("<br />\n"),
+			StaticPhrasingContent::Color(color) => self.span("color", &color.color, &color.children),
+			StaticPhrasingContent::Delete(delete) => self.wrap("del", &delete.children),
+			StaticPhrasingContent::Embed(embed) => self.embed(&embed.url),
+			StaticPhrasingContent::Emphasis(emphasis) => self.wrap("em", &emphasis.children),
+			StaticPhrasingContent::FontSize(size) => self.span("font-size", &size.size, &size.children),
+			StaticPhrasingContent::Html(html) => self.out.push_str(&html.value),
+			StaticPhrasingContent::Image(image) => self.image(&image.url, &image.alt, image.title.as_deref()),
+			StaticPhrasingContent::ImageRef(reference) => {
+				if let Some(def) = self.definitions.definition(&reference.identifier) {
+					let Definition { url, title, .. } = def;
+					self.image(url, &reference.alt, title.as_deref());
+				} else {
+					self.out.push_str("![");
+					self.out.push_str(&escape_html(&reference.alt));
+					self.out.push(']');
+				}
+			}
+			StaticPhrasingContent::InlineCode(code) => {
+				self.out.push_str("<code>");
+				self.out.push_str(&escape_html(&code.value));
+				self.out.push_str("</code>");
+			}
+			StaticPhrasingContent::InlineMath(math) => {
+				self.out.push_str("<code class=\"language-math math-inline\">");
+				self.out.push_str(&escape_html(&math.value));
+				self.out.push_str("</code>");
+			}
+			StaticPhrasingContent::Strong(strong) => self.wrap("strong", &strong.children),
+			StaticPhrasingContent::Subscript(sub) => self.wrap("sub", &sub.children),
+			StaticPhrasingContent::Superscript(sup) => self.wrap("sup", &sup.children),
+			StaticPhrasingContent::Text(text) => self.out.push_str(&escape_html(&text.value)),
+			StaticPhrasingContent::Underline(underline) => self.wrap("u", &underline.children),
+		}
+	}
+
+	fn wrap(&mut self, tag: &str, children: &'n [PhrasingContent<'n>]) {
+		self.out.push('<');
+		self.out.push_str(tag);
+		self.out.push('>');
+		self.inlines(children);
+		self.out.push_str("</");
+		self.out.push_str(tag);
+		self.out.push('>');
+	}
+
+	/// Writes a span styled with a CSS property.
+	fn span(&mut self, property: &str, value: &str, children: &'n [PhrasingContent<'n>]) {
+		// Semicolons would end the declaration, letting the value add others.
+		let value = value.split(';').next().unwrap_or_default().trim();
+
+		self.out.push_str("<span style=\"");
+		self.out.push_str(property);
+		self.out.push_str(": ");
+		self.out.push_str(&escape_html(value));
+		self.out.push_str(";\">");
+		self.inlines(children);
+		self.out.push_str("</span>");
+	}
+
+	/// Writes a YouTube video as an embedded player, and any other embed as a
+	/// link.
+	fn embed(&mut self, url: &str) {
+		if let Some(id) = youtube_id(url) {
+			self.out.push_str(&format!(
+				"<iframe src=\"https://www.youtube.com/embed/{id}\" allowfullscreen=\"\"></iframe>"
+			));
+		} else {
+			let url = escape_html(sanitize_url(url, SAFE_PROTOCOLS));
+			self.out.push_str(&format!("<a href=\"{url}\">{url}</a>"));
+		}
+	}
+
+	fn code_value(&mut self, value: &str) {
+		self.out.push_str(&escape_html(value));
+
+		if !value.is_empty() {
+			self.out.push('\n');
+		}
+	}
+
+	fn link(&mut self, url: &str, title: Option<&str>, children: &'n [StaticPhrasingContent<'n>]) {
+		self.out.push_str("<a href=\"");
+		self.out.push_str(&escape_html(sanitize_url(url, SAFE_PROTOCOLS)));
+		self.out.push('"');
+		self.title(title);
+		self.out.push('>');
+		self.static_inlines(children);
+		self.out.push_str("</a>");
+	}
+
+	fn image(&mut self, url: &str, alt: &str, title: Option<&str>) {
+		self.out.push_str("<img src=\"");
+		self.out.push_str(&escape_html(sanitize_url(url, SAFE_PROTOCOLS)));
+		self.out.push_str("\" alt=\"");
+		self.out.push_str(&escape_html(alt));
+		self.out.push('"');
+		self.title(title);
+		self.out.push_str(" />");
+	}
+
+	fn title(&mut self, title: Option<&str>) {
+		if let Some(title) = title {
+			self.out.push_str(" title=\"");
+			self.out.push_str(&escape_html(title));
+			self.out.push('"');
+		}
+	}
+
+	fn list(&mut self, list: &'n List<'n>) {
+		let tag = if list.ordered { "ol" } else { "ul" };
+		let tight = !list.spread && list.children.iter().all(|item| !item.spread);
+
+		self.out.push('<');
+		self.out.push_str(tag);
+
+		if let Some(start) = list.start.filter(|start| list.ordered && *start != 1) {
+			self.out.push_str(&format!(" start=\"{start}\""));
+		}
+
+		self.out.push_str(">\n");
+
+		for item in &list.children {
+			self.list_item(item, tight);
+		}
+
+		self.out.push_str("</");
+		self.out.push_str(tag);
+		self.out.push_str(">\n");
+	}
+
+	fn list_item(&mut self, item: &'n ListItem<'n>, tight: bool) {
+		self.out.push_str("<li>");
+
+		if let Some(checked) = item.checked {
+			self.out.push_str("<input type=\"checkbox\" disabled=\"\"");
+
+			if checked {
+				self.out.push_str(" checked=\"\"");
+			}
+
+			self.out.push_str(" /> ");
+		}
+
+		if !tight {
+			self.out.push('\n');
+			self.blocks(&item.children);
+		} else {
+			// Paragraphs in tight lists aren't wrapped in <p> tags.
+			for (i, child) in item.children.iter().enumerate() {
+				match child {
+					FlowContent::Content(TextContent::Paragraph(para)) => {
+						if i > 0 && !self.out.ends_with('\n') {
+							self.out.push('\n');
+						}
+
+						self.inlines(&para.children);
+					}
+					_ => {
+						if !self.out.ends_with('\n') {
+							self.out.push('\n');
+						}
+
+						self.block(child);
+					}
+				}
+			}
+		}
+
+		self.out.push_str("</li>\n");
+	}
+
+	fn table(&mut self, table: &'n Table<'n>) {
+		let mut rows = table.children.iter();
+
+		self.out.push_str("<table>\n");
+
+		if let Some(header) = rows.next() {
+			self.out.push_str("<thead>\n");
+			self.table_row(header, &table.align, "th");
+			self.out.push_str("</thead>\n");
+		}
+
+		if rows.len() > 0 {
+			self.out.push_str("<tbody>\n");
+
+			for row in rows {
+				self.table_row(row, &table.align, "td");
+			}
+
+			self.out.push_str("</tbody>\n");
+		}
+
+		self.out.push_str("</table>\n");
+	}
+
+	fn table_row(&mut self, row: &'n TableRow<'n>, align: &[AlignKind], tag: &str) {
+		self.out.push_str("<tr>\n");
+
+		for (i, cell) in row.children.iter().enumerate() {
+			self.out.push('<');
+			self.out.push_str(tag);
+
+			let align = match align.get(i) {
+				Some(AlignKind::Left  ) => Some("left"),
+				Some(AlignKind::Right ) => Some("right"),
+				Some(AlignKind::Center) => Some("center"),
+				_                       => None,
+			};
+
+			if let Some(align) = align {
+				self.out.push_str(" align=\"");
+				self.out.push_str(align);
+				self.out.push('"');
+			}
+
+			self.out.push('>');
+
+			self.inlines(&cell.children);
+
+			self.out.push_str("</");
+			self.out.push_str(tag);
+			self.out.push_str(">\n");
+		}
+
+		self.out.push_str("</tr>\n");
+	}
+
+	fn footnote_ref(&mut self, reference: &FootnoteRef) {
+		let Some((index, count)) = self.definitions.reference_footnote(&reference.identifier) else {
+			self.out.push_str("[^");
+			self.out.push_str(&escape_html(&reference.identifier));
+			self.out.push(']');
+			return
+		};
+
+		let id = reference.identifier.to_lowercase();
+		let id = escape_html(&id);
+		let suffix = if count > 1 { format!("-{count}") } else { String::new() };
+
+		self.out.push_str(&format!(
+			"<sup><a href=\"#user-content-fn-{id}\" id=\"user-content-fnref-{id}{suffix}\" \
+			data-footnote-ref=\"\" aria-describedby=\"footnote-label\">{}</a></sup>",
+			index + 1
+		));
+	}
+
+	fn footnote_section(&mut self, emit: &mut Emit) -> io::Result<()> {
+		if self.definitions.footnote(0).is_none() {
+			return Ok(())
+		}
+
+		self.out.push_str(
+			"<section data-footnotes=\"\" class=\"footnotes\">\
+			<h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>\n<ol>\n"
+		);
+
+		let mut i = 0;
+
+		while let Some((id, def, _)) = self.definitions.footnote(i) {
+			let id = id.to_string();
+
+			self.out.push_str(&format!("<li id=\"user-content-fn-{}\">\n", escape_html(&id)));
+			self.blocks(&def.children);
+
+			// The footnote may have referenced itself.
+			let count = self.definitions.footnote(i).map_or(1, |(_, _, count)| count);
+			let backrefs = (1..=count).map(|n| {
+				let suffix = if n > 1 { format!("-{n}") } else { String::new() };
+				let sup    = if n > 1 { format!("<sup>{n}</sup>") } else { String::new() };
+
+				format!(
+					"<a href=\"#user-content-fnref-{}{suffix}\" data-footnote-backref=\"\" \
+					aria-label=\"Back to reference {}{suffix}\" class=\"data-footnote-backref\">↩{sup}</a>",
+					escape_html(&id),
+					i + 1
+				)
+			}).collect::<Vec<_>>().join(" ");
+
+			// Back references go at the end of the last paragraph, if there is one.
+			if self.out.ends_with("</p>\n") {
+				self.out.truncate(self.out.len() - 5);
+				self.out.push(' ');
+				self.out.push_str(&backrefs);
+				self.out.push_str("</p>\n");
+			} else {
+				self.out.push_str(&backrefs);
+				self.out.push('\n');
+			}
+
+			self.out.push_str("</li>\n");
+			self.flush(emit)?;
+			i += 1;
+		}
+
+		self.out.push_str("</ol>\n</section>\n");
+		self.flush(emit)
+	}
+}
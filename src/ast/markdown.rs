@@ -0,0 +1,854 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Markdown output. Documents are written as GFM, or as CommonMark, escaping
+//! text only where it would otherwise be read as markup, so the output parses
+//! back to the same tree. Markdown is read with the [markdown](::markdown) crate.
+
+use std::io;
+
+use regex_macro::regex;
+
+use crate::tmast::*;
+use crate::markdown_text::{escape_markdown, escape_markdown_followed, EscapeContext};
+use crate::TmDoc;
+use crate::util::{collect, escape_html, stream, Emit};
+
+use super::html;
+
+/// Writes a [TmDoc] as GFM Markdown.
+pub fn write(doc: &TmDoc) -> String {
+	collect(|emit| Writer::default().root(&doc.0.children, emit))
+}
+
+/// Writes a [TmDoc] as GFM Markdown to `out`, as [write] does, without building
+/// the whole document in memory.
+pub fn write_to(doc: &TmDoc, out: &mut (impl io::Write + ?Sized)) -> io::Result<()> {
+	stream(out, |emit| Writer::default().root(&doc.0.children, emit))
+}
+
+/// Writes a [TmDoc] as CommonMark. Tables and deletions are written as HTML, and
+/// footnotes and task list checkboxes as text.
+pub fn write_commonmark(doc: &TmDoc) -> String {
+	collect(|emit| Writer::commonmark().root(&doc.0.children, emit))
+}
+
+/// Writes a [TmDoc] as CommonMark to `out`, as [write_commonmark] does, without
+/// building the whole document in memory.
+pub fn write_commonmark_to(doc: &TmDoc, out: &mut (impl io::Write + ?Sized)) -> io::Result<()> {
+	stream(out, |emit| Writer::commonmark().root(&doc.0.children, emit))
+}
+
+#[derive(Default)]
+struct Writer {
+	/// The context of the text being written, if not decided by its place on the
+	/// line.
+	context: Option<EscapeContext>,
+	/// Whether the content is in a heading, which can't span lines.
+	in_heading: bool,
+	/// Whether to write CommonMark, without the GFM extensions.
+	commonmark: bool,
+}
+
+impl Writer {
+	fn commonmark() -> Self {
+		Self { commonmark: true, ..Self::default() }
+	}
+
+	/// Writes the root content, emitting each block as it's finished. Runs of
+	/// phrasing content not wrapped in a paragraph are written as one.
+	fn root(&mut self, children: &[Content], emit: &mut Emit) -> io::Result<()> {
+		let mut blocks = Blocks::new(true);
+		let mut run = Vec::new();
+
+		for child in children {
+			match child {
+				Content::Phrasing(node) => run.push(node),
+				Content::Flow(node) => {
+					if !run.is_empty() {
+						blocks.push(self.paragraph(run.drain(..)), Kind::Paragraph);
+					}
+
+					blocks.push(self.block(node, blocks.last), Kind::of(node));
+					blocks.flush(emit)?;
+				}
+			}
+		}
+
+		if !run.is_empty() {
+			blocks.push(self.paragraph(run), Kind::Paragraph);
+		}
+
+		blocks.flush(emit)?;
+
+		if blocks.last.is_some() {
+			emit("\n")?;
+		}
+
+		Ok(())
+	}
+
+	fn blocks(&mut self, children: &[FlowContent], spread: bool) -> String {
+		let mut blocks = Blocks::new(spread);
+
+		for child in children {
+			blocks.push(self.block(child, blocks.last), Kind::of(child));
+		}
+
+		blocks.out
+	}
+
+	/// Writes a block. `prev` is the kind of block directly before it, as a list
+	/// would otherwise continue a list of the same kind.
+	fn block(&mut self, node: &FlowContent, prev: Option<Kind>) -> String {
+		match node {
+			FlowContent::Align(align) => {
+				let content = self.blocks(&align.children, true);
+				let align = match align.align {
+					AlignKind::Left   => "left",
+					AlignKind::Right  => "right",
+					AlignKind::Center => "center",
+					AlignKind::None   => return content,
+				};
+
+				html_block(&format!("<div align=\"{align}\">"), &content, "</div>")
+			}
+			FlowContent::Code(code) => {
+				let (fence, info) = code_fence(&code.value, code.lang.as_deref(), code.meta.as_deref());
+				let mut out = format!("{fence}{info}\n{}", code.value);
+
+				if !code.value.is_empty() {
+					out.push('\n');
+				}
+
+				out.push_str(&fence);
+				out
+			}
+			FlowContent::Content(TextContent::Definition(def)) => {
+				let mut out = format!("[{}]: {}", label(&def.identifier, def.label.as_deref()), destination(&def.url));
+				push_title(&mut out, def.title.as_deref());
+				out
+			}
+			FlowContent::Content(TextContent::Paragraph(para)) => self.paragraph(&para.children),
+			FlowContent::FootnoteDef(def) => {
+				let content = self.blocks(&def.children, true);
+				let label = label(&def.identifier, def.label.as_deref());
+
+				if self.commonmark {
+					// Indented lines would be read as code outside a footnote.
+					indent(&format!("{}: ", footnote_text(label)), "", &content)
+				} else {
+					indent(&format!("[^{label}]: "), "    ", &content)
+				}
+			}
+			FlowContent::Heading(heading) => {
+				let hashes = "#".repeat(heading.depth.clamp(1, 6) as usize);
+				self.in_heading = true;
+				let mut content = self.inlines(&heading.children);
+				end_line(&mut content);
+				self.in_heading = false;
+
+				if content.is_empty() {
+					hashes
+				} else {
+					format!("{hashes} {content}")
+				}
+			}
+			FlowContent::Html(html) => html.value.to_string(),
+			FlowContent::List(list) => self.list(list, prev == Some(Kind::List(list.ordered))),
+			FlowContent::Math(math) => {
+				let fence = "$".repeat(longest_run(&math.value, '$').max(1) + 1);
+				let meta = math.meta.as_deref().map(|meta| format!(" {meta}")).unwrap_or_default();
+				format!("{fence}{meta}\n{}\n{fence}", math.value)
+			}
+			FlowContent::Quote(quote) => {
+				let mut content = self.blocks(&quote.children, true);
+
+				if let Some(author) = &quote.author {
+					if !content.is_empty() {
+						content.push_str("\n\n");
+					}
+
+					content.push('—');
+					content.push_str(&escape_markdown(author, EscapeContext::Inline));
+				}
+
+				indent("> ", "> ", &content)
+			}
+			FlowContent::Spoiler(spoiler) => {
+				let content = self.blocks(&spoiler.children, true);
+				let mut open = "<details>".to_string();
+
+				if let Some(summary) = &spoiler.summary {
+					open.push_str(&format!("\n<summary>{}</summary>", escape_html(summary)));
+				}
+
+				html_block(&open, &content, "</details>")
+			}
+			FlowContent::Table(table) if self.commonmark => {
+				let root = Root::new(vec![FlowContent::Table(table.clone()).into()], None);
+				html::write(&TmDoc(root)).trim_end().to_string()
+			}
+			FlowContent::Table(table) => self.table(table),
+			FlowContent::ThematicBreak(_) => "***".to_string(),
+		}
+	}
+
+	/// Writes a list. Lists directly after a list of the same kind use alternate
+	/// markers, so they aren't read as one list.
+	fn list(&mut self, list: &List, alternate: bool) -> String {
+		let start = list.start.unwrap_or(1) as usize;
+		let mut out = String::new();
+
+		for (i, item) in list.children.iter().enumerate() {
+			let marker = match (list.ordered, alternate) {
+				(true , false) => format!("{}.", start + i),
+				(true , true ) => format!("{})", start + i),
+				(false, false) => "-".to_string(),
+				(false, true ) => "*".to_string(),
+			};
+
+			if i > 0 {
+				out.push_str(if list.spread || item.spread { "\n\n" } else { "\n" });
+			}
+
+			let mut content = self.blocks(&item.children, item.spread);
+
+			if let Some(checked) = item.checked {
+				let checkbox = match (checked, self.commonmark) {
+					(true , false) => "[x]",
+					(false, false) => "[ ]",
+					// Escaped, so a definition labeled "x" can't make it a link.
+					(true , true ) => "\\[x]",
+					(false, true ) => "\\[ ]",
+				};
+				content.insert_str(0, &format!("{checkbox} "));
+			}
+
+			let prefix = format!("{marker} ");
+			let padding = " ".repeat(prefix.len());
+			out.push_str(indent(&prefix, &padding, &content).trim_end());
+		}
+
+		out
+	}
+
+	fn table(&mut self, table: &Table) -> String {
+		let columns = table.children
+			.iter()
+			.map(|row| row.children.len())
+			.chain([table.align.len(), 1])
+			.max()
+			.unwrap_or(1);
+		let mut rows = table.children.iter();
+		let mut out = String::new();
+
+		self.context = Some(EscapeContext::TableCell);
+
+		if let Some(header) = rows.next() {
+			out.push_str(&self.table_row(header, columns));
+		} else {
+			out.push_str(&"|  ".repeat(columns));
+			out.push('|');
+		}
+
+		out.push('\n');
+
+		for i in 0..columns {
+			out.push_str(
+				match table.align.get(i) {
+					Some(AlignKind::Left  ) => "| :-- ",
+					Some(AlignKind::Right ) => "| --: ",
+					Some(AlignKind::Center) => "| :-: ",
+					_                       => "| --- ",
+				}
+			);
+		}
+
+		out.push('|');
+
+		for row in rows {
+			out.push('\n');
+			out.push_str(&self.table_row(row, columns));
+		}
+
+		self.context = None;
+		out
+	}
+
+	fn table_row(&mut self, row: &TableRow, columns: usize) -> String {
+		let mut out = String::new();
+
+		for i in 0..columns {
+			let cell = row.children
+				.get(i)
+				.map(|cell| {
+					let mut cell = self.inlines(&cell.children);
+					end_line(&mut cell);
+					cell
+				})
+				.unwrap_or_default();
+
+			out.push_str("| ");
+			out.push_str(&cell);
+			out.push(' ');
+		}
+
+		out.push('|');
+		out
+	}
+
+	fn paragraph<'a, 'n : 'a>(&mut self, children: impl IntoIterator<Item = &'a PhrasingContent<'n>>) -> String {
+		let mut out = self.inlines(children);
+		end_line(&mut out);
+		out
+	}
+
+	fn inlines<'a, 'n : 'a>(&mut self, children: impl IntoIterator<Item = &'a PhrasingContent<'n>>) -> String {
+		let mut out = String::new();
+
+		for child in children {
+			self.inline(&mut out, child);
+		}
+
+		out
+	}
+
+	fn inline(&mut self, out: &mut String, node: &PhrasingContent) {
+		match node {
+			PhrasingContent::FootnoteRef(reference) => {
+				let label = label(&reference.identifier, reference.label.as_deref());
+
+				if self.commonmark {
+					out.push_str(&footnote_text(label));
+				} else {
+					out.push_str("[^");
+					out.push_str(label);
+					out.push(']');
+				}
+			}
+			PhrasingContent::Link(link) => {
+				// Links whose text is their URL are written as autolinks.
+				if let [StaticPhrasingContent::Text(text)] = link.children.as_slice() {
+					let is_autolink = link.title.is_none() &&
+						text.value == link.url &&
+						regex!(r"^[A-Za-z][A-Za-z0-9+.-]{1,31}:[^\s<>]*$").is_match(&link.url);
+
+					if is_autolink {
+						out.push('<');
+						out.push_str(&link.url);
+						out.push('>');
+						return
+					}
+				}
+
+				out.push('[');
+				self.link_text(out, &link.children);
+				out.push_str("](");
+				out.push_str(&destination(&link.url));
+				push_title(out, link.title.as_deref());
+				out.push(')');
+			}
+			PhrasingContent::LinkRef(reference) => {
+				out.push('[');
+				self.link_text(out, &reference.children);
+				out.push(']');
+				push_reference(out, &reference.identifier, reference.label.as_deref(), reference.kind);
+			}
+			PhrasingContent::Static(node) => self.static_inline(out, node),
+		}
+	}
+
+	fn static_inline(&mut self, out: &mut String, node: &StaticPhrasingContent) {
+		match node {
+			StaticPhrasingContent::Break(_) =>
+				if self.in_heading || self.context == Some(EscapeContext::TableCell) {
+					// Headings and table rows can't span lines.
+					out.push_str("<br />");
+				} else {
+					out.push_str("\\\n");
+				},
+			StaticPhrasingContent::Color(color) => self.span(out, "color", &color.color, &color.children),
+			StaticPhrasingContent::Delete(delete) if self.commonmark => self.tag(out, "del", &delete.children),
+			StaticPhrasingContent::Delete(delete) => self.wrap(out, "~~", &delete.children),
+			StaticPhrasingContent::Embed(embed) => {
+				// Embeds can't be expressed, so are written as a link to their URL.
+				if regex!(r"^[A-Za-z][A-Za-z0-9+.-]{1,31}:[^\s<>]*$").is_match(&embed.url) {
+					out.push('<');
+					out.push_str(&embed.url);
+					out.push('>');
+				} else {
+					out.push('[');
+					out.push_str(&escape_markdown(&embed.url, EscapeContext::LinkText));
+					out.push_str("](");
+					out.push_str(&destination(&embed.url));
+					out.push(')');
+				}
+			}
+			StaticPhrasingContent::Emphasis(emphasis) => self.wrap(out, "*", &emphasis.children),
+			StaticPhrasingContent::FontSize(size) => self.span(out, "font-size", &size.size, &size.children),
+			StaticPhrasingContent::Html(html) => out.push_str(&html.value),
+			StaticPhrasingContent::Image(image) => {
+				out.push_str("![");
+				out.push_str(&escape_markdown(&image.alt, EscapeContext::LinkText));
+				out.push_str("](");
+				out.push_str(&destination(&image.url));
+				push_title(out, image.title.as_deref());
+				out.push(')');
+			}
+			StaticPhrasingContent::ImageRef(reference) => {
+				out.push_str("![");
+				out.push_str(&escape_markdown(&reference.alt, EscapeContext::LinkText));
+				out.push(']');
+				push_reference(out, &reference.identifier, reference.label.as_deref(), reference.kind);
+			}
+			StaticPhrasingContent::InlineCode(code) => out.push_str(&escape_markdown(&code.value, EscapeContext::CodeSpan)),
+			StaticPhrasingContent::InlineMath(math) => {
+				let fence = "$".repeat(longest_run(&math.value, '$') + 1);
+				out.push_str(&format!("{fence}{}{fence}", math.value));
+			}
+			StaticPhrasingContent::Strong(strong) => self.wrap(out, "**", &strong.children),
+			StaticPhrasingContent::Subscript(sub) => self.tag(out, "sub", &sub.children),
+			StaticPhrasingContent::Superscript(sup) => self.tag(out, "sup", &sup.children),
+			StaticPhrasingContent::Text(text) => {
+				let context = self.context.unwrap_or(
+					if out.is_empty() || out.ends_with('\n') {
+						EscapeContext::LineStart
+					} else {
+						EscapeContext::Inline
+					}
+				);
+
+				// Whitespace at the end of a paragraph is kept by end_line instead.
+				out.push_str(&escape_markdown_followed(&text.value, context));
+			}
+			StaticPhrasingContent::Underline(underline) => self.tag(out, "u", &underline.children),
+		}
+	}
+
+	/// Wraps content in an inline HTML tag, for formatting Markdown has no syntax
+	/// for.
+	fn tag(&mut self, out: &mut String, name: &str, children: &[PhrasingContent]) {
+		out.push_str(&format!("<{name}>"));
+
+		for child in children {
+			self.inline(out, child);
+		}
+
+		out.push_str(&format!("</{name}>"));
+	}
+
+	/// Wraps content in a span styled with a CSS property.
+	fn span(&mut self, out: &mut String, property: &str, value: &str, children: &[PhrasingContent]) {
+		// Semicolons would end the declaration, letting the value add others.
+		let value = value.split(';').next().unwrap_or_default().trim();
+
+		out.push_str(&format!("<span style=\"{property}: {};\">", escape_html(value)));
+
+		for child in children {
+			self.inline(out, child);
+		}
+
+		out.push_str("</span>");
+	}
+
+	/// Wraps content in delimiters. Delimiters next to whitespace can't open or
+	/// close, so whitespace at the edges is moved outside, along with trailing line
+	/// breaks. Leading spaces are kept as references when the text is escaped.
+	fn wrap(&mut self, out: &mut String, delimiter: &str, children: &[PhrasingContent]) {
+		let mut inner = String::new();
+
+		for child in children {
+			self.inline(&mut inner, child);
+		}
+
+		let start = inner.len() - inner.trim_start().len();
+		let end   = trim_paragraph(&inner[start..]).len() + start;
+
+		if start == end {
+			out.push_str(&inner);
+			return
+		}
+
+		out.push_str(&inner[..start]);
+		out.push_str(delimiter);
+		out.push_str(&inner[start..end]);
+		out.push_str(delimiter);
+		out.push_str(&inner[end..]);
+	}
+
+	fn link_text(&mut self, out: &mut String, children: &[StaticPhrasingContent]) {
+		let context = self.context.replace(EscapeContext::LinkText);
+
+		for child in children {
+			self.static_inline(out, child);
+		}
+
+		self.context = context;
+	}
+}
+
+/// Blocks separated by blank lines, or by newlines if tight.
+struct Blocks {
+	out: String,
+	spread: bool,
+	/// The kind of the last block written.
+	last: Option<Kind>,
+}
+
+impl Blocks {
+	fn new(spread: bool) -> Self {
+		Self { out: String::new(), spread, last: None }
+	}
+
+	/// Adds a block. Blocks in tight content are still separated by a blank line
+	/// where the second would continue the first, or couldn't interrupt it.
+	fn push(&mut self, block: String, kind: Kind) {
+		if block.is_empty() {
+			return
+		}
+
+		if let Some(last) = self.last {
+			let is_blank = self.spread || matches!(
+				(last, kind),
+				(_, Kind::Paragraph) if last != Kind::Other
+			) || matches!(
+				(last, kind),
+				(Kind::Paragraph, Kind::Container)
+			);
+
+			self.out.push_str(if is_blank { "\n\n" } else { "\n" });
+		}
+
+		self.out.push_str(&block);
+		self.last = Some(kind);
+	}
+
+	/// Emits the blocks added so far. Separators are only added before a block,
+	/// so what's emitted is never changed by later blocks.
+	fn flush(&mut self, emit: &mut Emit) -> io::Result<()> {
+		if !self.out.is_empty() {
+			emit(&self.out)?;
+			self.out.clear();
+		}
+
+		Ok(())
+	}
+}
+
+/// The kind of a block, deciding how it's separated from its neighbours.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Kind {
+	Paragraph,
+	/// A list, and whether it's ordered.
+	List(bool),
+	/// A block that a following paragraph would continue, or that can't interrupt
+	/// a paragraph.
+	Container,
+	/// A block that ends on its own line, such as a heading or code block.
+	Other,
+}
+
+impl Kind {
+	fn of(node: &FlowContent) -> Self {
+		match node {
+			FlowContent::Content(TextContent::Paragraph(_)) => Self::Paragraph,
+			FlowContent::List(list) => Self::List(list.ordered),
+			FlowContent::Align(_) |
+			FlowContent::Content(TextContent::Definition(_)) |
+			FlowContent::FootnoteDef(_) |
+			FlowContent::Html(_) |
+			FlowContent::Quote(_) |
+			FlowContent::Spoiler(_) |
+			FlowContent::Table(_) => Self::Container,
+			FlowContent::Code(_) |
+			FlowContent::Heading(_) |
+			FlowContent::Math(_) |
+			FlowContent::ThematicBreak(_) => Self::Other,
+		}
+	}
+}
+
+/// Writes blocks between opening and closing HTML tags. Blank lines around the
+/// content end the HTML block, so the content is read as Markdown.
+fn html_block(open: &str, content: &str, close: &str) -> String {
+	if content.is_empty() {
+		format!("{open}\n{close}")
+	} else {
+		format!("{open}\n\n{content}\n\n{close}")
+	}
+}
+
+/// Trims trailing whitespace and line breaks, which would be stripped or read as
+/// a literal backslash at the end of a paragraph.
+fn trim_paragraph(text: &str) -> &str {
+	let text = text.trim_end();
+	let backslashes = text.len() - text.trim_end_matches('\\').len();
+
+	if backslashes % 2 == 1 {
+		text[..text.len() - 1].trim_end()
+	} else {
+		text
+	}
+}
+
+/// Ends a line of inline content, such as a paragraph, heading or table cell, by
+/// trimming it as [trim_paragraph] does. Spaces and tabs trimmed from the end of
+/// text are kept, with the last as a character reference so it isn't stripped.
+fn end_line(out: &mut String) {
+	let end = trim_paragraph(out).len();
+	let kept = out[end..].len() - out[end..].trim_start_matches([' ', '\t']).len();
+
+	if let Some(last) = out[end..end + kept].chars().next_back() {
+		out.truncate(end + kept - 1);
+		out.push_str(&format!("&#{};", last as u32));
+	} else {
+		out.truncate(end);
+	}
+}
+
+/// Prefixes the first line of the content with `first`, and the rest with `rest`.
+/// Blank lines are left unindented, though their prefix is kept if it isn't just
+/// whitespace.
+fn indent(first: &str, rest: &str, content: &str) -> String {
+	let mut out = String::with_capacity(content.len() + first.len());
+
+	for (i, line) in content.split('\n').enumerate() {
+		if i > 0 {
+			out.push('\n');
+		}
+
+		let prefix = if i == 0 { first } else { rest };
+
+		if line.is_empty() {
+			out.push_str(prefix.trim_end());
+		} else {
+			out.push_str(prefix);
+			out.push_str(line);
+		}
+	}
+
+	out
+}
+
+/// Writes a footnote label as text, for CommonMark, which has no footnotes.
+fn footnote_text(label: &str) -> String {
+	format!("\\[^{}]", escape_markdown(label, EscapeContext::Inline))
+}
+
+/// Returns the label of a reference or definition, preferring the source label
+/// over the normalized identifier.
+fn label<'a>(identifier: &'a str, label: Option<&'a str>) -> &'a str {
+	label.unwrap_or(identifier)
+}
+
+fn push_reference(out: &mut String, identifier: &str, source_label: Option<&str>, kind: ReferenceKind) {
+	match kind {
+		ReferenceKind::Full      => {
+			out.push('[');
+			out.push_str(label(identifier, source_label));
+			out.push(']');
+		}
+		ReferenceKind::Collapsed => out.push_str("[]"),
+		ReferenceKind::Shortcut  => { }
+	}
+}
+
+fn destination(url: &str) -> String {
+	escape_markdown(url, EscapeContext::LinkDestination)
+}
+
+fn push_title(out: &mut String, title: Option<&str>) {
+	let Some(title) = title else { return };
+
+	out.push_str(" \"");
+
+	for (i, c) in title.char_indices() {
+		let next = title[i + c.len_utf8()..].chars().next();
+
+		if c == '"' || c == '\\' && next.is_none_or(|next| next.is_ascii_punctuation()) {
+			out.push('\\');
+		}
+
+		out.push(c);
+	}
+
+	out.push('"');
+}
+
+/// Returns a fence for a code block and its info string. Backticks are used
+/// unless the info string contains one.
+fn code_fence(value: &str, lang: Option<&str>, meta: Option<&str>) -> (String, String) {
+	let info = match (lang, meta) {
+		(Some(lang), Some(meta)) => format!("{lang} {meta}"),
+		(Some(lang), None      ) => lang.to_string(),
+		(None      , Some(meta)) => format!(" {meta}"),
+		(None      , None      ) => String::new(),
+	};
+	let char = if info.contains('`') { '~' } else { '`' };
+	let fence = char.to_string().repeat(longest_run(value, char).max(2) + 1);
+	(fence, info)
+}
+
+/// Returns the length of the longest run of `char` in the value.
+fn longest_run(value: &str, char: char) -> usize {
+	value.split(|c| c != char)
+		.map(str::len)
+		.max()
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use markdown::{to_mdast, ParseOptions};
+	use markdown::mdast::Node;
+
+	use crate::TmDoc;
+
+	const SOURCE: &str = r#"# A *heading* with `code`
+
+A paragraph with **strong**, *emphasis*, ~~deletion~~ and a [link](/url "title").
+A hard\
+break, an ![image](/img.png), a <https://example.com> autolink and a [reference][ref].
+
+[ref]: /reference "Reference"
+
+> A quote with
+> - a list
+
+1. One
+2. Two
+   - Nested
+
+* [x] Done
+* [ ] Not done
+
+```rust
+fn main() { }
+```
+
+| Left | Center | Right |
+| :-- | :-: | --: |
+| a \| b | `c` | d |
+
+***
+
+Text with a footnote[^1], 1. a literal period and \*stars\*.
+
+[^1]: The footnote.
+"#;
+
+	fn parse(markdown: &str) -> Node {
+		let mut node = to_mdast(markdown, &ParseOptions::gfm()).expect("parse failed");
+		clear_positions(&mut node);
+		node
+	}
+
+	fn clear_positions(node: &mut Node) {
+		node.position_set(None);
+
+		if let Some(children) = node.children_mut() {
+			children.iter_mut().for_each(clear_positions);
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let node = parse(SOURCE);
+		let markdown = super::write(&TmDoc(node.clone().into()));
+
+		assert_eq!(parse(&markdown), node, "written as:\n{markdown}");
+	}
+
+	#[test]
+	fn heading_break() {
+		use crate::tmast::{Break, FlowContent, Heading, Root, Text};
+
+		let heading = Heading::new(1, vec![Text::new("a", None).into(), Break::new(None).into(), Text::new("b", None).into()], None);
+		let markdown = super::write(&TmDoc(Root::new(vec![FlowContent::from(heading).into()], None)));
+
+		assert_eq!(markdown, "# a<br />b\n");
+		assert!(matches!(parse(&markdown).children().unwrap().as_slice(), [Node::Heading(_)]), "written as:\n{markdown}");
+	}
+
+	#[test]
+	fn trailing_whitespace() {
+		use crate::tmast::{FlowContent, Paragraph, Root, Text};
+
+		let para = Paragraph::new(vec![Text::new("trailing  ", None).into()], None);
+		let markdown = super::write(&TmDoc(Root::new(vec![FlowContent::from(para).into()], None)));
+
+		assert_eq!(markdown, "trailing &#32;\n");
+	}
+
+	#[test]
+	fn output() {
+		let markdown = super::write(&TmDoc(parse("Some *text*\n\n- a\n- b\n\n> quoted").into()));
+
+		assert_eq!(markdown, "Some *text*\n\n- a\n- b\n\n> quoted\n");
+	}
+
+	#[test]
+	fn commonmark() {
+		let doc = TmDoc(parse("~~Gone~~[^1]\n\n- [x] Done\n\n| A |\n| - |\n| 1 |\n\n[^1]: A note.").into());
+		let markdown = super::write_commonmark(&doc);
+
+		assert_eq!(
+			markdown,
+			"<del>Gone</del>\\[^1]\n\n- \\[x] Done\n\n\
+			<table>\n<thead>\n<tr>\n<th>A</th>\n</tr>\n</thead>\n<tbody>\n<tr>\n<td>1</td>\n</tr>\n</tbody>\n</table>\n\n\
+			\\[^1]: A note.\n"
+		);
+
+		let node = to_mdast(&markdown, &ParseOptions::default()).expect("parse failed");
+		let expected = to_mdast("<del>Gone</del>\\[^1]", &ParseOptions::default()).expect("parse failed");
+
+		assert_eq!(node.children().unwrap()[0], expected.children().unwrap()[0]);
+	}
+
+	#[test]
+	#[cfg(feature = "bbcode")]
+	fn adjacent_lists() {
+		let doc = crate::ast::bbcode::parse("[list][*]a[/list][list][*]b[/list]", crate::ast::bbcode::Dialect::Generic)
+			.expect("parse failed");
+
+		assert_eq!(super::write(&doc), "- a\n\n* b\n");
+	}
+
+	#[test]
+	#[cfg(feature = "bbcode")]
+	fn delimiter_whitespace() {
+		let doc = crate::ast::bbcode::parse_lenient("[b]Hi[/b] [i] there \n", crate::ast::bbcode::Dialect::Generic).0;
+
+		assert_eq!(super::write(&doc), "**Hi** *&#32;there&#32;*\n");
+	}
+
+	#[test]
+	#[cfg(feature = "bbcode")]
+	fn html_formatting() {
+		let doc = crate::ast::bbcode::parse(
+			"[center][u]a[/u] [color=red]b[/color][/center][spoiler=Plot]c[/spoiler][youtube]abc[/youtube]",
+			crate::ast::bbcode::Dialect::Generic
+		).expect("parse failed");
+
+		assert_eq!(
+			super::write(&doc),
+			"<div align=\"center\">\n\n<u>a</u> <span style=\"color: red;\">b</span>\n\n</div>\n\n\
+			<details>\n<summary>Plot</summary>\n\nc\n\n</details>\n\n\
+			<https://youtube.com/watch?v=abc>\n"
+		);
+	}
+}
@@ -0,0 +1,663 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Plain text input, and output for places markup can't be displayed. Headings
+//! are underlined, links are written as `text (url)`, and tables are drawn as
+//! ASCII grids.
+
+use std::io;
+
+use crate::tmast::*;
+use crate::tmast::definitions::Definitions;
+use crate::tmast::unist::LineIndex;
+use regex_macro::regex;
+
+use crate::TmDoc;
+use crate::util::{collect, decode_entities, stream, Emit};
+
+/// The width plain text is wrapped at by default.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Writes a [TmDoc] as plain text, wrapped at `width` columns, or not wrapped if
+/// `None`. Words longer than the width are left on their own line rather than
+/// broken.
+pub fn write(doc: &TmDoc, width: Option<usize>) -> String {
+	collect(|emit| Writer::new(width).write(&doc.0, emit))
+}
+
+/// Writes a [TmDoc] as plain text to `out`, as [write] does, without building the
+/// whole document in memory.
+pub fn write_to(doc: &TmDoc, width: Option<usize>, out: &mut (impl io::Write + ?Sized)) -> io::Result<()> {
+	stream(out, |emit| Writer::new(width).write(&doc.0, emit))
+}
+
+/// Reads plain text as a [TmDoc], with a paragraph for each run of non-blank
+/// lines and a break between the lines of a paragraph. Nothing is interpreted as
+/// markup, and the text is borrowed from the input.
+pub fn parse(text: &str) -> TmDoc<'_> {
+	let index = LineIndex::new(text);
+	let mut children  = Vec::new();
+	let mut paragraph = Vec::new();
+	let mut start  = 0;
+	let mut end    = 0;
+	let mut offset = 0;
+
+	let mut flush = |paragraph: &mut Vec<_>, start, end| {
+		if !paragraph.is_empty() {
+			let position = Some(index.position(start..end));
+			children.push(Paragraph::new(std::mem::take(paragraph), position).into());
+		}
+	};
+
+	for line in text.split_inclusive('\n') {
+		let trimmed = line.trim_start();
+		let first = offset + line.len() - trimmed.len();
+		let trimmed = trimmed.trim_end();
+		offset += line.len();
+
+		if trimmed.is_empty() {
+			flush(&mut paragraph, start, end);
+			continue
+		}
+
+		if paragraph.is_empty() {
+			start = first;
+		} else {
+			paragraph.push(Break::new(Some(index.position(end..first))).into());
+		}
+
+		end = first + trimmed.len();
+		paragraph.push(Text::new(trimmed, Some(index.position(first..end))).into());
+	}
+
+	flush(&mut paragraph, start, end);
+	TmDoc(Root::new(children, None))
+}
+
+type Lines = Vec<String>;
+
+struct Writer<'n> {
+	width: Option<usize>,
+	definitions: Definitions<'n>,
+	/// Whether any lines have been emitted.
+	emitted: bool,
+}
+
+impl<'n> Writer<'n> {
+	fn new(width: Option<usize>) -> Self {
+		Self {
+			width,
+			definitions: Definitions::default(),
+			emitted: false,
+		}
+	}
+
+	/// Writes the document, emitting the lines of each top-level block as it's
+	/// finished.
+	fn write(mut self, root: &'n Root<'n>, emit: &mut Emit) -> io::Result<()> {
+		self.definitions = Definitions::collect(root);
+
+		self.root(&root.children, self.width, emit)?;
+
+		let footnotes = self.footnote_section();
+		self.emit_block(footnotes, emit)
+	}
+
+	/// Emits a top-level block, separated from the last by a blank line.
+	fn emit_block(&mut self, block: Lines, emit: &mut Emit) -> io::Result<()> {
+		if block.is_empty() {
+			return Ok(())
+		}
+
+		if self.emitted {
+			emit("\n")?;
+		}
+
+		for line in block {
+			emit(&line)?;
+			emit("\n")?;
+		}
+
+		self.emitted = true;
+		Ok(())
+	}
+
+	/// Writes the root content, separating blocks with a blank line. Runs of
+	/// phrasing content are wrapped like a paragraph.
+	fn root(&mut self, children: &'n [Content<'n>], width: Option<usize>, emit: &mut Emit) -> io::Result<()> {
+		let mut run = Lines::new();
+
+		for child in children {
+			match child {
+				Content::Phrasing(node) => self.inline(node, &mut run),
+				Content::Flow(node) => {
+					if !run.is_empty() {
+						self.emit_block(wrap(run.drain(..), width), emit)?;
+					}
+
+					let block = self.block(node, width);
+					self.emit_block(block, emit)?;
+				}
+			}
+		}
+
+		if !run.is_empty() {
+			self.emit_block(wrap(run, width), emit)?;
+		}
+
+		Ok(())
+	}
+
+	/// Writes flow content, separating blocks with a blank line if `spread` is
+	/// set.
+	fn blocks(&mut self, children: &'n [FlowContent<'n>], width: Option<usize>, spread: bool) -> Lines {
+		let mut lines = Lines::new();
+
+		for child in children {
+			let block = self.block(child, width);
+			push_block(&mut lines, block, spread);
+		}
+
+		lines
+	}
+
+	fn block(&mut self, node: &'n FlowContent<'n>, width: Option<usize>) -> Lines {
+		match node {
+			FlowContent::Align(align) => self.blocks(&align.children, width, true),
+			FlowContent::Quote(quote) => {
+				let width = width.map(|w| w.saturating_sub(2));
+				let mut lines = self.blocks(&quote.children, width, true);
+
+				if let Some(author) = &quote.author {
+					push_block(&mut lines, wrap([format!("—{author}")], width), true);
+				}
+
+				lines.into_iter()
+					 .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+					 .collect()
+			}
+			FlowContent::Code(Code { value, .. }) |
+			FlowContent::Math(Math { value, .. }) => {
+				value.lines()
+					 .map(|line| if line.is_empty() { String::new() } else { format!("    {line}") })
+					 .collect()
+			}
+			FlowContent::Heading(heading) => {
+				let mut lines = wrap(self.inlines(&heading.children), width);
+				let underline = match heading.depth {
+					1 => '=',
+					2 => '-',
+					_ => '~',
+				};
+
+				if let Some(len) = lines.iter().map(|line| line.chars().count()).max() {
+					lines.push(underline.to_string().repeat(len));
+				}
+
+				lines
+			}
+			FlowContent::Html(html) => {
+				let text = strip_tags(&html.value);
+				wrap(text.lines().map(str::to_string), width)
+					.into_iter()
+					.filter(|line| !line.is_empty())
+					.collect()
+			}
+			FlowContent::List(list) => self.list(list, width),
+			FlowContent::Content(TextContent::Paragraph(para)) => wrap(self.inlines(&para.children), width),
+			FlowContent::Spoiler(spoiler) => {
+				let mut lines = Lines::new();
+
+				if let Some(summary) = &spoiler.summary {
+					lines = wrap([summary.to_string()], width);
+				}
+
+				push_block(&mut lines, self.blocks(&spoiler.children, width, true), true);
+				lines
+			}
+			FlowContent::Table(table) => self.table(table),
+			FlowContent::ThematicBreak(_) => vec!["-".repeat(width.unwrap_or(DEFAULT_WIDTH))],
+			// Looked up by references, with footnotes listed after the last block.
+			FlowContent::Content(TextContent::Definition(_)) |
+			FlowContent::FootnoteDef(_) => Lines::new(),
+		}
+	}
+
+	/// Writes phrasing content as unwrapped lines, split at hard breaks.
+	fn inlines(&mut self, children: &'n [PhrasingContent<'n>]) -> Lines {
+		let mut lines = Lines::new();
+
+		for child in children {
+			self.inline(child, &mut lines);
+		}
+
+		lines
+	}
+
+	fn inline(&mut self, node: &'n PhrasingContent<'n>, lines: &mut Lines) {
+		match node {
+			PhrasingContent::FootnoteRef(reference) => {
+				let text = self.footnote_ref(reference);
+				append(lines, &text);
+			}
+			PhrasingContent::Link(link) => self.link(lines, &link.children, Some(&link.url)),
+			PhrasingContent::LinkRef(reference) => {
+				let url = self.definitions
+							  .definition(&reference.identifier)
+							  .map(|def| def.url.as_ref());
+				self.link(lines, &reference.children, url)
+			}
+			PhrasingContent::Static(node) => self.static_inline(node, lines),
+		}
+	}
+
+	fn static_inline(&mut self, node: &'n StaticPhrasingContent<'n>, lines: &mut Lines) {
+		match node {
+			StaticPhrasingContent::Break(_) => {
+				if lines.is_empty() {
+					lines.push(String::new());
+				}
+
+				lines.push(String::new());
+			}
+			StaticPhrasingContent::Color(Color { children, .. }) |
+			StaticPhrasingContent::Delete(Delete { children, .. }) |
+			StaticPhrasingContent::Emphasis(Emphasis { children, .. }) |
+			StaticPhrasingContent::FontSize(FontSize { children, .. }) |
+			StaticPhrasingContent::Strong(Strong { children, .. }) |
+			StaticPhrasingContent::Subscript(Subscript { children, .. }) |
+			StaticPhrasingContent::Superscript(Superscript { children, .. }) |
+			StaticPhrasingContent::Underline(Underline { children, .. }) => {
+				for child in children {
+					self.inline(child, lines);
+				}
+			}
+			StaticPhrasingContent::Embed(embed) => append(lines, &embed.url),
+			StaticPhrasingContent::Html(html) => append(lines, &strip_tags(&html.value)),
+			StaticPhrasingContent::Image(image) => image_text(lines, &image.alt, &image.url),
+			StaticPhrasingContent::ImageRef(reference) => {
+				if let Some(def) = self.definitions.definition(&reference.identifier) {
+					image_text(lines, &reference.alt, &def.url);
+				} else {
+					append(lines, &reference.alt);
+				}
+			}
+			StaticPhrasingContent::InlineCode(InlineCode { value, .. }) |
+			StaticPhrasingContent::InlineMath(InlineMath { value, .. }) |
+			StaticPhrasingContent::Text(Text { value, .. }) => append(lines, value),
+		}
+	}
+
+	fn link(&mut self, lines: &mut Lines, children: &'n [StaticPhrasingContent<'n>], url: Option<&str>) {
+		let mut text = Lines::new();
+
+		for child in children {
+			self.static_inline(child, &mut text);
+		}
+
+		let label = text.join(" ");
+		let label = label.trim();
+
+		for (i, line) in text.iter().enumerate() {
+			if i > 0 {
+				lines.push(String::new());
+			}
+
+			append(lines, line);
+		}
+
+		match url {
+			// Autolinks would only repeat themselves.
+			Some(url) if !url.is_empty() &&
+						 url != label &&
+						 url.strip_prefix("mailto:") != Some(label) => {
+				append(lines, &format!(" ({url})"));
+			}
+			_ => { }
+		}
+	}
+
+	fn list(&mut self, list: &'n List<'n>, width: Option<usize>) -> Lines {
+		let start = list.start.unwrap_or(1) as usize;
+		let markers: Vec<_> = (0..list.children.len()).map(|i|
+			if list.ordered {
+				format!("{}.", start + i)
+			} else {
+				"-".to_string()
+			}
+		).collect();
+		let marker_width = markers.iter().map(String::len).max().unwrap_or(0);
+		let indent = marker_width + 1;
+		let width = width.map(|w| w.saturating_sub(indent));
+
+		let mut lines = Lines::new();
+
+		for (item, marker) in list.children.iter().zip(markers) {
+			let mut content = self.blocks(&item.children, width, item.spread);
+
+			if let Some(checked) = item.checked {
+				let checkbox = if checked { "[x]" } else { "[ ]" };
+
+				match content.first_mut() {
+					Some(first) if !first.is_empty() => first.insert_str(0, &format!("{checkbox} ")),
+					Some(first) => first.push_str(checkbox),
+					None => content.push(checkbox.to_string()),
+				}
+			}
+
+			if content.is_empty() {
+				content.push(String::new());
+			}
+
+			if list.spread && !lines.is_empty() {
+				lines.push(String::new());
+			}
+
+			for (i, line) in content.into_iter().enumerate() {
+				lines.push(
+					if i == 0 {
+						format!("{marker:>marker_width$} {line}").trim_end().to_string()
+					} else if line.is_empty() {
+						line
+					} else {
+						format!("{:indent$}{line}", "")
+					}
+				);
+			}
+		}
+
+		lines
+	}
+
+	fn table(&mut self, table: &'n Table<'n>) -> Lines {
+		let rows: Vec<Vec<String>> = table.children.iter().map(|row|
+			row.children.iter().map(|cell| self.cell(cell)).collect()
+		).collect();
+
+		let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+		let widths: Vec<_> = (0..columns).map(|i|
+			rows.iter()
+				.filter_map(|row| row.get(i))
+				.map(|cell| cell.chars().count())
+				.max()
+				.unwrap_or(0)
+				.max(1)
+		).collect();
+
+		let rule = |fill: &str| {
+			let mut line = String::from("+");
+
+			for width in &widths {
+				line.push_str(&fill.repeat(width + 2));
+				line.push('+');
+			}
+
+			line
+		};
+
+		let mut lines = vec![rule("-")];
+
+		for (i, row) in rows.iter().enumerate() {
+			let mut line = String::from("|");
+
+			for (col, width) in widths.iter().enumerate() {
+				let cell  = row.get(col).map(String::as_str).unwrap_or_default();
+				let width = *width;
+
+				let cell = match table.align.get(col) {
+					Some(AlignKind::Right ) => format!("{cell:>width$}"),
+					Some(AlignKind::Center) => format!("{cell:^width$}"),
+					_                       => format!("{cell:<width$}"),
+				};
+
+				line.push(' ');
+				line.push_str(&cell);
+				line.push_str(" |");
+			}
+
+			lines.push(line);
+
+			// The header row is set apart from the body.
+			if i == 0 && rows.len() > 1 {
+				lines.push(rule("="));
+			}
+		}
+
+		lines.push(rule("-"));
+		lines
+	}
+
+	/// Writes a table cell on a single line.
+	fn cell(&mut self, cell: &'n TableCell<'n>) -> String {
+		let lines = self.inlines(&cell.children);
+		wrap(lines, None).join(" ")
+	}
+
+	fn footnote_ref(&mut self, reference: &FootnoteRef) -> String {
+		match self.definitions.reference_footnote(&reference.identifier) {
+			Some((index, _)) => format!("[{}]", index + 1),
+			None             => format!("[^{}]", reference.identifier),
+		}
+	}
+
+	fn footnote_section(&mut self) -> Lines {
+		let mut lines = Lines::new();
+
+		let mut i = 0;
+
+		while let Some((_, def, _)) = self.definitions.footnote(i) {
+			let marker = format!("[{}]", i + 1);
+			let indent = marker.len() + 1;
+			let content = self.blocks(&def.children, self.width.map(|w| w.saturating_sub(indent)), false);
+
+			for (j, line) in content.into_iter().enumerate() {
+				lines.push(
+					if j == 0 {
+						format!("{marker} {line}")
+					} else if line.is_empty() {
+						line
+					} else {
+						format!("{:indent$}{line}", "")
+					}
+				);
+			}
+
+			i += 1;
+		}
+
+		lines
+	}
+}
+
+/// Appends a block's lines, after a blank line if `spread` is set.
+fn push_block(lines: &mut Lines, block: Lines, spread: bool) {
+	if block.is_empty() {
+		return
+	}
+
+	if spread && !lines.is_empty() {
+		lines.push(String::new());
+	}
+
+	lines.extend(block);
+}
+
+/// Appends text to the last line, starting one if there are none.
+fn append(lines: &mut Lines, text: &str) {
+	if let Some(last) = lines.last_mut() {
+		last.push_str(text);
+	} else {
+		lines.push(text.to_string());
+	}
+}
+
+fn image_text(lines: &mut Lines, alt: &str, url: &str) {
+	match (alt.is_empty(), url.is_empty()) {
+		(false, false) => append(lines, &format!("{alt} ({url})")),
+		(false, true ) => append(lines, alt),
+		(true , _    ) => append(lines, url),
+	}
+}
+
+/// Strips tags and comments from HTML, decoding any character references.
+fn strip_tags(html: &str) -> String {
+	let tags = regex!(r"(?s)<!--.*?-->|<[^>]*>");
+	decode_entities(&tags.replace_all(html, "")).into_owned()
+}
+
+/// Collapses whitespace in each line and wraps it to the width, if any. Returns
+/// nothing if every line is blank.
+fn wrap(lines: impl IntoIterator<Item = String>, width: Option<usize>) -> Lines {
+	let mut wrapped = Lines::new();
+
+	for line in lines {
+		let mut current = String::new();
+		let mut current_len = 0;
+
+		for word in line.split_whitespace() {
+			let len = word.chars().count();
+
+			if current_len > 0 {
+				if width.is_some_and(|width| current_len + 1 + len > width) {
+					wrapped.push(current);
+					current = String::new();
+					current_len = 0;
+				} else {
+					current.push(' ');
+					current_len += 1;
+				}
+			}
+
+			current.push_str(word);
+			current_len += len;
+		}
+
+		wrapped.push(current);
+	}
+
+	if wrapped.iter().all(String::is_empty) {
+		wrapped.clear();
+	}
+
+	wrapped
+}
+
+#[cfg(all(test, feature = "markdown"))]
+mod tests {
+	use markdown::{Constructs, ParseOptions};
+
+	use crate::tmast::unist::Node;
+
+	use crate::{MarkdownFlavor, TmDoc};
+
+	fn write(markdown: &str, width: Option<usize>) -> String {
+		let options = ParseOptions {
+			constructs: Constructs { math_flow: true, math_text: true, ..Constructs::gfm() },
+			..ParseOptions::gfm()
+		};
+
+		let doc = TmDoc::parse_markdown(markdown, MarkdownFlavor::Custom(options)).expect("parse failed");
+		super::write(&doc, width)
+	}
+
+	#[test]
+	fn headings() {
+		assert_eq!(
+			write("# Title\n\nSome *meaningful* text.\n\n## Section\n\n### Subsection\n", None),
+			"Title\n=====\n\nSome meaningful text.\n\nSection\n-------\n\nSubsection\n~~~~~~~~~~\n"
+		);
+	}
+
+	#[test]
+	fn wrapping() {
+		assert_eq!(
+			write("The quick brown fox jumps over the lazy dog.\\\nA verylongwordthatdoesntfit.\n", Some(16)),
+			"The quick brown\nfox jumps over\nthe lazy dog.\nA\nverylongwordthatdoesntfit.\n"
+		);
+		assert_eq!(
+			write("Soft\nbreaks are\nspaces.\n", None),
+			"Soft breaks are spaces.\n"
+		);
+	}
+
+	#[test]
+	fn lists() {
+		assert_eq!(
+			write("9. Nine\n10. Ten and then\n    some more text\n    - Nested\n\n- [x] Done\n- [ ] Todo\n", Some(16)),
+			" 9. Nine\n\
+			10. Ten and then\n    some more\n    text\n    - Nested\n\
+			\n\
+			- [x] Done\n\
+			- [ ] Todo\n"
+		);
+	}
+
+	#[test]
+	fn links() {
+		assert_eq!(
+			write(
+				"A [link](https://example.com), <https://example.com>, [ref][r], and ![cat](cat.png).\n\n\
+				[r]: https://example.org\n",
+				None
+			),
+			"A link (https://example.com), https://example.com, ref (https://example.org), and cat (cat.png).\n"
+		);
+	}
+
+	#[test]
+	fn quotes_and_code() {
+		assert_eq!(
+			write("> Quoted\n>\n> > Nested\n\n```\nfn main() {}\n```\n\n<div>Some <b>HTML</b> &amp; more</div>\n", None),
+			"> Quoted\n>\n> > Nested\n\n    fn main() {}\n\nSome HTML & more\n"
+		);
+	}
+
+	#[test]
+	fn table() {
+		assert_eq!(
+			write("| Name | Qty | Note |\n|:--|--:|:-:|\n| Apple | 3 | ripe |\n| Fig | 12 | - |\n", None),
+			"+-------+-----+------+\n\
+			| Name  | Qty | Note |\n\
+			+=======+=====+======+\n\
+			| Apple |   3 | ripe |\n\
+			| Fig   |  12 |  -   |\n\
+			+-------+-----+------+\n"
+		);
+	}
+
+	#[test]
+	fn footnotes() {
+		assert_eq!(
+			write("Text[^a] and more[^b].\n\n[^b]: Second.\n[^a]: First.\n", None),
+			"Text[1] and more[2].\n\n[1] First.\n[2] Second.\n"
+		);
+	}
+
+	#[test]
+	fn parse() {
+		let doc = super::parse("  A *plain* line\r\nand another\n\n\nA <b>second</b> one  \n");
+
+		assert_eq!(
+			crate::ast::markdown::write(&doc),
+			"A \\*plain\\* line\\\nand another\n\nA \\<b>second\\</b> one\n"
+		);
+
+		let position = doc.0.children[1].position().unwrap();
+
+		assert_eq!((position.start.offset, position.end.offset), (32, 51));
+		assert_eq!((position.start.line, position.start.column), (5, 1));
+	}
+}
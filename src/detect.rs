@@ -0,0 +1,234 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Guesses the markup format of text from the constructs it contains: BBCode
+//! tags, HTML elements, and Markdown syntax. Text with none of these is plain
+//! text. Only formats whose features are enabled are detected.
+
+#[cfg(feature = "markdown")]
+use regex_macro::regex;
+
+#[cfg(feature = "bbcode")]
+use crate::ast::bbcode;
+#[cfg(all(feature = "bbcode", feature = "text"))]
+use crate::ast::bbcode::Dialect;
+#[cfg(feature = "text")]
+use crate::ast::text;
+#[cfg(all(feature = "markdown", feature = "text"))]
+use crate::MarkdownFlavor;
+#[cfg(feature = "text")]
+use crate::TmDoc;
+
+/// A markup format [detect_format] can recognize.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Format {
+	Markdown,
+	BBCode,
+	Html,
+	Text,
+}
+
+/// Common elements found in HTML documents and fragments.
+#[cfg(feature = "html")]
+const HTML_ELEMENTS: &[&str] = &[
+	"a", "abbr", "article", "aside", "b", "blockquote", "body", "br", "caption",
+	"cite", "code", "dd", "del", "details", "div", "dl", "dt", "em", "figure",
+	"footer", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr", "html",
+	"i", "img", "ins", "kbd", "li", "link", "main", "mark", "meta", "nav", "ol",
+	"p", "pre", "q", "s", "section", "small", "span", "strong", "sub", "summary",
+	"sup", "table", "tbody", "td", "tfoot", "th", "thead", "title", "tr", "u",
+	"ul",
+];
+
+/// Scores how likely the text is to be written in each [Format], returning every
+/// format with a confidence between `0` and `1`, most likely first.
+///
+/// BBCode is scored by the density of known tags among everything that looks
+/// like a tag, HTML likewise by its known elements, and Markdown by the weight
+/// of block and inline constructs found. Plain text takes whatever confidence
+/// the strongest of these leaves. Formats whose features are disabled are left
+/// out, except plain text, which needs no parser to recognize.
+#[cfg_attr(not(any(feature = "bbcode", feature = "html", feature = "markdown")), allow(unused_variables))]
+pub fn detect_format(text: &str) -> Vec<(Format, f32)> {
+	let mut scores = Vec::with_capacity(4);
+
+	#[cfg(feature = "markdown")]
+	scores.push((Format::Markdown, markdown_confidence(text)));
+	#[cfg(feature = "bbcode")]
+	scores.push((Format::BBCode, bbcode_confidence(text)));
+	#[cfg(feature = "html")]
+	scores.push((Format::Html, html_confidence(text)));
+
+	let markup = scores.iter().map(|&(_, confidence)| confidence).fold(0.0, f32::max);
+	scores.push((Format::Text, 1.0 - markup));
+
+	// The sort is stable, so ties keep the order above.
+	scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+	scores
+}
+
+#[cfg(feature = "text")]
+impl<'t> TmDoc<'t> {
+	/// Parses text in whichever format [detect_format] finds most likely,
+	/// returning the format with the document. Markdown is read as GFM, and BBCode
+	/// with the generic dialect, recovering from any errors. If the text can't be
+	/// parsed as HTML, the next most likely format is tried.
+	pub fn parse_auto(text: &'t str) -> (Format, Self) {
+		for (format, _) in detect_format(text) {
+			let doc = match format {
+				#[cfg(feature = "markdown")]
+				Format::Markdown => TmDoc::parse_markdown(text, MarkdownFlavor::GFM).ok(),
+				#[cfg(feature = "bbcode")]
+				Format::BBCode   => Some(bbcode::parse_lenient(text, Dialect::Generic).0),
+				#[cfg(feature = "html")]
+				Format::Html     => Self::parse_html(text).ok(),
+				Format::Text     => Some(text::parse(text)),
+				// Formats whose features are disabled are never detected.
+				#[allow(unreachable_patterns)]
+				_                => None,
+			};
+
+			if let Some(doc) = doc {
+				return (format, doc)
+			}
+		}
+
+		(Format::Text, text::parse(text))
+	}
+}
+
+/// Rises toward `1` with the weight of evidence, closing most of the remaining
+/// distance with each unit, so a single construct outweighs its absence.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+fn saturate(weight: f32) -> f32 {
+	1.0 - 0.4f32.powf(weight)
+}
+
+#[cfg(feature = "bbcode")]
+fn bbcode_confidence(text: &str) -> f32 {
+	let mut known = 0;
+	let mut total = 0;
+
+	for caps in bbcode::tag_regex().captures_iter(text) {
+		// Markdown links, like [text](url), aren't tags.
+		if text[caps.get(0).unwrap().end()..].starts_with('(') {
+			continue
+		}
+
+		total += 1;
+
+		let name = caps.name("tag").or(caps.name("endTag")).unwrap().as_str();
+		if bbcode::BUILT_IN_TAGS.contains(&name.to_ascii_lowercase().as_str()) {
+			known += 1;
+		}
+	}
+
+	if total == 0 { return 0.0 }
+
+	saturate(known as f32) * known as f32 / total as f32
+}
+
+#[cfg(feature = "html")]
+fn html_confidence(text: &str) -> f32 {
+	let Ok(dom) = tl::parse(text, tl::ParserOptions::default()) else { return 0.0 };
+	let mut known = 0;
+	let mut total = 0;
+
+	for tag in dom.nodes().iter().filter_map(tl::Node::as_tag) {
+		total += 1;
+
+		let name = tag.name().as_utf8_str().to_ascii_lowercase();
+		if HTML_ELEMENTS.contains(&name.as_str()) {
+			known += 1;
+		}
+	}
+
+	if total == 0 { return 0.0 }
+
+	saturate(known as f32) * known as f32 / total as f32
+}
+
+#[cfg(feature = "markdown")]
+fn markdown_confidence(text: &str) -> f32 {
+	// Constructs that rarely occur by accident weigh more than those that do,
+	// like a dash starting a line.
+	let constructs = [
+		(regex!(r"(?m)^ {0,3}#{1,6}[ \t]+\S"),                   2.0), // ATX heading
+		(regex!(r"(?m)^ {0,3}(```|~~~)"),                        1.0), // Code fence
+		(regex!(r"(?m)^ {0,3}\|.*\|[ \t]*$"),                    1.0), // Table row
+		(regex!(r"!?\[[^\]\n]+\]\([^)\s]+( [^)\n]*)?\)"),        2.0), // Link or image
+		(regex!(r"(?m)^ {0,3}\[[^\]\n]+\]:[ \t]*\S"),            2.0), // Definition
+		(regex!(r"(?m)^ {0,3}([-*+]|\d{1,9}[.)])[ \t]+\S"),      1.0), // List item
+		(regex!(r"(?m)^ {0,3}>"),                                1.0), // Block quote
+		(regex!(r"(\*\*|__)[^\s*_]([^*_\n]*[^\s*_])?(\*\*|__)"), 1.0), // Strong
+		(regex!(r"(^|[\s(])[*_][^\s*_][^*_\n]*[*_]([\s).,!?]|$)"), 1.0), // Emphasis
+		(regex!(r"`[^`\n]+`"),                                   1.0), // Inline code
+	];
+
+	let weight: f32 = constructs.iter()
+		.map(|(regex, weight)| regex.find_iter(text).count() as f32 * weight)
+		.sum();
+
+	saturate(weight)
+}
+
+#[cfg(all(test, feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+mod tests {
+	use crate::TmDoc;
+	use crate::tmast::{Content, FlowContent, TextContent};
+
+	use super::{detect_format, Format};
+
+	fn detect(text: &str) -> Format {
+		detect_format(text)[0].0
+	}
+
+	#[test]
+	fn formats() {
+		assert_eq!(detect("[b]Bold[/b] and [url=https://example.com]a link[/url]"), Format::BBCode);
+		assert_eq!(detect("[list]\n[*]One\n[*]Two\n[/list]"), Format::BBCode);
+		assert_eq!(detect("<p>Some <strong>bold</strong> text</p>"), Format::Html);
+		assert_eq!(detect("<!DOCTYPE html>\n<html><body><div>Hi</div></body></html>"), Format::Html);
+		assert_eq!(detect("# Title\n\nSome **bold** text and [a link](https://example.com)."), Format::Markdown);
+		assert_eq!(detect("- One\n- Two\n\n> Quoted"), Format::Markdown);
+		assert_eq!(detect("Just a sentence, with [brackets] and 1 < 2."), Format::Text);
+		assert_eq!(detect(""), Format::Text);
+	}
+
+	#[test]
+	fn confidence() {
+		let scores = detect_format("[b]Bold[/b] *and* [foo]");
+
+		assert_eq!(scores.len(), 4);
+		assert!(scores.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+		assert!(scores.iter().all(|(_, confidence)| (0.0..=1.0).contains(confidence)));
+		assert_eq!(detect_format("Plain")[0], (Format::Text, 1.0));
+	}
+
+	#[test]
+	fn parse_auto() {
+		let (format, doc) = TmDoc::parse_auto("[b]Bold");
+
+		assert_eq!(format, Format::BBCode);
+		assert_eq!(doc.text_content(), "Bold");
+
+		let (format, doc) = TmDoc::parse_auto("Line one\nline two\n\nAnother paragraph");
+
+		assert_eq!(format, Format::Text);
+		assert_eq!(doc.0.children.len(), 2);
+		assert!(matches!(doc.0.children[0], Content::Flow(FlowContent::Content(TextContent::Paragraph(_)))));
+	}
+}
@@ -0,0 +1,184 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Locating errors in their source text, and rendering them with an annotated
+//! snippet of the source.
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::ops::Range;
+
+#[cfg(feature = "html")]
+use tl::errors::ParseError as TlError;
+
+#[cfg(feature = "bbcode")]
+use crate::ast::bbcode::Error as BbError;
+use crate::tmast::unist::{Position, PositionRange};
+use crate::Error;
+
+/// An error that can be located in the text it was read from.
+pub trait Diagnostic : Display {
+	/// Returns the byte range of the error in the source, or `None` if the error
+	/// has no location.
+	fn range(&self) -> Option<Range<usize>>;
+
+	/// Returns a short label for the annotated source.
+	fn label(&self) -> Option<String> { None }
+
+	/// Resolves the error range to a [Position] in the source.
+	fn position(&self, source: &str) -> Option<Position> {
+		Some(Position::new_range(self.range()?, source))
+	}
+
+	/// Renders the error with its location and an annotated snippet of the first
+	/// line of the source it covers, like:
+	///
+	/// ```text
+	/// error: unclosed tag
+	///  --> 2:1
+	///   |
+	/// 2 | [b]text
+	///   | ^ unclosed tag
+	/// ```
+	fn render(&self, source: &str) -> String {
+		let mut out = format!("error: {self}\n");
+
+		let (Some(range), Some(position)) = (self.range(), self.position(source)) else {
+			return out
+		};
+
+		let line_num = position.start.line.to_string();
+		let gutter   = " ".repeat(line_num.len());
+		let start    = range.start.min(source.len());
+		let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+		let line_end   = source[start..].find('\n').map_or(source.len(), |i| start + i);
+		let line = source[line_start..line_end].trim_end_matches('\r');
+		let end  = range.end.clamp(start, line_start + line.len());
+
+		// Tabs are kept so the markers line up with the text.
+		let padding = source[line_start..start]
+			.chars()
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect::<String>();
+		let markers = "^".repeat(source[start..end].chars().count().max(1));
+
+		out.push_str(&format!("{gutter}--> {}:{}\n", position.start.line, position.start.column));
+		out.push_str(&format!("{gutter} |\n"));
+		out.push_str(&format!("{line_num} | {line}\n"));
+		out.push_str(&format!("{gutter} | {padding}{markers}"));
+
+		if let Some(label) = self.label() {
+			out.push(' ');
+			out.push_str(&label);
+		}
+
+		out.push('\n');
+		out
+	}
+}
+
+#[cfg(feature = "bbcode")]
+impl Diagnostic for BbError {
+	fn range(&self) -> Option<Range<usize>> {
+		Some(self.range.clone())
+	}
+
+	fn label(&self) -> Option<String> {
+		Some(self.kind.to_string())
+	}
+}
+
+#[cfg(feature = "html")]
+impl Diagnostic for TlError {
+	fn range(&self) -> Option<Range<usize>> { None }
+}
+
+impl Diagnostic for Error {
+	fn range(&self) -> Option<Range<usize>> {
+		self.range.clone()
+	}
+
+	/// Labels the error with its cause, or with the kind of BBCode error.
+	fn label(&self) -> Option<String> {
+		let source = self.source()?;
+
+		#[cfg(feature = "bbcode")]
+		if let Some(error) = source.downcast_ref::<BbError>() {
+			return error.label()
+		}
+
+		Some(source.to_string())
+	}
+
+	fn position(&self, source: &str) -> Option<Position> {
+		self.position.clone().or_else(|| Some(Position::new_range(self.range()?, source)))
+	}
+}
+
+#[cfg(all(test, feature = "bbcode"))]
+mod tests {
+	use crate::ast::bbcode::{self, Dialect};
+	use crate::tmast::unist::Point;
+
+	use super::Diagnostic;
+
+	#[test]
+	fn bbcode_position() {
+		let source = "First line\n[b]bold[/i]";
+		let error = bbcode::parse(source, Dialect::Generic).expect_err("no error");
+		let position = error.position(source).expect("no position");
+
+		assert_eq!(position.start, Point { line: 2, column: 2, offset: 12 });
+		assert_eq!(position.end,   Point { line: 2, column: 3, offset: 13 });
+	}
+
+	#[test]
+	fn render_snippet() {
+		let source = "First line\n\t[quote]Quoted [b]bold[/b]\nLast line";
+		let error = bbcode::parse(source, Dialect::Generic).expect_err("no error");
+
+		assert_eq!(
+			error.render(source),
+			"error: invalid BBCode at \"quote\": unclosed tag\n \
+			 --> 2:3\n  \
+			 |\n\
+			 2 | \t[quote]Quoted [b]bold[/b]\n  \
+			 | \t ^^^^^ unclosed tag\n"
+		);
+	}
+
+	#[test]
+	fn render_without_range() {
+		let error = crate::Error::from(std::io::Error::other("closed"));
+
+		assert_eq!(error.render(""), "error: couldn't read the input\n");
+	}
+
+	#[test]
+	fn render_unified() {
+		let source = "[b]bold";
+		let error = crate::TmDoc::parse_bbcode(source, Dialect::Generic).expect_err("no error");
+
+		assert_eq!(
+			error.render(source),
+			"error: invalid BBCode at 1:2\n \
+			 --> 1:2\n  \
+			 |\n\
+			 1 | [b]bold\n  \
+			 |  ^ unclosed tag\n"
+		);
+	}
+}
@@ -0,0 +1,223 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The error type returned when reading or parsing markup.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Error as IoError;
+use std::ops::Range;
+
+#[cfg(feature = "markdown")]
+use markdown::message::{Message, Place};
+#[cfg(feature = "html")]
+use tl::errors::ParseError as TlError;
+
+#[cfg(feature = "bbcode")]
+use crate::ast::bbcode::Error as BbError;
+use crate::tmast::unist::{Position, PositionRange};
+
+/// A [Result] with an [Error].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What went wrong.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+	/// The input couldn't be read.
+	Read,
+	/// The input couldn't be parsed as Markdown.
+	Markdown,
+	/// The input couldn't be parsed as BBCode.
+	BBCode,
+	/// The input couldn't be parsed as HTML.
+	Html,
+	/// A document couldn't be converted to or from another language's AST.
+	Convert,
+	/// No format is registered under the name given.
+	UnknownFormat,
+	/// A format option has an invalid value.
+	InvalidOption,
+	/// Any other error, such as one from a language outside the crate.
+	Other,
+}
+
+impl fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Read          => write!(f, "couldn't read the input"),
+			Self::Markdown      => write!(f, "invalid Markdown"),
+			Self::BBCode        => write!(f, "invalid BBCode"),
+			Self::Html          => write!(f, "invalid HTML"),
+			Self::Convert       => write!(f, "couldn't convert the document"),
+			Self::UnknownFormat => write!(f, "unknown format"),
+			Self::InvalidOption => write!(f, "invalid format option"),
+			Self::Other         => write!(f, "markup error"),
+		}
+	}
+}
+
+/// An error reading or parsing markup. It owns everything it holds, so it can
+/// outlive its input and be sent between threads. The error it was caused by,
+/// if any, is its [source](StdError::source).
+#[derive(Debug)]
+pub struct Error {
+	pub kind: ErrorKind,
+	/// The byte range of the error in the input, if known.
+	pub range: Option<Range<usize>>,
+	/// The position of the error in the input, if known. Errors converted without
+	/// their input only have a range; see [Error::locate].
+	pub position: Option<Position>,
+	source: Option<Box<dyn StdError + Send + Sync>>,
+}
+
+impl Error {
+	/// Creates an error without a cause or location.
+	pub fn new(kind: ErrorKind) -> Self {
+		Self { kind, range: None, position: None, source: None }
+	}
+
+	/// Creates an error caused by `source`.
+	pub fn with_source(kind: ErrorKind, source: impl Into<Box<dyn StdError + Send + Sync>>) -> Self {
+		Self { source: Some(source.into()), ..Self::new(kind) }
+	}
+
+	/// Sets the byte range of the error in the input.
+	pub fn at(mut self, range: Range<usize>) -> Self {
+		self.range = Some(range);
+		self
+	}
+
+	/// Resolves the error's range to a position in `input`, the text it was read
+	/// from. Does nothing if the error has no range.
+	pub fn locate(mut self, input: &str) -> Self {
+		if let Some(range) = &self.range {
+			self.position = Some(Position::new_range(range.clone(), input));
+		}
+		self
+	}
+
+	/// Wraps a BBCode error, locating it in `input`.
+	#[cfg(feature = "bbcode")]
+	pub(crate) fn bbcode(error: BbError, input: &str) -> Self {
+		Self::from(error).locate(input)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.kind.fmt(f)?;
+
+		if let Some(position) = &self.position {
+			write!(f, " at {}:{}", position.start.line, position.start.column)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl StdError for Error {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		self.source.as_deref().map(|source| source as _)
+	}
+}
+
+impl From<IoError> for Error {
+	fn from(error: IoError) -> Self {
+		Self::with_source(ErrorKind::Read, error)
+	}
+}
+
+#[cfg(feature = "bbcode")]
+impl From<BbError> for Error {
+	fn from(error: BbError) -> Self {
+		let range = error.range.clone();
+		Self::with_source(ErrorKind::BBCode, error).at(range)
+	}
+}
+
+#[cfg(feature = "html")]
+impl From<TlError> for Error {
+	fn from(error: TlError) -> Self {
+		Self::with_source(ErrorKind::Html, error)
+	}
+}
+
+#[cfg(feature = "markdown")]
+impl From<Message> for Error {
+	fn from(message: Message) -> Self {
+		let position = message.place.as_deref().map(|place|
+			match place {
+				Place::Position(position) => position.clone(),
+				Place::Point(point) => Position { start: point.clone(), end: point.clone() },
+			}
+		);
+		let mut error = Self::with_source(ErrorKind::Markdown, message.reason);
+		error.range    = position.as_ref().map(|Position { start, end }| start.offset..end.offset);
+		error.position = position;
+		error
+	}
+}
+
+#[cfg(all(test, feature = "bbcode"))]
+mod tests {
+	use std::error::Error as StdError;
+
+	use crate::TmDoc;
+	use crate::ast::bbcode::{self, Dialect};
+	use crate::tmast::unist::Point;
+
+	use super::{Error, ErrorKind};
+
+	fn parse(bbcode: &str) -> Error {
+		TmDoc::parse_bbcode(bbcode, Dialect::Generic).expect_err("no error")
+	}
+
+	#[test]
+	fn bbcode() {
+		let error = parse("First line\n[b]bold[/i]");
+
+		assert_eq!(error.kind, ErrorKind::BBCode);
+		assert_eq!(error.range, Some(12..13));
+		assert_eq!(error.position.as_ref().unwrap().start, Point { line: 2, column: 2, offset: 12 });
+		assert_eq!(error.to_string(), "invalid BBCode at 2:2");
+
+		let source = error.source().expect("no source");
+
+		assert!(source.downcast_ref::<bbcode::Error>().is_some());
+	}
+
+	#[test]
+	fn owned() {
+		fn read() -> Result<(), Box<dyn StdError + Send + Sync>> {
+			let input = String::from("[quote]Unclosed");
+			TmDoc::parse_bbcode(input.as_str(), Dialect::Generic)?;
+			Ok(())
+		}
+
+		let error = read().expect_err("no error");
+
+		assert_eq!(error.downcast_ref::<Error>().map(|error| error.kind), Some(ErrorKind::BBCode));
+	}
+
+	#[test]
+	fn io() {
+		let error = Error::from(std::io::Error::other("closed"));
+
+		assert_eq!(error.to_string(), "couldn't read the input");
+		assert_eq!(error.source().map(ToString::to_string).as_deref(), Some("closed"));
+		assert_eq!(error.position, None);
+	}
+}
@@ -14,39 +14,50 @@
  * limitations under the License.
  */
 
-#![feature(
-	assert_matches,
-	exclusive_range_pattern,
-	never_type,
-	option_zip,
-	trait_alias,
-)]
+#![feature(trait_alias)]
+// The HTML reader can't fail, which it spells with `!`.
+#![cfg_attr(feature = "html", feature(never_type))]
 
 pub mod ast;
+pub mod detect;
+pub mod diagnostic;
+pub mod error;
 pub(crate) mod util;
+#[cfg(feature = "markdown")]
 pub mod markdown_text;
+pub mod markup;
+pub mod registry;
 pub mod tmast;
 pub use ast::TmDoc;
+pub use detect::{detect_format, Format};
+pub use error::{Error, ErrorKind, Result};
+pub use markup::{ConvertMarkup, Markup, ReadMarkup, WriteMarkup};
+pub use registry::{FormatOptions, FormatReader, FormatRegistry, FormatWriter};
+#[cfg(feature = "markdown")]
 use markdown::{to_mdast, ParseOptions};
+#[cfg(feature = "html")]
 use tl::VDomGuard;
 
-use std::io::{Read, Error as IoError, BufReader};
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+use std::io;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+use std::io::{Read, BufReader};
 
-use ast::bbcode::{Error as BbError, self};
+#[cfg(feature = "bbcode")]
+use ast::bbcode::{self, Dialect};
+#[cfg(feature = "markdown")]
 use markdown::mdast::Node;
-use tl::{VDom, errors::ParseError as TlError};
-
-pub enum Error<P> {
-	Parse(P),
-	Read (IoError),
-}
+#[cfg(feature = "html")]
+use tl::VDom;
 
+#[cfg(feature = "markdown")]
 pub enum MarkdownFlavor {
 	CommonMark,
 	GFM,
 	Custom(ParseOptions),
 }
 
+#[cfg(feature = "markdown")]
 impl MarkdownFlavor {
 	pub fn options(self) -> ParseOptions {
 		match self {
@@ -59,125 +70,180 @@ impl MarkdownFlavor {
 
 // AST traits
 
-pub trait IntoCommonAst<E> {
-	fn into_common_ast(self) -> Result<TmDoc, E>;
+pub trait IntoCommonAst<'t, E> {
+	fn into_common_ast(self) -> Result<TmDoc<'t>, E>;
 }
 
 /// Facilitates conversion or parsing into a Markdown [Node].
+#[cfg(feature = "markdown")]
 pub trait IntoMarkdownAst {
 	/// Converts self into a Markdown [Node].
-	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node, Error<!>>;
+	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node>;
 }
 
 /// Facilitates conversion or parsing into a [TmDoc] representation of BBCode.
-pub trait IntoBBCodeAst {
+#[cfg(feature = "bbcode")]
+pub trait IntoBBCodeAst<'t> {
 	/// Converts self into a [TmDoc].
-	fn into_bbcode_ast<'t>(self) -> Result<TmDoc, Error<BbError<'t>>>;
+	fn into_bbcode_ast(self, dialect: Dialect) -> Result<TmDoc<'t>>;
 }
 
 /// Facilitates conversion or parsing into a [VDom] representation of HTML.
+#[cfg(feature = "html")]
 pub trait IntoHtmlDom<'d> {
 	/// Converts self into a [VDom].
-	fn into_html_dom(self) -> Result<VDom<'d>, Error<TlError>>;
+	fn into_html_dom(self) -> Result<VDom<'d>>;
 }
 
 /// Facilitates conversion or parsing into a [VDomGuard] representation of HTML.
+#[cfg(feature = "html")]
 pub trait IntoHtmlDomOwned {
 	/// Converts self into an owned [VDomGuard].
-	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard, Error<TlError>>;
+	///
+	/// # Safety
+	///
+	/// See [tl::parse_owned].
+	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard>;
 }
 
 // Text traits
 
+#[cfg(feature = "markdown")]
 pub trait IntoMarkdownText {
 	fn into_markdown_text(self) -> String;
+
+	/// Writes self as Markdown text to `out`. [TmDoc] writes each block as it's
+	/// finished, rather than building the whole text first.
+	fn write_markdown_text(self, out: &mut impl io::Write) -> io::Result<()> where Self : Sized {
+		out.write_all(self.into_markdown_text().as_bytes())
+	}
 }
 
+#[cfg(feature = "bbcode")]
 pub trait IntoBBCodeText {
-	fn into_bbcode_text(self) -> String;
+	fn into_bbcode_text(self, dialect: Dialect) -> String;
+
+	/// Writes self as BBCode text to `out`. [TmDoc] writes each block as it's
+	/// finished, rather than building the whole text first.
+	fn write_bbcode_text(self, dialect: Dialect, out: &mut impl io::Write) -> io::Result<()> where Self : Sized {
+		out.write_all(self.into_bbcode_text(dialect).as_bytes())
+	}
 }
 
+#[cfg(feature = "html")]
 pub trait IntoHtmlText {
 	fn into_html_text(self) -> String;
+
+	/// Writes self as HTML text to `out`. [TmDoc] writes each block as it's
+	/// finished, rather than building the whole text first.
+	fn write_html_text(self, out: &mut impl io::Write) -> io::Result<()> where Self : Sized {
+		out.write_all(self.into_html_text().as_bytes())
+	}
+}
+
+/// Facilitates conversion into readable plain text, without any markup.
+#[cfg(feature = "text")]
+pub trait IntoPlainText {
+	fn into_plain_text(self) -> String;
+
+	/// Writes self as plain text to `out`. [TmDoc] writes each block as it's
+	/// finished, rather than building the whole text first.
+	fn write_plain_text(self, out: &mut impl io::Write) -> io::Result<()> where Self : Sized {
+		out.write_all(self.into_plain_text().as_bytes())
+	}
 }
 
 // Default AST conversions
 
+#[cfg(feature = "markdown")]
 impl IntoMarkdownAst for Node {
-	fn into_markdown_ast(self, _: MarkdownFlavor) -> Result<Node, Error<!>> { Ok(self) }
+	fn into_markdown_ast(self, _: MarkdownFlavor) -> Result<Node> { Ok(self) }
 }
 
+#[cfg(feature = "markdown")]
 impl IntoMarkdownAst for &str {
-	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node, Error<!>> {
-		Ok(to_mdast(self, &flavor.options()).unwrap())
+	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node> {
+		Ok(to_mdast(self, &flavor.options())?)
 	}
 }
 
+#[cfg(feature = "markdown")]
 impl IntoMarkdownAst for String {
-	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node, Error<!>> {
+	fn into_markdown_ast(self, flavor: MarkdownFlavor) -> Result<Node> {
 		self.as_str().into_markdown_ast(flavor)
 	}
 }
 
+#[cfg(feature = "markdown")]
 impl<R : Read> IntoMarkdownAst for BufReader<R> {
-	fn into_markdown_ast(mut self, flavor: MarkdownFlavor) -> Result<Node, Error<!>> {
+	fn into_markdown_ast(mut self, flavor: MarkdownFlavor) -> Result<Node> {
 		let mut text = String::new();
 
-		self.read_to_string(&mut text).map_err(Error::Read)?;
+		self.read_to_string(&mut text)?;
 
 		text.into_markdown_ast(flavor)
 	}
 }
 
-/*impl IntoBBCodeAst for &str {
-	fn into_bbcode_ast<'t>(self) -> Result<TmDoc, Error<BbError<'t>>> {
-		bbcode::parse(self).map_err(Error::Parse)
+#[cfg(feature = "bbcode")]
+impl<'t> IntoBBCodeAst<'t> for &'t str {
+	fn into_bbcode_ast(self, dialect: Dialect) -> Result<TmDoc<'t>> {
+		bbcode::parse(self, dialect).map_err(|error| Error::bbcode(error, self))
 	}
-}*/
+}
 
-/*impl IntoBBCodeAst for String {
-	fn into_bbcode_ast<'t>(self) -> Result<TmDoc, Error<BbError<'t>>> {
-		bbcode::parse(&self).map_err(Error::Parse)
+#[cfg(feature = "bbcode")]
+impl IntoBBCodeAst<'static> for String {
+	fn into_bbcode_ast(self, dialect: Dialect) -> Result<TmDoc<'static>> {
+		self.as_str()
+			.into_bbcode_ast(dialect)
+			.map(TmDoc::into_owned)
 	}
-}*/
+}
 
-/*impl<R : Read> IntoBBCodeAst for BufReader<R> {
-	fn into_bbcode_ast<'t>(mut self) -> Result<TmDoc, Error<BbError<'t>>> {
+#[cfg(feature = "bbcode")]
+impl<R : Read> IntoBBCodeAst<'static> for BufReader<R> {
+	fn into_bbcode_ast(mut self, dialect: Dialect) -> Result<TmDoc<'static>> {
 		let mut text = String::new();
 
-		self.read_to_string(&mut text).map_err(Error::Read)?;
+		self.read_to_string(&mut text)?;
 
-		bbcode::parse(&text).map_err(Error::Parse)
+		text.into_bbcode_ast(dialect)
 	}
-}*/
+}
 
+#[cfg(feature = "html")]
 impl<'d> IntoHtmlDom<'d> for VDom<'d> {
-	fn into_html_dom(self) -> Result<VDom<'d>, Error<TlError>> { Ok(self) }
+	fn into_html_dom(self) -> Result<VDom<'d>> { Ok(self) }
 }
 
+#[cfg(feature = "html")]
 impl IntoHtmlDomOwned for VDomGuard {
-	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard, Error<TlError>> { Ok(self) }
+	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard> { Ok(self) }
 }
 
+#[cfg(feature = "html")]
 impl<'d> IntoHtmlDom<'d> for &'d str {
-	fn into_html_dom(self) -> Result<VDom<'d>, Error<TlError>> {
+	fn into_html_dom(self) -> Result<VDom<'d>> {
 		tl::parse(self, tl::ParserOptions::default())
-			.map_err(Error::Parse)
+			.map_err(Error::from)
 	}
 }
 
+#[cfg(feature = "html")]
 impl IntoHtmlDomOwned for String {
-	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard, Error<TlError>> {
+	unsafe fn into_html_dom_owned(self) -> Result<VDomGuard> {
 		tl::parse_owned(self, tl::ParserOptions::default())
-			.map_err(Error::Parse)
+			.map_err(Error::from)
 	}
 }
 
+#[cfg(feature = "html")]
 impl<R : Read> IntoHtmlDomOwned for BufReader<R> {
-	unsafe fn into_html_dom_owned(mut self) -> Result<VDomGuard, Error<TlError>> {
+	unsafe fn into_html_dom_owned(mut self) -> Result<VDomGuard> {
 		let mut text = String::new();
 
-		self.read_to_string(&mut text).map_err(Error::Read)?;
+		self.read_to_string(&mut text)?;
 
 		text.into_html_dom_owned()
 	}
@@ -185,14 +251,17 @@ impl<R : Read> IntoHtmlDomOwned for BufReader<R> {
 
 // Default text conversions
 
+#[cfg(feature = "markdown")]
 impl IntoMarkdownText for Node {
 	fn into_markdown_text(self) -> String { self.to_string() }
 }
 
+#[cfg(feature = "html")]
 impl<'d> IntoHtmlText for VDom<'d> {
 	fn into_html_text(self) -> String { self.outer_html() }
 }
 
+#[cfg(feature = "html")]
 impl IntoHtmlText for VDomGuard {
 	fn into_html_text(self) -> String {
 		self.get_ref().outer_html()
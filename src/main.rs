@@ -0,0 +1,354 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `transmark` command, converting documents between markup formats.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use transmark::{detect_format, Format, FormatOptions, FormatRegistry, TmDoc};
+use transmark::ast::bbcode::{self, Dialect};
+use transmark::diagnostic::Diagnostic;
+
+const USAGE: &str = "\
+Usage: transmark --to <FORMAT> [OPTIONS] [FILE]...
+
+Converts each FILE, or standard input if none or `-` is given, and writes the
+result to standard output.
+
+Formats:
+  md, markdown, commonmark   CommonMark Markdown
+  gfm                        GitHub Flavored Markdown
+  bbcode                     BBCode, in the dialect given by --dialect
+  phpbb, vbulletin, xenforo,
+  smf, steam                 BBCode in that dialect
+  html                       HTML
+  text                       Plain text
+
+Options:
+  -f, --from <FORMAT>     The input format, or auto (default) to detect it
+  -t, --to <FORMAT>       The output format
+  -d, --dialect <NAME>    The BBCode dialect: generic (default), phpbb,
+                          vbulletin, xenforo, smf or steam
+  -l, --lenient           Recover from BBCode errors, reporting them as warnings
+  -h, --help              Print this help
+  -V, --version           Print the version
+
+Exit status is 0 on success, 1 if an input couldn't be read or converted, and 2
+if the arguments are invalid.";
+
+/// Exit status for invalid arguments.
+const USAGE_ERROR: u8 = 2;
+
+#[derive(Debug)]
+struct Options {
+	/// The name of the input format, or `None` to detect it.
+	from: Option<String>,
+	/// The name of the output format.
+	to: String,
+	/// Options for the registered reader and writer, such as the BBCode dialect.
+	format: FormatOptions,
+	lenient: bool,
+	/// Input files, where `-` is standard input.
+	files: Vec<String>,
+}
+
+/// What the arguments ask to be done.
+#[derive(Debug)]
+enum Command {
+	Convert(Options),
+	Help,
+	Version,
+}
+
+impl Command {
+	/// Parses the arguments, with formats named as in the registry.
+	fn parse(args: impl IntoIterator<Item = String>, registry: &FormatRegistry) -> Result<Self, String> {
+		let mut args = args.into_iter();
+		let mut from    = None;
+		let mut to      = None;
+		let mut format  = FormatOptions::new();
+		let mut lenient = false;
+		let mut files   = Vec::new();
+
+		while let Some(arg) = args.next() {
+			// Values may be given as the next argument, or after `=`.
+			let (name, inline) = match arg.split_once('=') {
+				Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+				_ => (arg.clone(), None),
+			};
+			let mut value = || inline.clone()
+				.or_else(|| args.next())
+				.ok_or_else(|| format!("missing value for {name}"));
+
+			match name.as_str() {
+				"-f" | "--from"    => from = input_format(&value()?, registry)?,
+				"-t" | "--to"      => to   = Some(output_format(&value()?, registry)?),
+				"-d" | "--dialect" => { format.set("dialect", &parse_dialect(&value()?)?); }
+				"-l" | "--lenient" => lenient = true,
+				"-h" | "--help"    => return Ok(Self::Help),
+				"-V" | "--version" => return Ok(Self::Version),
+				"--"               => files.extend(args.by_ref()),
+				_ if name.starts_with('-') && name != "-" => return Err(format!("unknown option {name}")),
+				_                  => files.push(arg),
+			}
+		}
+
+		let to = to.ok_or("missing --to")?;
+
+		if files.is_empty() {
+			files.push("-".to_string());
+		}
+
+		Ok(Self::Convert(Options { from, to, format, lenient, files }))
+	}
+}
+
+/// Returns the input format name, or `None` for `auto`, which is detected here
+/// rather than by the registry, so BBCode errors aren't always recovered from.
+fn input_format(name: &str, registry: &FormatRegistry) -> Result<Option<String>, String> {
+	if name.eq_ignore_ascii_case("auto") {
+		Ok(None)
+	} else if registry.has_reader(name) {
+		Ok(Some(name.to_ascii_lowercase()))
+	} else {
+		Err(format!("unknown format {name:?}"))
+	}
+}
+
+fn output_format(name: &str, registry: &FormatRegistry) -> Result<String, String> {
+	if registry.has_writer(name) {
+		Ok(name.to_ascii_lowercase())
+	} else {
+		Err(format!("unknown format {name:?}"))
+	}
+}
+
+/// Checks a dialect name, returning it to pass to the registry.
+fn parse_dialect(name: &str) -> Result<String, String> {
+	match Dialect::from_name(name) {
+		Some(_) => Ok(name.to_string()),
+		None    => Err(format!("unknown dialect {name:?}")),
+	}
+}
+
+fn main() -> ExitCode {
+	let registry = FormatRegistry::new();
+	let options = match Command::parse(env::args().skip(1), &registry) {
+		Ok(Command::Convert(options)) => options,
+		Ok(Command::Help) => {
+			println!("{USAGE}");
+			return ExitCode::SUCCESS
+		}
+		Ok(Command::Version) => {
+			println!("transmark {}", env!("CARGO_PKG_VERSION"));
+			return ExitCode::SUCCESS
+		}
+		Err(error) => {
+			eprintln!("transmark: {error}\n\n{USAGE}");
+			return ExitCode::from(USAGE_ERROR)
+		}
+	};
+
+	let mut stdout = BufWriter::new(io::stdout().lock());
+	let mut stderr = io::stderr().lock();
+	let mut status = ExitCode::SUCCESS;
+
+	for file in &options.files {
+		let name = if file == "-" { "<stdin>" } else { file };
+		let source = if file == "-" {
+			let mut source = String::new();
+			io::stdin().read_to_string(&mut source).map(|_| source)
+		} else {
+			fs::read_to_string(file)
+		};
+
+		let source = match source {
+			Ok(source) => source,
+			Err(error) => {
+				let _ = writeln!(stderr, "{name}: error: {error}");
+				status = ExitCode::FAILURE;
+				continue
+			}
+		};
+
+		match read(&source, &options, &registry) {
+			Ok((doc, warnings)) => {
+				for warning in warnings {
+					let _ = write!(stderr, "{name}: {warning}");
+				}
+
+				if let Err(error) = registry.write_to(&options.to, &doc, &options.format, &mut stdout) {
+					let _ = writeln!(stderr, "transmark: error: {error}");
+					return ExitCode::FAILURE
+				}
+			}
+			Err(error) => {
+				let _ = write!(stderr, "{name}: {error}");
+				status = ExitCode::FAILURE;
+			}
+		}
+	}
+
+	if let Err(error) = stdout.flush() {
+		let _ = writeln!(stderr, "transmark: error: {error}");
+		return ExitCode::FAILURE
+	}
+
+	status
+}
+
+/// Parses the source, returning the document with any warnings, or the error.
+/// Errors and warnings are rendered with a snippet of the source.
+fn read<'t>(source: &'t str, options: &Options, registry: &FormatRegistry) -> Result<(TmDoc<'t>, Vec<String>), String> {
+	let from = options.from.as_deref().unwrap_or_else(||
+		match detect_format(source)[0].0 {
+			Format::Markdown => "gfm",
+			Format::BBCode   => "bbcode",
+			Format::Html     => "html",
+			Format::Text     => "text",
+		}
+	);
+
+	// Registered readers drop the errors they recover from, so lenient BBCode is
+	// parsed here to report them.
+	if let Some(dialect) = options.lenient.then(|| bbcode_dialect(from, &options.format)).flatten() {
+		let (doc, errors) = bbcode::parse_lenient(source, dialect);
+		let warnings = errors.iter().map(|error| {
+			let rendered = error.render(source);
+			rendered.strip_prefix("error")
+				.map(|rest| format!("warning{rest}"))
+				.unwrap_or(rendered)
+		}).collect();
+
+		return Ok((doc, warnings))
+	}
+
+	let doc = registry.read_with(from, source, &options.format).map_err(|error| error.render(source))?;
+	Ok((doc, Vec::new()))
+}
+
+/// Returns the dialect read by a built-in BBCode format, or `None` if the format
+/// isn't BBCode. The `--dialect` option overrides the dialect a format is named
+/// for.
+fn bbcode_dialect(format: &str, options: &FormatOptions) -> Option<Dialect> {
+	let named = match format {
+		"bbcode" => Dialect::Generic,
+		name     => Dialect::from_name(name)?,
+	};
+
+	Some(options.get("dialect").and_then(Dialect::from_name).unwrap_or(named))
+}
+
+#[cfg(test)]
+mod tests {
+	use transmark::FormatRegistry;
+
+	use super::{read, Command, Options};
+
+	fn parse(args: &[&str]) -> Result<Command, String> {
+		Command::parse(args.iter().map(|arg| arg.to_string()), &FormatRegistry::new())
+	}
+
+	fn options(args: &[&str]) -> Options {
+		match parse(args) {
+			Ok(Command::Convert(options)) => options,
+			other => panic!("not a conversion: {other:?}")
+		}
+	}
+
+	fn convert(source: &str, options: &Options) -> Result<(String, Vec<String>), String> {
+		let registry = FormatRegistry::new();
+		let (doc, warnings) = read(source, options, &registry)?;
+		let mut out = Vec::new();
+		registry.write_to(&options.to, &doc, &options.format, &mut out).expect("write failed");
+		Ok((String::from_utf8(out).expect("invalid UTF-8"), warnings))
+	}
+
+	#[test]
+	fn arguments() {
+		let options = options(&["-f", "bbcode", "--to=gfm", "--lenient", "a.bb", "-", "--", "-b.bb"]);
+
+		assert_eq!(options.from.as_deref(), Some("bbcode"));
+		assert_eq!(options.to, "gfm");
+		assert!(options.lenient);
+		assert_eq!(options.files, ["a.bb", "-", "-b.bb"]);
+
+		assert_eq!(self::options(&["--from", "md", "--to", "html"]).files, ["-"]);
+		assert_eq!(self::options(&["--to", "html"]).from, None);
+		assert_eq!(self::options(&["--from", "AUTO", "--to", "html"]).from, None);
+		assert!(matches!(parse(&["--help"]), Ok(Command::Help)));
+		assert_eq!(parse(&["--from", "md"]).unwrap_err(), "missing --to");
+		assert_eq!(parse(&["--from", "rtf"]).unwrap_err(), "unknown format \"rtf\"");
+		assert_eq!(parse(&["--to", "auto"]).unwrap_err(), "unknown format \"auto\"");
+		assert_eq!(parse(&["--to", "md", "-d", "mybb"]).unwrap_err(), "unknown dialect \"mybb\"");
+		assert_eq!(parse(&["--from"]).unwrap_err(), "missing value for --from");
+		assert_eq!(parse(&["--frob"]).unwrap_err(), "unknown option --frob");
+	}
+
+	#[test]
+	fn conversion() {
+		let (output, warnings) = convert("[b]Bold[/b] text", &options(&["-f", "bbcode", "-t", "md"])).unwrap();
+
+		assert_eq!(output, "**Bold** text\n");
+		assert!(warnings.is_empty());
+
+		let (output, _) = convert("# Title", &options(&["-f", "gfm", "-t", "bbcode"])).unwrap();
+
+		assert_eq!(output, "[h1]Title[/h1]\n");
+
+		let (output, _) = convert("[i]Detected[/i]", &options(&["-t", "html"])).unwrap();
+
+		assert_eq!(output, "<p><em>Detected</em></p>\n");
+
+		let (output, _) = convert("Plain *text*", &options(&["-f", "text", "-t", "md"])).unwrap();
+
+		assert_eq!(output, "Plain \\*text\\*\n");
+
+		let (output, _) = convert("[s]gone[/s]", &options(&["-f", "phpbb", "-t", "steam"])).unwrap();
+
+		assert_eq!(output, "[strike]gone[/strike]\n");
+
+		let (output, _) = convert("[s]gone[/s]", &options(&["-f", "phpbb", "-t", "bbcode", "-d", "steam"])).unwrap();
+
+		assert_eq!(output, "[strike]gone[/strike]\n");
+
+		let (output, _) = convert("~~gone~~", &options(&["-f", "gfm", "-t", "commonmark"])).unwrap();
+
+		assert_eq!(output, "<del>gone</del>\n");
+	}
+
+	#[test]
+	fn diagnostics() {
+		let error = convert("[b]Bold", &options(&["-f", "bbcode", "-t", "html"])).unwrap_err();
+
+		assert!(error.starts_with("error: "), "{error}");
+		assert!(error.contains(" --> 1:2"), "{error}");
+
+		let (output, warnings) = convert("[b]Bold", &options(&["-f", "bbcode", "-t", "html", "--lenient"])).unwrap();
+
+		assert_eq!(output, "<p><strong>Bold</strong></p>\n");
+		assert_eq!(warnings.len(), 1);
+		assert!(warnings[0].starts_with("warning: "), "{}", warnings[0]);
+
+		let (output, warnings) = convert("[strike]gone", &options(&["-f", "steam", "-t", "gfm", "--lenient"])).unwrap();
+
+		assert_eq!(output, "~~gone~~\n");
+		assert_eq!(warnings.len(), 1);
+	}
+}
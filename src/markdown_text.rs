@@ -14,159 +14,474 @@
  * limitations under the License.
  */
 
-use fancy_regex::Regex;
-use lazy_static::lazy_static;
+//! Escaping text for Markdown, and unescaping it. Only characters that would
+//! otherwise be read as markup where the text is placed are escaped, so the
+//! escaped text parses back to the same text.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use markdown::{decode_named, decode_numeric};
+use regex_macro::regex;
+
+/// Where escaped text is placed in a Markdown document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EscapeContext {
+	/// Text starting a line, such as the start of a paragraph.
+	LineStart,
+	/// Text following other inline content on a line.
+	Inline,
+	/// Text in a GFM table cell.
+	TableCell,
+	/// Text inside the brackets of a link or image.
+	LinkText,
+	/// A link or image destination.
+	LinkDestination,
+	/// The content of a code span. Code spans can't contain escapes, so the text
+	/// is returned with delimiting backticks that it doesn't contain.
+	CodeSpan,
+}
+
+/// Escapes text for the context it's placed in. Newlines in text are kept, and
+/// the text after them is escaped as the start of a line. Blank lines can't be
+/// kept in text, as they split paragraphs, and newlines in code spans are read
+/// as spaces.
+pub fn escape_markdown(text: &str, context: EscapeContext) -> String {
+	match context {
+		EscapeContext::CodeSpan        => code_span(text),
+		EscapeContext::LinkDestination => link_destination(text),
+		_                              => escape_text(text, context, true),
+	}
+}
+
+/// Escapes text as [escape_markdown] does, for text followed by other content on
+/// its line. Whitespace at the end of the text isn't stripped there, so it's
+/// kept as-is.
+pub(crate) fn escape_markdown_followed(text: &str, context: EscapeContext) -> String {
+	match context {
+		EscapeContext::CodeSpan        => code_span(text),
+		EscapeContext::LinkDestination => link_destination(text),
+		_                              => escape_text(text, context, false),
+	}
+}
+
+/// Escapes text. `at_end` is whether the text ends its line, where trailing
+/// whitespace would be stripped.
+fn escape_text(text: &str, context: EscapeContext, at_end: bool) -> String {
+	let mut out = String::with_capacity(text.len());
+	let mut lines = text.split('\n').peekable();
+	let mut at_start = context == EscapeContext::LineStart;
+
+	while let Some(line) = lines.next() {
+		let is_last = lines.peek().is_none();
+		escape_line(&mut out, line, at_start, !is_last || at_end, context);
+
+		if !is_last {
+			out.push('\n');
+		}
 
-lazy_static! {
-	static ref RE: Regex = Regex::new(
-		r"#{1,6}|[=-]{2,}|[*_]+|(?<=\d)\.|(?<=^|\s)[-+>]|~{2}|`{1,3}|!?\[|</?.*>"
-	).unwrap();
+		at_start = true;
+	}
+
+	out
 }
 
-pub fn escape_markdown(text: &str) -> String {
-	RE.replace_all(text, r"\$0").to_string()
+/// Escapes a line of text. `at_start` is whether the line starts a line in the
+/// document, and `at_end` whether it ends one.
+fn escape_line(out: &mut String, line: &str, at_start: bool, at_end: bool, context: EscapeContext) {
+	// Leading whitespace is stripped, and trailing whitespace is stripped or read
+	// as a hard break, so the outermost is kept as a character reference. A line
+	// starting with a reference can't start a block either.
+	let leading = if at_start {
+		line.chars().next().filter(|c| matches!(c, ' ' | '\t'))
+	} else {
+		None
+	};
+	let trailing = if at_end && line.len() > leading.map_or(0, char::len_utf8) {
+		line.chars().next_back().filter(|c| matches!(c, ' ' | '\t'))
+	} else {
+		None
+	};
+	let start = leading.map_or(0, char::len_utf8);
+	let end   = line.len() - trailing.map_or(0, char::len_utf8);
+
+	if let Some(c) = leading {
+		push_reference(out, c);
+	}
+
+	let marker = if at_start && leading.is_none() {
+		block_marker(line)
+	} else {
+		None
+	};
+
+	for (i, c) in line[start..end].char_indices() {
+		let i = start + i;
+		let before = &line[..i];
+		let after  = &line[i + c.len_utf8()..];
+		let prev = before.chars().next_back();
+		let next = after.chars().next();
+
+		let escape = marker == Some(i) || match c {
+			'\\'      => next.is_none_or(|next| next.is_ascii_punctuation()),
+			'`' | '[' => true,
+			']'       => context == EscapeContext::LinkText,
+			'|'       => context == EscapeContext::TableCell,
+			'*' | '~' => !is_inert_run(line, i, c, char::is_whitespace),
+			'_'       => !is_inert_run(line, i, c, char::is_alphanumeric),
+			'<'       => next.is_some_and(|next| next.is_ascii_alphabetic() || matches!(next, '/' | '!' | '?')),
+			'&'       => regex!(r"^&(?:#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[A-Za-z][A-Za-z0-9]{0,31});").is_match(&line[i..]),
+			// GFM autolink literals.
+			':'       => after.starts_with("//") && regex!(r"(?i)(?:^|[^A-Za-z])https?$").is_match(before),
+			'.'       => regex!(r"(?i)(?:^|[\s*_~(])www$").is_match(before),
+			'@'       => prev.is_some_and(|prev| prev.is_alphanumeric() || matches!(prev, '.' | '_' | '+' | '-')) &&
+			             next.is_some_and(char::is_alphanumeric),
+			_         => false,
+		};
+
+		if escape {
+			out.push('\\');
+		}
+
+		out.push(c);
+	}
+
+	if let Some(c) = trailing {
+		push_reference(out, c);
+	}
+}
+
+/// Returns the offset of a character at the start of a line that would start a
+/// block, such as a heading or list item, or `None` if the line is a paragraph.
+/// Markers also made of emphasis characters are escaped as emphasis.
+fn block_marker(line: &str) -> Option<usize> {
+	if regex!(r"^(?:#{1,6}(?:[ \t]|$)|>|[-+](?:[ \t]|$)|=+[ \t]*$)").is_match(line) {
+		return Some(0)
+	}
+
+	// Setext underlines, and GFM table delimiter rows.
+	if regex!(r"^\|?[ \t]*:?-+:?[ \t]*(?:\|[ \t]*:?-+:?[ \t]*)*\|?[ \t]*$").is_match(line) {
+		return line.find('-')
+	}
+
+	// Ordered list items, where the delimiter is escaped.
+	regex!(r"^[0-9]{1,9}([.)])(?:[ \t]|$)")
+		.captures(line)
+		.map(|caps| caps.get(1).unwrap().start())
+}
+
+/// Returns whether the run of `c` around `i` is inert, as the characters around
+/// the run match `is_inert`. Runs at the edges of the line aren't inert.
+fn is_inert_run(line: &str, i: usize, c: char, is_inert: fn(char) -> bool) -> bool {
+	let start = line[..i].trim_end_matches(c).len();
+	let end   = i + line[i..].len() - line[i..].trim_start_matches(c).len();
+	let prev  = line[..start].chars().next_back();
+	let next  = line[end..].chars().next();
+
+	prev.zip(next).is_some_and(|(prev, next)| is_inert(prev) && is_inert(next))
+}
+
+fn push_reference(out: &mut String, c: char) {
+	out.push_str(&format!("&#{};", c as u32));
+}
+
+/// Wraps text in the shortest backtick fence it doesn't contain, padding it with
+/// spaces where they'd otherwise be stripped or the text would join the fence.
+fn code_span(text: &str) -> String {
+	let runs = regex!("`+")
+		.find_iter(text)
+		.map(|run| run.len())
+		.collect::<HashSet<_>>();
+	let fence = "`".repeat((1..).find(|len| !runs.contains(len)).unwrap());
+	let pad = text.starts_with('`') ||
+		text.ends_with('`') ||
+		text.starts_with(' ') && text.ends_with(' ') && !text.trim_matches(' ').is_empty();
+	let pad = if pad { " " } else { "" };
+
+	format!("{fence}{pad}{text}{pad}{fence}")
+}
+
+/// Escapes a destination, enclosing it in angle brackets if it's empty or has
+/// whitespace, control characters or unbalanced parentheses.
+fn link_destination(url: &str) -> String {
+	let mut depth = 0usize;
+	let is_balanced = url.chars().all(|c| {
+		match c {
+			'(' => depth += 1,
+			')' if depth == 0 => return false,
+			')' => depth -= 1,
+			_   => { }
+		}
+
+		true
+	}) && depth == 0;
+	let is_enclosed = url.is_empty() ||
+		url.starts_with('<') ||
+		!is_balanced ||
+		url.chars().any(|c| c.is_whitespace() || c.is_control());
+
+	let mut out = String::with_capacity(url.len() + 2);
+
+	if is_enclosed {
+		out.push('<');
+	}
+
+	for (i, c) in url.char_indices() {
+		let next = url[i + c.len_utf8()..].chars().next();
+		let escape = match c {
+			'\\'      => next.is_none_or(|next| next.is_ascii_punctuation()),
+			'<' | '>' => is_enclosed,
+			'&'       => regex!(r"^&(?:#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[A-Za-z][A-Za-z0-9]{0,31});").is_match(&url[i..]),
+			_         => false,
+		};
+
+		if escape {
+			out.push('\\');
+		}
+
+		out.push(c);
+	}
+
+	if is_enclosed {
+		out.push('>');
+	}
+
+	out
+}
+
+/// Text with Markdown backslash escapes and character references resolved, which
+/// can map offsets in the text back to its source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unescaped<'t> {
+	pub value: Cow<'t, str>,
+	/// Offsets in the value and the source at the end of each replacement.
+	offsets: Vec<(usize, usize)>,
+}
+
+impl Unescaped<'_> {
+	/// Returns the offset in the source of an offset in the value. Offsets within
+	/// a replaced character map into its escape or reference.
+	pub fn source_offset(&self, offset: usize) -> usize {
+		let i = self.offsets.partition_point(|&(value, _)| value <= offset);
+		let (value, source) = i.checked_sub(1).map_or((0, 0), |i| self.offsets[i]);
+		source + offset - value
+	}
+
+	/// Returns the range in the source of a range in the value.
+	pub fn source_range(&self, range: Range<usize>) -> Range<usize> {
+		self.source_offset(range.start)..self.source_offset(range.end)
+	}
+}
+
+/// Resolves backslash escapes and character references in Markdown text, such as
+/// the content of a paragraph, to the literal text. Other markup is kept as-is,
+/// as are backslashes before characters they can't escape and unknown references.
+pub fn unescape_markdown(text: &str) -> Unescaped<'_> {
+	let pattern = regex!(r"\\([!-/:-@\[-`{-~])|&(?:#([0-9]{1,7})|#[xX]([0-9a-fA-F]{1,6})|([A-Za-z][A-Za-z0-9]{0,31}));");
+
+	let mut value   = String::new();
+	let mut offsets = Vec::new();
+	let mut last    = 0;
+
+	for caps in pattern.captures_iter(text) {
+		let all = caps.get(0).unwrap();
+		let replacement = if let Some(char) = caps.get(1) {
+			char.as_str().to_string()
+		} else if let Some(dec) = caps.get(2) {
+			decode_numeric(dec.as_str(), 10)
+		} else if let Some(hex) = caps.get(3) {
+			decode_numeric(hex.as_str(), 16)
+		} else if let Some(named) = decode_named(&caps[4], true) {
+			named
+		} else {
+			continue
+		};
+
+		value.push_str(&text[last..all.start()]);
+		value.push_str(&replacement);
+		offsets.push((value.len(), all.end()));
+		last = all.end();
+	}
+
+	if offsets.is_empty() {
+		return Unescaped { value: text.into(), offsets }
+	}
+
+	value.push_str(&text[last..]);
+	Unescaped { value: value.into(), offsets }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::escape_markdown;
+	use markdown::{to_mdast, ParseOptions};
+	use markdown::mdast::Node;
+
+	use super::{escape_markdown, unescape_markdown, EscapeContext};
+
+	const TEXTS: &[&str] = &[
+		"# Heading level 1",
+		"###### Heading level 6",
+		"####### Not a heading",
+		"Heading level 1\n===============",
+		"Heading level 2\n---------------",
+		"I just love **bold text** and __bold text__.",
+		"Love**is**bold, snake_case and a_b__c",
+		"Italicized text is the *cat's meow*, or _the cat's meow_.",
+		"A lone * star and ~ tilde, a ~~strike~~",
+		"> Dorothy followed her\n>> through many of the beautiful rooms",
+		"1. First item\n2) Second item\n3.Not an item",
+		"- First item\n+ Second item\n* Third item\n-Not an item",
+		"***\n---\n___\n- - -",
+		"At the command prompt, type `nano`. ``Use `code` ``",
+		"```json\n~~~",
+		"My favorite search engine is [Duck Duck Go](https://duckduckgo.com \"The best\").",
+		"![image](a.png) and [a] [b][c]",
+		"<https://www.markdownguide.org/basic-syntax/> and <html></html> and a < b",
+		"&amp; &#35; &#x23; & AT&T",
+		"C:\\path\\to\\file and a trailing \\",
+		"a \\* b \\\\* c",
+		"Visit www.example.com or https://example.com, or mail me@example.com",
+		"   Indented\n\tand tabbed  \nand trailing",
+		"trailing  ",
+		"a | b\n-- | --",
+		"|a|\n|:-:|",
+		"Multi — byte 🦊 _text_",
+	];
+
+	fn parse(markdown: &str) -> Node {
+		to_mdast(markdown, &ParseOptions::gfm()).expect("parse failed")
+	}
+
+	/// Returns the text of the only paragraph in the document, panicking if it has
+	/// anything else.
+	fn paragraph_text(markdown: &str) -> String {
+		let root = parse(markdown);
+		let [Node::Paragraph(para)] = root.children().unwrap().as_slice() else {
+			panic!("not a single paragraph: {markdown:?} -> {root:?}")
+		};
+
+		para.children
+			.iter()
+			.map(|child|
+				match child {
+					Node::Text(text) => text.value.as_str(),
+					_ => panic!("not text: {markdown:?} -> {root:?}")
+				}
+			)
+			.collect()
+	}
 
 	#[test]
-	fn escape() {
-		let md = r#"
-<https://www.markdownguide.org/basic-syntax/>
-
-<html></html>
-
-# Heading level 1
-## Heading level 2
-### Heading level 3
-#### Heading level 4
-##### Heading level 5
-###### Heading level 6
-
-Heading level 1
-===============
-Heading level 2
----------------
-
-I just love **bold text**.
-I just love __bold text__.
-Love**is**bold
-
-Italicized text is the *cat's meow*.
-Italicized text is the _cat's meow_.
-A*cat*meow
-
-> Dorothy followed her through many of the beautiful rooms in her castle.
-
-> Dorothy followed her through many of the beautiful rooms in her castle.
->
->> The Witch bade her clean the pots and kettles and sweep the floor and keep the fire fed with wood.
-
-1. First item
-2. Second item
-3. Third item
-4. Fourth item
-
-1. First item
-2. Second item
-3. Third item
-    1. Indented item
-    2. Indented item
-4. Fourth item
-
-- First item
-- Second item
-- Third item
-    - Indented item
-    - Indented item
-- Fourth item
-
-***
----
-_________________
-
-At the command prompt, type `nano`.
-``Use `code` in your Markdown file.``
-```json
-{
-    "firstName": "John",
-    "lastName": "Smith",
-    "age": 25
-}
-```
-
-My favorite search engine is [Duck Duck Go](https://duckduckgo.com "The best search engine for privacy").
-		"#;
-
-		let esc = r#"
-\<https://www.markdownguide.org/basic-syntax/>
-
-\<html></html>
-
-\# Heading level 1
-\## Heading level 2
-\### Heading level 3
-\#### Heading level 4
-\##### Heading level 5
-\###### Heading level 6
-
-Heading level 1
-\===============
-Heading level 2
-\---------------
-
-I just love \**bold text\**.
-I just love \__bold text\__.
-Love\**is\**bold
-
-Italicized text is the \*cat's meow\*.
-Italicized text is the \_cat's meow\_.
-A\*cat\*meow
-
-\> Dorothy followed her through many of the beautiful rooms in her castle.
-
-\> Dorothy followed her through many of the beautiful rooms in her castle.
-\>
-\>> The Witch bade her clean the pots and kettles and sweep the floor and keep the fire fed with wood.
-
-1\. First item
-2\. Second item
-3\. Third item
-4\. Fourth item
-
-1\. First item
-2\. Second item
-3\. Third item
-    1\. Indented item
-    2\. Indented item
-4\. Fourth item
-
-\- First item
-\- Second item
-\- Third item
-    \- Indented item
-    \- Indented item
-\- Fourth item
-
-\***
-\---
-\_________________
-
-At the command prompt, type \`nano\`.
-\``Use \`code\` in your Markdown file.\``
-\```json
-{
-    "firstName": "John",
-    "lastName": "Smith",
-    "age": 25
-}
-\```
+	fn minimal() {
+		for (text, context, expected) in [
+			("a - b 1. c", EscapeContext::LineStart, "a - b 1. c"),
+			("- a", EscapeContext::LineStart, r"\- a"),
+			("- a", EscapeContext::Inline, "- a"),
+			("1. a", EscapeContext::LineStart, r"1\. a"),
+			("# a #", EscapeContext::LineStart, r"\# a #"),
+			("snake_case a * b", EscapeContext::Inline, "snake_case a * b"),
+			("*a* _b_", EscapeContext::Inline, r"\*a\* \_b\_"),
+			("a | [b]", EscapeContext::Inline, r"a | \[b]"),
+			("a | [b]", EscapeContext::TableCell, r"a \| \[b]"),
+			("a | [b]", EscapeContext::LinkText, r"a | \[b\]"),
+			(r"\a \* &amp; & <b> a < b", EscapeContext::Inline, r"\a \\\* \&amp; & \<b> a < b"),
+		] {
+			assert_eq!(escape_markdown(text, context), expected, "{text:?} in {context:?}");
+		}
+	}
+
+	#[test]
+	fn code_spans() {
+		for (text, expected) in [
+			("nano", "`nano`"),
+			("Use `code`", "`` Use `code` ``"),
+			("`a``", "``` `a`` ```"),
+			(" a ", "`  a  `"),
+			("  ", "`  `"),
+		] {
+			assert_eq!(escape_markdown(text, EscapeContext::CodeSpan), expected);
+		}
+	}
+
+	#[test]
+	fn link_destinations() {
+		for (url, expected) in [
+			("https://example.com/a_(b)", "https://example.com/a_(b)"),
+			("a b", "<a b>"),
+			("a)", "<a)>"),
+			("<a>", r"<\<a\>>"),
+			("", "<>"),
+			(r"a\*&amp;", r"a\\*\&amp;"),
+		] {
+			assert_eq!(escape_markdown(url, EscapeContext::LinkDestination), expected);
+		}
+	}
 
-My favorite search engine is \[Duck Duck Go](https://duckduckgo.com "The best search engine for privacy").
-		"#;
+	#[test]
+	fn round_trip_text() {
+		for text in TEXTS {
+			let escaped = escape_markdown(text, EscapeContext::LineStart);
+			assert_eq!(paragraph_text(&escaped), *text, "escaped as {escaped:?}");
+
+			// Inline text follows other text on its line.
+			let escaped = escape_markdown(text, EscapeContext::Inline);
+			let value = paragraph_text(&format!("x{escaped}"));
+			assert_eq!(value.strip_prefix('x'), Some(*text), "escaped as {escaped:?}");
+		}
+	}
 
-		assert_eq!(escape_markdown(md), esc.to_string());
+	#[test]
+	fn round_trip_contexts() {
+		for text in TEXTS.iter().filter(|text| !text.contains('\n') && text.trim() == **text) {
+			let cell = escape_markdown(text, EscapeContext::TableCell);
+			let root = parse(&format!("| h |\n| - |\n| {cell} |"));
+			let Node::Table(table) = &root.children().unwrap()[0] else { panic!("no table: {cell:?}") };
+			let Node::TableRow(row) = &table.children[1] else { panic!("no row: {cell:?}") };
+			assert_eq!(row.children[0].to_string(), *text, "escaped as {cell:?}");
+
+			let link = escape_markdown(text, EscapeContext::LinkText);
+			let dest = escape_markdown(text, EscapeContext::LinkDestination);
+			let root = parse(&format!("[{link}]({dest})"));
+			let Node::Link(node) = &root.children().unwrap()[0].children().unwrap()[0] else {
+				panic!("no link: [{link}]({dest})")
+			};
+			assert_eq!(Node::Link(node.clone()).to_string(), *text, "escaped as {link:?}");
+			assert_eq!(node.url, *text, "escaped as {dest:?}");
+
+			let code = escape_markdown(text, EscapeContext::CodeSpan);
+			let root = parse(&code);
+			let Node::InlineCode(node) = &root.children().unwrap()[0].children().unwrap()[0] else {
+				panic!("no code span: {code:?}")
+			};
+			assert_eq!(node.value, *text, "escaped as {code:?}");
+		}
+	}
+
+	#[test]
+	fn unescape() {
+		let source = r"\*Not\* \a &amp; &#35;&#x1F98A; &bogus; \\";
+		let unescaped = unescape_markdown(source);
+
+		assert_eq!(unescaped.value, r"*Not* \a & #🦊 &bogus; \");
+		assert_eq!(unescaped.source_offset(0), 0);
+		assert_eq!(unescaped.source_offset(1), 2);
+		assert_eq!(unescaped.source_range(4..6), 5..8);
+
+		let fox = unescaped.value.find('🦊').unwrap();
+		assert_eq!(unescaped.source_range(fox..fox + '🦊'.len_utf8()), 22..31);
+		assert_eq!(&source[unescaped.source_range(fox + 4..unescaped.value.len())], r" &bogus; \\");
+
+		assert!(matches!(unescape_markdown("plain").value, std::borrow::Cow::Borrowed("plain")));
+	}
+
+	#[test]
+	fn unescape_round_trip() {
+		for context in [EscapeContext::LineStart, EscapeContext::Inline, EscapeContext::TableCell, EscapeContext::LinkText] {
+			for text in TEXTS {
+				let escaped = escape_markdown(text, context);
+				assert_eq!(unescape_markdown(&escaped).value, *text, "escaped as {escaped:?}");
+			}
+		}
 	}
 }
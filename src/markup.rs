@@ -0,0 +1,338 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The extension API for languages outside the crate. A language provides an AST
+//! that converts to and from the common [Markup] AST with [ConvertMarkup], and
+//! reads and writes its own text with [ReadMarkup] and [WriteMarkup]. [TmDoc]
+//! can then parse and write it as it does the built-in languages.
+//!
+//! The built-in languages implement these traits too: Markdown on its mdast
+//! [Node], HTML on [VDomGuard], and BBCode on [BBCode].
+
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "markdown")]
+use ::markdown::mdast::Node;
+#[cfg(feature = "html")]
+use tl::VDomGuard;
+
+use crate::{Error, TmDoc};
+#[cfg(feature = "bbcode")]
+use crate::ast::bbcode::{self, BBCode, Dialect};
+#[cfg(feature = "html")]
+use crate::IntoHtmlDomOwned;
+#[cfg(feature = "markdown")]
+use crate::{IntoMarkdownAst, MarkdownFlavor};
+#[cfg(feature = "markdown")]
+use crate::ast::markdown;
+
+/// The common AST every language converts through.
+pub type Markup<'t> = TmDoc<'t>;
+
+/// Converts a value to and from another representation of the same document,
+/// usually between a language's AST and [Markup].
+pub trait ConvertMarkup<T, E = Error> : Sized {
+	/// Converts self into `T`.
+	fn convert_into(self) -> Result<T, E>;
+
+	/// Converts `T` into a value of this type.
+	fn convert_from(value: T) -> Result<Self, E>;
+}
+
+/// Reads a language's text into its AST.
+pub trait ReadMarkup<E = Error> : Sized {
+	/// Parses text.
+	fn read_markup_text(text: &str) -> Result<Self, E>;
+
+	/// Reads and parses text from a reader. By default, this reads everything and
+	/// passes it to [ReadMarkup::read_markup_text].
+	fn read_markup<R : Read>(reader: &mut R) -> Result<Self, E> where E : From<io::Error> {
+		let mut text = String::new();
+		reader.read_to_string(&mut text)?;
+		Self::read_markup_text(&text)
+	}
+}
+
+/// Writes an AST as its language's text.
+pub trait WriteMarkup<E = Error> : Sized {
+	/// Writes self as text.
+	fn write_markup_text(self) -> Result<String, E>;
+
+	/// Writes self as text to `out`. By default, this writes the text returned by
+	/// [WriteMarkup::write_markup_text]; languages with large documents should
+	/// write as they go instead.
+	fn write_markup<W : Write>(self, out: &mut W) -> Result<(), E> where E : From<io::Error> {
+		out.write_all(self.write_markup_text()?.as_bytes())?;
+		Ok(())
+	}
+}
+
+impl<'t> TmDoc<'t> {
+	/// Parses text in the language of `A`, converting its AST to a document.
+	pub fn parse_markup<A, E>(text: &str) -> Result<Self, E>
+	where A : ReadMarkup<E> + ConvertMarkup<Self, E> {
+		A::read_markup_text(text)?.convert_into()
+	}
+
+	/// Reads and parses text in the language of `A` from a reader, converting its
+	/// AST to a document.
+	pub fn read_markup<A, E>(reader: &mut impl Read) -> Result<Self, E>
+	where A : ReadMarkup<E> + ConvertMarkup<Self, E>, E : From<io::Error> {
+		A::read_markup(reader)?.convert_into()
+	}
+
+	/// Converts the document to the AST of `A`, and writes it as text.
+	pub fn into_markup_text<A, E>(self) -> Result<String, E>
+	where A : WriteMarkup<E> + ConvertMarkup<Self, E> {
+		A::convert_from(self)?.write_markup_text()
+	}
+
+	/// Converts the document to the AST of `A`, and writes it as text to `out`.
+	pub fn write_markup_text<A, E>(self, out: &mut impl Write) -> Result<(), E>
+	where A : WriteMarkup<E> + ConvertMarkup<Self, E>, E : From<io::Error> {
+		A::convert_from(self)?.write_markup(out)
+	}
+}
+
+#[cfg(feature = "markdown")]
+impl ConvertMarkup<Markup<'static>> for Node {
+	fn convert_into(self) -> Result<Markup<'static>, Error> {
+		Ok(TmDoc(self.into()))
+	}
+
+	fn convert_from(doc: Markup<'static>) -> Result<Self, Error> {
+		Ok(doc.0.into())
+	}
+}
+
+#[cfg(feature = "markdown")]
+impl ReadMarkup for Node {
+	/// Parses GitHub Flavored Markdown.
+	fn read_markup_text(text: &str) -> Result<Self, Error> {
+		text.into_markdown_ast(MarkdownFlavor::GFM)
+	}
+}
+
+#[cfg(feature = "markdown")]
+impl WriteMarkup for Node {
+	fn write_markup_text(self) -> Result<String, Error> {
+		Ok(markdown::write(&TmDoc(self.into())))
+	}
+
+	fn write_markup<W : Write>(self, out: &mut W) -> Result<(), Error> {
+		Ok(markdown::write_to(&TmDoc(self.into()), out)?)
+	}
+}
+
+#[cfg(feature = "html")]
+impl ConvertMarkup<Markup<'static>> for VDomGuard {
+	fn convert_into(self) -> Result<Markup<'static>, Error> {
+		TmDoc::parse_html_owned(self)
+	}
+
+	fn convert_from(doc: Markup<'static>) -> Result<Self, Error> {
+		// Safety: the guard owns the text it was parsed from.
+		unsafe { doc.into_html_dom_owned() }
+	}
+}
+
+#[cfg(feature = "html")]
+impl ReadMarkup for VDomGuard {
+	fn read_markup_text(text: &str) -> Result<Self, Error> {
+		// Safety: the guard owns a copy of the text.
+		unsafe { text.to_string().into_html_dom_owned() }
+	}
+}
+
+#[cfg(feature = "html")]
+impl WriteMarkup for VDomGuard {
+	fn write_markup_text(self) -> Result<String, Error> {
+		Ok(self.get_ref().outer_html())
+	}
+}
+
+#[cfg(feature = "bbcode")]
+impl ConvertMarkup<Markup<'static>> for BBCode {
+	fn convert_into(self) -> Result<Markup<'static>, Error> {
+		Ok(self.0)
+	}
+
+	fn convert_from(doc: Markup<'static>) -> Result<Self, Error> {
+		Ok(BBCode(doc))
+	}
+}
+
+#[cfg(feature = "bbcode")]
+impl ReadMarkup for BBCode {
+	fn read_markup_text(text: &str) -> Result<Self, Error> {
+		Ok(BBCode(TmDoc::parse_bbcode(text, Dialect::Generic)?.into_owned()))
+	}
+}
+
+#[cfg(feature = "bbcode")]
+impl WriteMarkup for BBCode {
+	fn write_markup_text(self) -> Result<String, Error> {
+		Ok(bbcode::write(&self.0, Dialect::Generic))
+	}
+
+	fn write_markup<W : Write>(self, out: &mut W) -> Result<(), Error> {
+		Ok(bbcode::write_to(&self.0, Dialect::Generic, out)?)
+	}
+}
+
+#[cfg(all(test, feature = "html", feature = "markdown"))]
+mod tests {
+	use std::io::{self, Cursor};
+
+	use markdown::mdast::Node;
+	use tl::VDomGuard;
+
+	use crate::ast::markdown::write;
+	use crate::tmast::*;
+	use crate::{Error, ErrorKind, FormatRegistry, MarkdownFlavor, TmDoc};
+
+	use super::{ConvertMarkup, Markup, ReadMarkup, WriteMarkup};
+
+	/// A toy wiki language, where `= Title =` lines are headings and anything
+	/// else is a paragraph.
+	enum Wiki {
+		Title(String),
+		Para(String),
+	}
+
+	struct Page(Vec<Wiki>);
+
+	impl ConvertMarkup<Markup<'static>> for Page {
+		fn convert_into(self) -> Result<Markup<'static>, Error> {
+			let children = self.0.into_iter().map(|block| -> Content {
+				match block {
+					Wiki::Title(title) => Heading::new(1, vec![Text::new(title, None).into()], None).into(),
+					Wiki::Para (text)  => Paragraph::new(vec![Text::new(text, None).into()], None).into(),
+				}
+			}).collect();
+
+			Ok(TmDoc(Root::new(children, None)))
+		}
+
+		fn convert_from(markup: Markup<'static>) -> Result<Self, Error> {
+			let text = |children: &[PhrasingContent]| children.iter().map(|child| match child {
+				PhrasingContent::Static(StaticPhrasingContent::Text(text)) => Ok(text.value.to_string()),
+				_ => Err(Error::new(ErrorKind::Convert)),
+			}).collect::<Result<String, _>>();
+
+			markup.0.children.iter().map(|child| match child {
+				Content::Flow(FlowContent::Heading(heading)) => text(&heading.children).map(Wiki::Title),
+				Content::Flow(FlowContent::Content(TextContent::Paragraph(para))) =>
+					text(&para.children).map(Wiki::Para),
+				_ => Err(Error::new(ErrorKind::Convert)),
+			}).collect::<Result<_, _>>().map(Page)
+		}
+	}
+
+	impl ReadMarkup for Page {
+		fn read_markup_text(text: &str) -> Result<Self, Error> {
+			Ok(Page(
+				text.lines().filter(|line| !line.is_empty()).map(|line|
+					match line.strip_prefix("= ").and_then(|line| line.strip_suffix(" =")) {
+						Some(title) => Wiki::Title(title.to_string()),
+						None        => Wiki::Para(line.to_string()),
+					}
+				).collect()
+			))
+		}
+	}
+
+	impl WriteMarkup for Page {
+		fn write_markup_text(self) -> Result<String, Error> {
+			Ok(self.0.into_iter().map(|block| match block {
+				Wiki::Title(title) => format!("= {title} =\n"),
+				Wiki::Para (text)  => format!("{text}\n"),
+			}).collect())
+		}
+	}
+
+	#[test]
+	fn custom_language() {
+		let doc = TmDoc::parse_markup::<Page, _>("= Title =\n\nSome text.\n").expect("parse failed");
+
+		assert_eq!(write(&doc), "# Title\n\nSome text.\n");
+
+		let doc = TmDoc::parse_markdown("# Heading\n\nA paragraph.", MarkdownFlavor::GFM).expect("parse failed");
+
+		assert_eq!(doc.clone().into_markup_text::<Page, _>().expect("write failed"), "= Heading =\nA paragraph.\n");
+
+		let mut out = Vec::new();
+		doc.write_markup_text::<Page, _>(&mut out).expect("write failed");
+
+		assert_eq!(out, b"= Heading =\nA paragraph.\n");
+
+		let doc = TmDoc::read_markup::<Page, Error>(&mut Cursor::new("= Read =")).expect("read failed");
+
+		assert_eq!(doc.text_content(), "Read");
+	}
+
+	#[test]
+	fn read_error() {
+		struct Broken;
+
+		impl io::Read for Broken {
+			fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+				Err(io::Error::other("broken"))
+			}
+		}
+
+		let error = TmDoc::read_markup::<Page, Error>(&mut Broken).expect_err("no error");
+
+		assert_eq!(error.kind, ErrorKind::Read);
+	}
+
+	#[test]
+	fn built_in() {
+		let doc = TmDoc::parse_markup::<Node, _>("Some *text*").expect("parse failed");
+
+		assert_eq!(doc, TmDoc::parse_markdown("Some *text*", MarkdownFlavor::GFM).expect("parse failed"));
+		assert_eq!(doc.clone().into_markup_text::<Node, _>().expect("write failed"), "Some *text*\n");
+
+		let html = doc.clone().into_markup_text::<VDomGuard, _>().expect("write failed");
+
+		assert_eq!(html, "<p>Some <em>text</em></p>\n");
+		assert_eq!(write(&TmDoc::parse_markup::<VDomGuard, _>(&html).expect("parse failed")), write(&doc));
+
+		let node = Node::convert_from(doc.clone()).expect("conversion failed");
+
+		assert_eq!(node.convert_into().expect("conversion failed"), doc);
+	}
+
+	#[test]
+	#[cfg(feature = "bbcode")]
+	fn built_in_bbcode() {
+		use crate::ast::bbcode::BBCode;
+
+		let doc = TmDoc::parse_markup::<BBCode, _>("[b]Bold[/b] text").expect("parse failed");
+
+		assert_eq!(write(&doc), "**Bold** text\n");
+		assert_eq!(doc.into_markup_text::<BBCode, _>().expect("write failed"), "[b]Bold[/b] text\n");
+	}
+
+	#[test]
+	fn registered() {
+		let mut registry = FormatRegistry::new();
+		registry.register_markup::<Page>("wiki");
+
+		assert_eq!(registry.convert("wiki", "html", "= Title =").unwrap(), "<h1>Title</h1>\n");
+		assert_eq!(registry.convert("gfm", "wiki", "Some text").unwrap(), "Some text\n");
+	}
+}
@@ -0,0 +1,412 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Formats selected by name at runtime, for conversions between formats that
+//! aren't known until the program runs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+#[cfg(feature = "bbcode")]
+use crate::ast::bbcode::{self, Dialect};
+#[cfg(feature = "html")]
+use crate::ast::html;
+#[cfg(feature = "markdown")]
+use crate::ast::markdown;
+#[cfg(feature = "text")]
+use crate::ast::text;
+#[cfg(feature = "markdown")]
+use crate::MarkdownFlavor;
+use crate::{ConvertMarkup, Error, ErrorKind, ReadMarkup, Result, TmDoc, WriteMarkup};
+
+/// Parses text into a document with the given options. Text may be borrowed from
+/// the input.
+pub trait FormatReader = for<'t> Fn(&'t str, &FormatOptions) -> Result<TmDoc<'t>> + Send + Sync;
+
+/// Writes a document to `out` with the given options.
+pub trait FormatWriter = Fn(&TmDoc, &FormatOptions, &mut dyn io::Write) -> Result<()> + Send + Sync;
+
+/// String options for a format, such as a BBCode dialect. Options given when a
+/// format is registered are overridden by those given for a conversion.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+	options: BTreeMap<String, String>,
+}
+
+impl FormatOptions {
+	pub fn new() -> Self { Self::default() }
+
+	/// Sets an option, returning the options.
+	pub fn with(mut self, key: &str, value: &str) -> Self {
+		self.set(key, value);
+		self
+	}
+
+	/// Sets an option, replacing any value it had.
+	pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+		self.options.insert(key.to_string(), value.to_string());
+		self
+	}
+
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.options.get(key).map(String::as_str)
+	}
+
+	/// Returns an error for an option with an invalid value.
+	pub fn invalid(&self, key: &str) -> Error {
+		let value = self.get(key).unwrap_or_default();
+		Error::with_source(ErrorKind::InvalidOption, format!("invalid value {value:?} for {key}"))
+	}
+
+	/// Returns these options, with any set in `overrides` replaced.
+	fn merge(&self, overrides: &Self) -> Self {
+		let mut options = self.clone();
+		options.options.extend(overrides.options.clone());
+		options
+	}
+}
+
+impl<K : Into<String>, V : Into<String>> FromIterator<(K, V)> for FormatOptions {
+	fn from_iter<I : IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		Self { options: iter.into_iter().map(|(key, value)| (key.into(), value.into())).collect() }
+	}
+}
+
+struct Registered<H : ?Sized> {
+	options: FormatOptions,
+	handler: Arc<H>,
+}
+
+impl<H : ?Sized> Clone for Registered<H> {
+	fn clone(&self) -> Self {
+		Self { options: self.options.clone(), handler: self.handler.clone() }
+	}
+}
+
+/// Readers and writers registered by name, so formats can be chosen at runtime.
+/// [FormatRegistry::new] starts with the built-in formats:
+///
+/// - `markdown`, `md` and `commonmark` read and write CommonMark, and `gfm` reads
+///   and writes GitHub Flavored Markdown.
+/// - `bbcode` reads and writes BBCode, with the `dialect` option naming a
+///   [Dialect], and `lenient` set to `true` to recover from errors. Each dialect
+///   is also registered under its own name, such as `phpbb`.
+/// - `html` reads and writes HTML.
+/// - `text` reads and writes plain text, wrapped at the `width` option, or not
+///   wrapped if it's `none`.
+/// - `auto` reads whichever format [detect_format](crate::detect_format) finds
+///   most likely.
+///
+/// Each is only registered if its feature is enabled; `auto` needs `text`.
+#[derive(Clone)]
+pub struct FormatRegistry {
+	readers: HashMap<String, Registered<dyn FormatReader>>,
+	writers: HashMap<String, Registered<dyn FormatWriter>>,
+}
+
+impl FormatRegistry {
+	/// Creates a registry with the built-in formats.
+	pub fn new() -> Self {
+		let mut registry = Self::empty();
+		registry.register_built_in();
+		registry
+	}
+
+	/// Registers the built-in formats whose features are enabled.
+	fn register_built_in(&mut self) {
+		#[cfg(feature = "markdown")]
+		self.register_markdown();
+		#[cfg(feature = "bbcode")]
+		self.register_bbcode();
+		#[cfg(feature = "html")]
+		self.register_html();
+		#[cfg(feature = "text")]
+		self.register_text();
+	}
+
+	#[cfg(feature = "markdown")]
+	fn register_markdown(&mut self) {
+		for name in ["markdown", "md", "commonmark"] {
+			self.register_reader(name, FormatOptions::new(), |text, _| TmDoc::parse_markdown(text, MarkdownFlavor::CommonMark));
+			self.register_writer(name, FormatOptions::new(), |doc, _, out| Ok(markdown::write_commonmark_to(doc, out)?));
+		}
+
+		self.register_reader("gfm", FormatOptions::new(), |text, _| TmDoc::parse_markdown(text, MarkdownFlavor::GFM));
+		self.register_writer("gfm", FormatOptions::new(), |doc, _, out| Ok(markdown::write_to(doc, out)?));
+	}
+
+	#[cfg(feature = "bbcode")]
+	fn register_bbcode(&mut self) {
+		self.register_reader("bbcode", FormatOptions::new(), read_bbcode);
+		self.register_writer("bbcode", FormatOptions::new(), write_bbcode);
+
+		for name in ["phpbb", "vbulletin", "xenforo", "smf", "steam"] {
+			let options = FormatOptions::new().with("dialect", name);
+			self.register_reader(name, options.clone(), read_bbcode);
+			self.register_writer(name, options, write_bbcode);
+		}
+	}
+
+	#[cfg(feature = "html")]
+	fn register_html(&mut self) {
+		self.register_reader("html", FormatOptions::new(), |text, _| TmDoc::parse_html(text));
+		self.register_writer("html", FormatOptions::new(), |doc, _, out| Ok(html::write_to(doc, out)?));
+	}
+
+	#[cfg(feature = "text")]
+	fn register_text(&mut self) {
+		self.register_reader("text", FormatOptions::new(), |text, _| Ok(text::parse(text)));
+		self.register_writer("text", FormatOptions::new(), |doc, options, out| {
+			let width = match options.get("width") {
+				None         => Some(text::DEFAULT_WIDTH),
+				Some("none") => None,
+				Some(width)  => Some(width.parse().map_err(|_| options.invalid("width"))?),
+			};
+
+			Ok(text::write_to(doc, width, out)?)
+		});
+
+		self.register_reader("auto", FormatOptions::new(), |text, _| Ok(TmDoc::parse_auto(text).1));
+	}
+
+	/// Creates a registry without any formats.
+	pub fn empty() -> Self {
+		Self { readers: HashMap::new(), writers: HashMap::new() }
+	}
+
+	/// Registers a reader, replacing any reader of the same name. Names are case-
+	/// insensitive.
+	pub fn register_reader(&mut self, name: &str, options: FormatOptions, reader: impl FormatReader + 'static) -> &mut Self {
+		self.readers.insert(
+			name.to_ascii_lowercase(),
+			Registered { options, handler: Arc::new(reader) }
+		);
+		self
+	}
+
+	/// Registers a writer, replacing any writer of the same name. Names are case-
+	/// insensitive.
+	pub fn register_writer(&mut self, name: &str, options: FormatOptions, writer: impl FormatWriter + 'static) -> &mut Self {
+		self.writers.insert(
+			name.to_ascii_lowercase(),
+			Registered { options, handler: Arc::new(writer) }
+		);
+		self
+	}
+
+	/// Registers a language implementing the [markup](crate::markup) traits as
+	/// both a reader and a writer.
+	pub fn register_markup<A>(&mut self, name: &str) -> &mut Self
+	where A : ReadMarkup + WriteMarkup + ConvertMarkup<TmDoc<'static>> + 'static {
+		self.register_reader(name, FormatOptions::new(), |text, _| TmDoc::parse_markup::<A, _>(text));
+		self.register_writer(name, FormatOptions::new(), |doc, _, mut out| doc.clone().into_owned().write_markup_text::<A, _>(&mut out))
+	}
+
+	/// Removes a registered reader, returning whether it was registered.
+	pub fn unregister_reader(&mut self, name: &str) -> bool {
+		self.readers.remove(&name.to_ascii_lowercase()).is_some()
+	}
+
+	/// Removes a registered writer, returning whether it was registered.
+	pub fn unregister_writer(&mut self, name: &str) -> bool {
+		self.writers.remove(&name.to_ascii_lowercase()).is_some()
+	}
+
+	pub fn has_reader(&self, name: &str) -> bool {
+		self.readers.contains_key(&name.to_ascii_lowercase())
+	}
+
+	pub fn has_writer(&self, name: &str) -> bool {
+		self.writers.contains_key(&name.to_ascii_lowercase())
+	}
+
+	/// Returns the names of every reader, in no particular order.
+	pub fn readers(&self) -> impl Iterator<Item = &str> {
+		self.readers.keys().map(String::as_str)
+	}
+
+	/// Returns the names of every writer, in no particular order.
+	pub fn writers(&self) -> impl Iterator<Item = &str> {
+		self.writers.keys().map(String::as_str)
+	}
+
+	/// Parses text with the reader registered under `name`.
+	pub fn read<'t>(&self, name: &str, text: &'t str) -> Result<TmDoc<'t>> {
+		self.read_with(name, text, &FormatOptions::new())
+	}
+
+	/// Parses text with the reader registered under `name`, overriding its options.
+	pub fn read_with<'t>(&self, name: &str, text: &'t str, options: &FormatOptions) -> Result<TmDoc<'t>> {
+		let reader = lookup(&self.readers, "reader", name)?;
+		(reader.handler)(text, &reader.options.merge(options))
+	}
+
+	/// Writes a document with the writer registered under `name`.
+	pub fn write(&self, name: &str, doc: &TmDoc) -> Result<String> {
+		self.write_with(name, doc, &FormatOptions::new())
+	}
+
+	/// Writes a document with the writer registered under `name`, overriding its
+	/// options.
+	pub fn write_with(&self, name: &str, doc: &TmDoc, options: &FormatOptions) -> Result<String> {
+		let mut out = Vec::new();
+		self.write_to(name, doc, options, &mut out)?;
+		String::from_utf8(out).map_err(|error| Error::with_source(ErrorKind::Other, error))
+	}
+
+	/// Writes a document to `out` with the writer registered under `name`,
+	/// overriding its options.
+	pub fn write_to(&self, name: &str, doc: &TmDoc, options: &FormatOptions, out: &mut dyn io::Write) -> Result<()> {
+		let writer = lookup(&self.writers, "writer", name)?;
+		(writer.handler)(doc, &writer.options.merge(options), out)
+	}
+
+	/// Converts text from one format to another by name.
+	pub fn convert(&self, from: &str, to: &str, text: &str) -> Result<String> {
+		self.convert_with(from, to, text, &FormatOptions::new())
+	}
+
+	/// Converts text from one format to another by name, overriding the options of
+	/// both the reader and the writer.
+	pub fn convert_with(&self, from: &str, to: &str, text: &str, options: &FormatOptions) -> Result<String> {
+		// Look the writer up first, so an unknown name fails before parsing.
+		lookup(&self.writers, "writer", to)?;
+
+		let doc = self.read_with(from, text, options)?;
+		self.write_with(to, &doc, options)
+	}
+}
+
+impl Default for FormatRegistry {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for FormatRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut readers = self.readers().collect::<Vec<_>>();
+		let mut writers = self.writers().collect::<Vec<_>>();
+		readers.sort_unstable();
+		writers.sort_unstable();
+
+		f.debug_struct("FormatRegistry")
+		 .field("readers", &readers)
+		 .field("writers", &writers)
+		 .finish()
+	}
+}
+
+fn lookup<'r, H : ?Sized>(handlers: &'r HashMap<String, Registered<H>>, kind: &str, name: &str) -> Result<&'r Registered<H>> {
+	handlers.get(&name.to_ascii_lowercase()).ok_or_else(||
+		Error::with_source(ErrorKind::UnknownFormat, format!("no {kind} named {name:?}"))
+	)
+}
+
+#[cfg(feature = "bbcode")]
+fn dialect(options: &FormatOptions) -> Result<Dialect> {
+	match options.get("dialect") {
+		None       => Ok(Dialect::Generic),
+		Some(name) => Dialect::from_name(name).ok_or_else(|| options.invalid("dialect")),
+	}
+}
+
+#[cfg(feature = "bbcode")]
+fn read_bbcode<'t>(text: &'t str, options: &FormatOptions) -> Result<TmDoc<'t>> {
+	let dialect = dialect(options)?;
+
+	match options.get("lenient") {
+		None | Some("false") => TmDoc::parse_bbcode(text, dialect),
+		Some("true")         => Ok(bbcode::parse_lenient(text, dialect).0),
+		Some(_)              => Err(options.invalid("lenient")),
+	}
+}
+
+#[cfg(feature = "bbcode")]
+fn write_bbcode(doc: &TmDoc, options: &FormatOptions, out: &mut dyn io::Write) -> Result<()> {
+	Ok(bbcode::write_to(doc, dialect(options)?, out)?)
+}
+
+#[cfg(all(test, feature = "bbcode", feature = "markdown"))]
+mod tests {
+	use std::error::Error as StdError;
+
+	use crate::{ErrorKind, TmDoc};
+	use crate::tmast::{Paragraph, Root, Text};
+
+	use super::{FormatOptions, FormatRegistry};
+
+	#[test]
+	#[cfg(all(feature = "html", feature = "text"))]
+	fn built_in() {
+		let registry = FormatRegistry::new();
+
+		assert_eq!(registry.convert("bbcode", "gfm", "[b]Bold[/b] text").unwrap(), "**Bold** text\n");
+		assert_eq!(registry.convert("GFM", "html", "~~Gone~~").unwrap(), "<p><del>Gone</del></p>\n");
+		assert_eq!(registry.convert("gfm", "commonmark", "~~Gone~~").unwrap(), "<del>Gone</del>\n");
+		assert_eq!(registry.convert("md", "gfm", "~~Kept~~").unwrap(), "\\~\\~Kept\\~\\~\n");
+		assert_eq!(registry.convert("html", "steam", "<h4>Deep</h4>").unwrap(), "[h3]Deep[/h3]\n");
+		assert_eq!(registry.convert("auto", "text", "[i]Detected[/i]").unwrap(), "Detected\n");
+	}
+
+	#[test]
+	#[cfg(feature = "text")]
+	fn options() {
+		let registry = FormatRegistry::new();
+		let lenient = FormatOptions::new().with("lenient", "true");
+
+		assert_eq!(registry.convert("bbcode", "md", "[b]Bold").unwrap_err().kind, ErrorKind::BBCode);
+		assert_eq!(registry.convert_with("bbcode", "md", "[b]Bold", &lenient).unwrap(), "**Bold**\n");
+
+		let narrow = FormatOptions::new().with("width", "5");
+
+		assert_eq!(registry.convert_with("md", "text", "Some words", &narrow).unwrap(), "Some\nwords\n");
+
+		let error = registry.convert_with("md", "bbcode", "Text", &[("dialect", "mybb")].into_iter().collect()).unwrap_err();
+
+		assert_eq!(error.kind, ErrorKind::InvalidOption);
+		assert_eq!(error.source().unwrap().to_string(), "invalid value \"mybb\" for dialect");
+	}
+
+	#[test]
+	fn custom() {
+		let mut registry = FormatRegistry::new();
+
+		registry
+			.register_reader("lines", FormatOptions::new().with("separator", "|"), |text, options| {
+				let separator = options.get("separator").unwrap_or("\n");
+				let children = text.split(separator).map(|line|
+					Paragraph::new(vec![Text::new(line, None).into()], None).into()
+				).collect();
+
+				Ok(TmDoc(Root::new(children, None)))
+			})
+			.register_writer("length", FormatOptions::new(), |doc, _, out| {
+				Ok(write!(out, "{}", doc.text_content().len())?)
+			});
+
+		assert_eq!(registry.convert("lines", "md", "One|Two").unwrap(), "One\n\nTwo\n");
+		assert_eq!(registry.convert("bbcode", "length", "[b]Four[/b]").unwrap(), "4");
+		assert_eq!(registry.convert("lines", "length", "a;bc").unwrap(), "4");
+
+		let error = registry.convert("rtf", "length", "").unwrap_err();
+
+		assert_eq!(error.kind, ErrorKind::UnknownFormat);
+		assert_eq!(error.source().unwrap().to_string(), "no reader named \"rtf\"");
+		assert!(registry.unregister_writer("length"));
+		assert!(!registry.has_writer("length"));
+	}
+}
@@ -16,15 +16,39 @@
 
 //! A common Abstract Syntax Tree for markup languages all supported by TransMark,
 //! based on Markdown syntax. Draws heavily on on the [markdown] crate's [mdast](markdown::mdast)
-//! implementation, but unlike [markdown], string slices are used instead of owned
-//! strings. Also, Markdown extensions such as MDX and Frontmatter are not supported.
+//! implementation, but unlike [markdown], strings are borrowed from the source
+//! where possible instead of always owned. Also, Markdown extensions such as MDX
+//! and Frontmatter are not supported.
+//!
+//! With the `markdown` feature, trees convert losslessly to and from [mdast](markdown::mdast)
+//! with [From]. Nodes mdast has no equivalent of, including a [Quote] with an
+//! author, are kept as MDX JSX elements.
+//!
+//! Trees can be walked with [Visit], [VisitMut] and [Fold], without matching over
+//! each content enum by hand.
+//!
+//! With the `serde` feature, nodes serialize to [mdast](https://github.com/syntax-tree/mdast)
+//! JSON, with `type`, `children` and `position` fields, and can be read back from
+//! it. Strings are always owned when deserialized. HTML directly in the root is
+//! read as block HTML, and a Quote's author is kept in an extra `author` field.
+#[cfg(feature = "markdown")]
+mod convert;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+pub(crate) mod definitions;
+pub mod fold;
 pub mod unist;
+pub mod visit;
+pub mod visit_mut;
+
+use std::borrow::Cow;
 
-use markdown::mdast::AlignKind;
 use property::Property;
 
 use unist::{Parent, Position, Literal, Node};
-pub use markdown::mdast::ReferenceKind;
+pub use markdown::mdast::{AlignKind, ReferenceKind};
+pub use fold::Fold;
+pub use visit::Visit;
+pub use visit_mut::VisitMut;
 
 // Macros to generate Node trait boilerplate. A bit overkill, but I don't feel like
 // writing all of these out by hand.
@@ -59,14 +83,34 @@ macro_rules! impl_node {
 	};
 }
 
+macro_rules! impl_node_enum {
+	($($name:ident { $($variant:ident),+ $(,)? })+) => {
+		$(
+			impl Node for $name<'_> {
+				fn position(&self) -> Option<Position> {
+					match self {
+						$(Self::$variant(node) => node.position()),+
+					}
+				}
+
+				fn set_position(&mut self, position: Option<Position>) {
+					match self {
+						$(Self::$variant(node) => node.set_position(position)),+
+					}
+				}
+			}
+		)+
+	};
+}
+
 macro_rules! impl_literal {
 	($($name:ident)+) => {
 		$(
 			impl<'t> Literal<'t> for $name<'t> {
-				fn value(&self) -> &'t str { self.value }
+				fn value(&self) -> &str { &self.value }
 
-				fn set_value(&mut self, value: &'t str) {
-					self.value = value;
+				fn set_value(&mut self, value: impl Into<Cow<'t, str>>) {
+					self.value = value.into();
 				}
 			}
 		)+
@@ -77,7 +121,7 @@ macro_rules! impl_parent {
 	($($name:ident<$child:ident>)+) => {
 		$(
 			impl<'t> Parent<$child<'t>> for $name<'t> {
-				fn children<'c>(&'c self) -> &'c [$child<'t>] {
+				fn children(&self) -> &[$child<'t>] {
 					self.children.as_slice()
 				}
 
@@ -93,15 +137,15 @@ macro_rules! impl_resource {
 	($($name:ident)+) => {
 		$(
 			impl<'t> Resource<'t> for $name<'t> {
-				fn url(&self) -> &'t str { self.url }
-				fn title(&self) -> Option<&'t str> {
-					self.title
+				fn url(&self) -> &str { &self.url }
+				fn title(&self) -> Option<&str> {
+					self.title.as_deref()
 				}
 			
-				fn set_url(&mut self, url: &'t str) {
-					self.url = url;
+				fn set_url(&mut self, url: impl Into<Cow<'t, str>>) {
+					self.url = url.into();
 				}
-				fn set_title(&mut self, title: Option<&'t str>) {
+				fn set_title(&mut self, title: Option<Cow<'t, str>>) {
 					self.title = title;
 				}
 			}
@@ -113,13 +157,13 @@ macro_rules! impl_association {
 	($($name:ident)+) => {
 		$(
 			impl<'t> Association<'t> for $name<'t> {
-				fn identifier(&self) -> &'t str         { self.identifier }
-				fn label     (&self) -> Option<&'t str> { self.label      }
+				fn identifier(&self) -> &str         { &self.identifier       }
+				fn label     (&self) -> Option<&str> { self.label.as_deref() }
 			
-				fn set_identifier(&mut self, identifier: &'t str) {
-					self.identifier = identifier;
+				fn set_identifier(&mut self, identifier: impl Into<Cow<'t, str>>) {
+					self.identifier = identifier.into();
 				}
-				fn set_label(&mut self, label: Option<&'t str>) {
+				fn set_label(&mut self, label: Option<Cow<'t, str>>) {
 					self.label = label;
 				}
 			}
@@ -145,10 +189,10 @@ macro_rules! impl_alternative {
 	($($name:ident)+) => {
 		$(
 			impl<'t> Alternative<'t> for $name<'t> {
-				fn alt(&self) -> Option<&'t str> { self.alt }
+				fn alt(&self) -> &str { &self.alt }
 			
-				fn set_alt(&mut self, alt: Option<&'t str>) {
-					self.alt = alt;
+				fn set_alt(&mut self, alt: impl Into<Cow<'t, str>>) {
+					self.alt = alt.into();
 				}
 			}
 		)+
@@ -159,8 +203,8 @@ macro_rules! impl_literal_cstr {
 	($($name:ident)+) => {
 		$(
 			impl<'t> $name<'t> {
-				pub fn new(value: &'t str, position: Option<Position>) -> Self {
-					Self { value, position }
+				pub fn new(value: impl Into<Cow<'t, str>>, position: Option<Position>) -> Self {
+					Self { value: value.into(), position }
 				}
 			}
 		)+
@@ -192,67 +236,108 @@ macro_rules! impl_node_cstr {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(untagged)
+)]
 pub enum Content<'t> {
 	Flow(FlowContent<'t>),
-	Phasing(PhrasingContent<'t>),
+	Phrasing(PhrasingContent<'t>),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename_all = "camelCase")
+)]
 pub enum FlowContent<'t> {
+	Align(Align<'t>),
 	Code(Code<'t>),
-	Content(TextContent<'t>),
+	#[cfg_attr(feature = "serde", serde(rename = "footnoteDefinition"))]
 	FootnoteDef(FootnoteDef<'t>),
 	Heading(Heading<'t>),
 	Html(Html<'t>),
 	List(List<'t>),
 	Math(Math<'t>),
+	#[cfg_attr(feature = "serde", serde(rename = "blockquote"))]
 	Quote(Quote<'t>),
+	Spoiler(Spoiler<'t>),
 	Table(Table<'t>),
 	ThematicBreak(ThematicBreak),
+	// Untagged variants must come last, and are tried after the others.
+	#[cfg_attr(feature = "serde", serde(untagged))]
+	Content(TextContent<'t>),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename_all = "camelCase")
+)]
 pub enum PhrasingContent<'t> {
-	FootnoteRef(FootnoteDef<'t>),
+	#[cfg_attr(feature = "serde", serde(rename = "footnoteReference"))]
+	FootnoteRef(FootnoteRef<'t>),
 	Link(Link<'t>),
+	#[cfg_attr(feature = "serde", serde(rename = "linkReference"))]
 	LinkRef(LinkReference<'t>),
+	#[cfg_attr(feature = "serde", serde(untagged))]
 	Static(StaticPhrasingContent<'t>)
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename_all = "camelCase")
+)]
 pub enum StaticPhrasingContent<'t> {
 	Break(Break),
+	Color(Color<'t>),
 	Delete(Delete<'t>),
+	Embed(Embed<'t>),
 	Emphasis(Emphasis<'t>),
+	FontSize(FontSize<'t>),
 	Html(Html<'t>),
 	Image(Image<'t>),
+	#[cfg_attr(feature = "serde", serde(rename = "imageReference"))]
 	ImageRef(ImageReference<'t>),
 	InlineCode(InlineCode<'t>),
 	InlineMath(InlineMath<'t>),
 	Strong(Strong<'t>),
+	Subscript(Subscript<'t>),
+	Superscript(Superscript<'t>),
 	Text(Text<'t>),
+	Underline(Underline<'t>),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename_all = "camelCase")
+)]
 pub enum TextContent<'t> {
 	Definition(Definition<'t>),
 	Paragraph(Paragraph<'t>),
 }
 
 pub trait Resource<'t> {
-	fn url  (&self) -> &'t str;
-	fn title(&self) -> Option<&'t str>;
+	fn url  (&self) -> &str;
+	fn title(&self) -> Option<&str>;
 
-	fn set_url  (&mut self, url  : &'t str);
-	fn set_title(&mut self, title: Option<&'t str>);
+	fn set_url  (&mut self, url  : impl Into<Cow<'t, str>>);
+	fn set_title(&mut self, title: Option<Cow<'t, str>>);
 }
 
 pub trait Association<'t> {
-	fn identifier(&self) -> &'t str;
-	fn label     (&self) -> Option<&'t str>;
+	fn identifier(&self) -> &str;
+	fn label     (&self) -> Option<&str>;
 
-	fn set_identifier(&mut self, identifier: &'t str);
-	fn set_label     (&mut self, label     : Option<&'t str>);
+	fn set_identifier(&mut self, identifier: impl Into<Cow<'t, str>>);
+	fn set_label     (&mut self, label     : Option<Cow<'t, str>>);
 }
 
 pub trait Reference<'t> : Association<'t> {
@@ -262,9 +347,30 @@ pub trait Reference<'t> : Association<'t> {
 }
 
 pub trait Alternative<'t> {
-	fn alt(&self) -> Option<&'t str>;
+	fn alt(&self) -> &str;
+
+	fn set_alt(&mut self, alt: impl Into<Cow<'t, str>>);
+}
 
-	fn set_alt(&mut self, alt: Option<&'t str>);
+/// An aligned block node, such as BBCode's `[center]`. Markdown has no
+/// equivalent.
+/// ```bbcode
+/// [center]The quick brown fox[/center]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[property(get(public), set(public), mut(disable))]
+pub struct Align<'t> {
+	/// The alignment of the children.
+	#[property(get(type = "clone"), set(type = "none"))]
+	pub align: AlignKind,
+	/// [FlowContent] children.
+	#[property(skip)]
+	pub children: Vec<FlowContent<'t>>,
+	/// The position within the document.
+	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
 }
 
 /// A line break node.
@@ -273,8 +379,10 @@ pub trait Alternative<'t> {
 /// b
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Break {
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -285,19 +393,39 @@ pub struct Break {
 /// ```
 /// ~~~
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct Code<'t> {
 	/// The code string.
 	#[property(skip)]
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
 	/// The language, if any.
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub lang : Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub lang : Option<Cow<'t, str>>,
 	/// The metadata, if any.
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub meta : Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub meta : Option<Cow<'t, str>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// A colored text node. Markdown has no equivalent.
+/// ```bbcode
+/// [color=red]alarming[/color]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color<'t> {
+	/// The CSS color, such as `red` or `#ff0000`.
+	pub color: Cow<'t, str>,
+	/// [PhrasingContent] children.
+	pub children: Vec<PhrasingContent<'t>>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -306,13 +434,21 @@ pub struct Code<'t> {
 /// [label]: url "title"
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Definition<'t> {
+	/// The destination URL.
+	pub url: Cow<'t, str>,
+	/// The title, if any.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub title: Option<Cow<'t, str>>,
 	/// The identifier. This should be a lowercased, word-character-only version of
 	/// the label.
-	pub identifier: &'t str,
+	pub identifier: Cow<'t, str>,
 	/// The label, if any.
-	pub label: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub label: Option<Cow<'t, str>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -321,10 +457,26 @@ pub struct Definition<'t> {
 /// ~~strike~~
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Delete<'t> {
 	/// [PhrasingContent] children.
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// An embedded media node, such as a video. Markdown has no equivalent.
+/// ```bbcode
+/// [youtube]dQw4w9WgXcQ[/youtube]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embed<'t> {
+	/// The URL of the media.
+	pub url: Cow<'t, str>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -333,10 +485,28 @@ pub struct Delete<'t> {
 /// *important*
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Emphasis<'t> {
 	/// [PhrasingContent] children.
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// A resized text node. Markdown has no equivalent.
+/// ```bbcode
+/// [size=20px]loud[/size]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontSize<'t> {
+	/// The CSS font size, such as `20px`, `1.5em` or `x-large`.
+	pub size: Cow<'t, str>,
+	/// [PhrasingContent] children.
+	pub children: Vec<PhrasingContent<'t>>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -345,14 +515,17 @@ pub struct Emphasis<'t> {
 /// [^id]: Some content "label"
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FootnoteDef<'t> {
 	/// The identifier.
-	pub identifier: &'t str,
+	pub identifier: Cow<'t, str>,
 	/// The label, if any.
-	pub label: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub label: Option<Cow<'t, str>>,
 	/// [FlowContent] children.
 	pub children: Vec<FlowContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -361,12 +534,15 @@ pub struct FootnoteDef<'t> {
 /// [^id]
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FootnoteRef<'t> {
 	/// The identifier.
-	pub identifier: &'t str,
+	pub identifier: Cow<'t, str>,
 	/// The label, if any.
-	pub label: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub label: Option<Cow<'t, str>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -375,6 +551,7 @@ pub struct FootnoteRef<'t> {
 /// # The quick brown fox
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct Heading<'t> {
 	/// The heading depth from 1 to 6.
@@ -385,6 +562,7 @@ pub struct Heading<'t> {
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -393,10 +571,12 @@ pub struct Heading<'t> {
 /// <a></a>
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Html<'t> {
 	/// The literal value.
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -405,10 +585,12 @@ pub struct Html<'t> {
 /// `a`
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InlineCode<'t> {
 	/// The code string.
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -417,10 +599,12 @@ pub struct InlineCode<'t> {
 /// &x + y&
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InlineMath<'t> {
 	/// The LaTeX math string.
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -429,15 +613,18 @@ pub struct InlineMath<'t> {
 /// ![alt](url "title")
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Image<'t> {
 	/// The alternate text to display if the image can't be rendered.
-	pub alt: Option<&'t str>,
+	pub alt: Cow<'t, str>,
 	/// The image url.
-	pub url: &'t str,
+	pub url: Cow<'t, str>,
 	/// The image title, if any, to be displayed as extra information, such as a
 	/// tooltip.
-	pub title: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub title: Option<Cow<'t, str>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -446,16 +633,20 @@ pub struct Image<'t> {
 /// ![alt][Label]
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImageReference<'t> {
 	/// The alternate text to display if the image can't be rendered.
-	pub alt: Option<&'t str>,
+	pub alt: Cow<'t, str>,
 	/// The reference identifier.
-	pub identifier: &'t str,
+	pub identifier: Cow<'t, str>,
 	/// The reference label.
-	pub label: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub label: Option<Cow<'t, str>>,
 	/// The reference kind.
+	#[cfg_attr(feature = "serde", serde(rename = "referenceType"))]
 	pub kind: ReferenceKind,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -464,15 +655,18 @@ pub struct ImageReference<'t> {
 /// [some text](url "title")
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Link<'t> {
 	/// The link URL.
-	pub url: &'t str,
+	pub url: Cow<'t, str>,
 	/// The link title, if any, to be displayed as extra information, such as a
 	/// tooltip.
-	pub title: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub title: Option<Cow<'t, str>>,
 	/// [StaticPhrasingContent] children to display instead of the URL.
 	pub children: Vec<StaticPhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -481,16 +675,20 @@ pub struct Link<'t> {
 /// [some text][Label]
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinkReference<'t> {
 	/// The reference identifier.
-	pub identifier: &'t str,
+	pub identifier: Cow<'t, str>,
 	/// The reference label.
-	pub label: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub label: Option<Cow<'t, str>>,
 	/// The reference kind.
+	#[cfg_attr(feature = "serde", serde(rename = "referenceType"))]
 	pub kind: ReferenceKind,
 	/// [StaticPhrasingContent] children.
 	pub children: Vec<StaticPhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -501,6 +699,7 @@ pub struct LinkReference<'t> {
 /// - Item 3
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct List<'t> {
 	/// Whether the list is ordered or unordered.
@@ -508,16 +707,17 @@ pub struct List<'t> {
 	pub ordered: bool,
 	/// The list start number, or `None` if unordered.
 	#[property(get(type = "clone"), set(type = "none"))]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub start: Option<u32>,
-	/// `true` if some list items have a blank line between them, `false` or `None`
-	/// if not.
+	/// `true` if some list items have a blank line between them.
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub spread: Option<bool>,
+	pub spread: bool,
 	/// [ListItem] children.
 	#[property(skip)]
 	pub children: Vec<ListItem<'t>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -526,6 +726,11 @@ pub struct List<'t> {
 /// - Item
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename = "listItem")
+)]
 #[property(get(public), set(public), mut(disable))]
 pub struct ListItem<'t> {
 	/// Whether the list item has a blank space at the end.
@@ -534,12 +739,14 @@ pub struct ListItem<'t> {
 	/// Whether the list item is checked, or `None` if indeterminate (not a checked
 	/// list).
 	#[property(get(type = "clone"), set(type = "none"))]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub checked: Option<bool>,
 	/// [FlowContent] children.
 	#[property(skip)]
 	pub children: Vec<FlowContent<'t>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -550,16 +757,19 @@ pub struct ListItem<'t> {
 /// $$
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct Math<'t> {
 	/// The LaTeX math string.
 	#[property(skip)]
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
 	/// The metadata, if any.
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub meta: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub meta: Option<Cow<'t, str>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -568,10 +778,12 @@ pub struct Math<'t> {
 /// The quick brown fox
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Paragraph<'t> {
 	/// [PhrasingContent] children.
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -580,6 +792,7 @@ pub struct Paragraph<'t> {
 /// > The quick brown fox
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct Quote<'t> {
 	/// The author the quote is attributed to. While this field is not supported in
@@ -589,21 +802,51 @@ pub struct Quote<'t> {
 	/// —Unknown
 	/// ```
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub author: Option<&'t str>,
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub author: Option<Cow<'t, str>>,
 	/// [FlowContent] children.
 	#[property(skip)]
 	pub children: Vec<FlowContent<'t>>,
 	/// The position within the document.
 	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
 /// The root node.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename = "root")
+)]
 pub struct Root<'t> {
 	/// [Content] children.
 	pub children: Vec<Content<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// A spoiler node, hiding its children until revealed. Markdown has no
+/// equivalent.
+/// ```bbcode
+/// [spoiler=Ending]The butler did it.[/spoiler]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[property(get(public), set(public), mut(disable))]
+pub struct Spoiler<'t> {
+	/// The summary shown while hidden, if any.
+	#[property(get(type = "clone"), set(type = "none"))]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub summary: Option<Cow<'t, str>>,
+	/// [FlowContent] children.
+	#[property(skip)]
+	pub children: Vec<FlowContent<'t>>,
+	/// The position within the document.
+	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -612,10 +855,40 @@ pub struct Root<'t> {
 /// **scream**
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Strong<'t> {
 	/// [PhrasingContent] children.
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// A subscript node. Markdown has no equivalent.
+/// ```bbcode
+/// H[sub]2[/sub]O
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subscript<'t> {
+	/// [PhrasingContent] children.
+	pub children: Vec<PhrasingContent<'t>>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// A superscript node. Markdown has no equivalent.
+/// ```bbcode
+/// x[sup]2[/sup]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Superscript<'t> {
+	/// [PhrasingContent] children.
+	pub children: Vec<PhrasingContent<'t>>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -626,14 +899,18 @@ pub struct Strong<'t> {
 /// | 4 | 5 | 6 |
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq, Property)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[property(get(public), set(public), mut(disable))]
 pub struct Table<'t> {
 	/// The column alignments.
 	#[property(get(type = "clone"), set(type = "none"))]
-	pub align: Option<Vec<AlignKind>>,
+	pub align: Vec<AlignKind>,
 	/// [TableRow] children.
+	#[property(skip)]
 	pub children: Vec<TableRow<'t>>,
 	/// The position within the document.
+	#[property(skip)]
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -642,10 +919,16 @@ pub struct Table<'t> {
 /// | a |
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename = "tableCell")
+)]
 pub struct TableCell<'t> {
 	/// [PhrasingContent] children.
 	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -654,10 +937,16 @@ pub struct TableCell<'t> {
 /// | a | b | c |
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename = "tableRow")
+)]
 pub struct TableRow<'t> {
 	/// [TableCell] children.
 	pub children: Vec<TableCell<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -666,10 +955,26 @@ pub struct TableRow<'t> {
 /// The quick brown fox
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Text<'t> {
 	/// The literal value.
-	pub value: &'t str,
+	pub value: Cow<'t, str>,
+	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub position: Option<Position>
+}
+
+/// An underlined text node. Markdown has no equivalent.
+/// ```bbcode
+/// [u]underlined[/u]
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Underline<'t> {
+	/// [PhrasingContent] children.
+	pub children: Vec<PhrasingContent<'t>>,
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
@@ -678,16 +983,23 @@ pub struct Text<'t> {
 /// ***
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ThematicBreak {
 	/// The position within the document.
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
 	pub position: Option<Position>
 }
 
 impl_node! {
+	life Align
 	Break
 	life Code
+	life Color
+	life Definition
 	life Delete
+	life Embed
 	life Emphasis
+	life FontSize
 	life FootnoteDef
 	life FootnoteRef
 	life Heading
@@ -704,22 +1016,70 @@ impl_node! {
 	life Paragraph
 	life Quote
 	life Root
+	life Spoiler
+	life Strong
+	life Subscript
+	life Superscript
 	life Table
 	life TableCell
 	life TableRow
 	life Text
 	ThematicBreak
+	life Underline
+}
+
+impl_node_enum! {
+	Content { Flow, Phrasing }
+	FlowContent {
+		Align,
+		Code,
+		Content,
+		FootnoteDef,
+		Heading,
+		Html,
+		List,
+		Math,
+		Quote,
+		Spoiler,
+		Table,
+		ThematicBreak,
+	}
+	PhrasingContent { FootnoteRef, Link, LinkRef, Static }
+	StaticPhrasingContent {
+		Break,
+		Color,
+		Delete,
+		Embed,
+		Emphasis,
+		FontSize,
+		Html,
+		Image,
+		ImageRef,
+		InlineCode,
+		InlineMath,
+		Strong,
+		Subscript,
+		Superscript,
+		Text,
+		Underline,
+	}
+	TextContent { Definition, Paragraph }
 }
 
 impl_literal! {
 	Code
 	Html
+	InlineCode
+	InlineMath
 	Math
 	Text
 }
 
 impl_parent! {
+	Align<FlowContent>
+	Color<PhrasingContent>
 	Delete<PhrasingContent>
+	FontSize<PhrasingContent>
 	FootnoteDef<FlowContent>
 	Heading<PhrasingContent>
 	Link<StaticPhrasingContent>
@@ -729,9 +1089,17 @@ impl_parent! {
 	Paragraph<PhrasingContent>
 	Quote<FlowContent>
 	Root<Content>
+	Spoiler<FlowContent>
+	Subscript<PhrasingContent>
+	Superscript<PhrasingContent>
 	Table<TableRow>
 	TableCell<PhrasingContent>
 	TableRow<TableCell>
+	Underline<PhrasingContent>
+}
+
+impl_resource! {
+	Definition
 }
 
 impl_association! {
@@ -770,8 +1138,11 @@ impl_parent_cstr! {
 	Paragraph<PhrasingContent>
 	Root<Content>
 	Strong<PhrasingContent>
+	Subscript<PhrasingContent>
+	Superscript<PhrasingContent>
 	TableCell<PhrasingContent>
 	TableRow<TableCell>
+	Underline<PhrasingContent>
 }
 
 impl_node_cstr! {
@@ -779,45 +1150,83 @@ impl_node_cstr! {
 	ThematicBreak
 }
 
+impl<'t> Align<'t> {
+	pub fn new(
+		align: AlignKind,
+		children: Vec<FlowContent<'t>>,
+		position: Option<Position>
+	) -> Self {
+		Self { align, children, position }
+	}
+}
+
 impl<'t> Code<'t> {
 	pub fn new(
-		value: &'t str,
-		lang: Option<&'t str>,
-		meta: Option<&'t str>,
+		value: impl Into<Cow<'t, str>>,
+		lang: Option<Cow<'t, str>>,
+		meta: Option<Cow<'t, str>>,
+		position: Option<Position>
+	) -> Self {
+		Self { value: value.into(), lang, meta, position }
+	}
+}
+
+impl<'t> Color<'t> {
+	pub fn new(
+		color: impl Into<Cow<'t, str>>,
+		children: Vec<PhrasingContent<'t>>,
 		position: Option<Position>
 	) -> Self {
-		Self { value, lang, meta, position }
+		Self { color: color.into(), children, position }
 	}
 }
 
 impl<'t> Definition<'t> {
 	pub fn new(
-		identifier: &'t str,
-		label: Option<&'t str>,
+		url: impl Into<Cow<'t, str>>,
+		title: Option<Cow<'t, str>>,
+		identifier: impl Into<Cow<'t, str>>,
+		label: Option<Cow<'t, str>>,
 		position: Option<Position>
 	) -> Self {
-		Self { identifier, label, position }
+		Self { url: url.into(), title, identifier: identifier.into(), label, position }
+	}
+}
+
+impl<'t> Embed<'t> {
+	pub fn new(url: impl Into<Cow<'t, str>>, position: Option<Position>) -> Self {
+		Self { url: url.into(), position }
+	}
+}
+
+impl<'t> FontSize<'t> {
+	pub fn new(
+		size: impl Into<Cow<'t, str>>,
+		children: Vec<PhrasingContent<'t>>,
+		position: Option<Position>
+	) -> Self {
+		Self { size: size.into(), children, position }
 	}
 }
 
 impl<'t> FootnoteDef<'t> {
 	pub fn new(
-		identifier: &'t str,
-		label: Option<&'t str>,
+		identifier: impl Into<Cow<'t, str>>,
+		label: Option<Cow<'t, str>>,
 		children: Vec<FlowContent<'t>>,
 		position: Option<Position>
 	) -> Self {
-		Self { identifier, label, children, position }
+		Self { identifier: identifier.into(), label, children, position }
 	}
 }
 
 impl<'t> FootnoteRef<'t> {
 	pub fn new(
-		identifier: &'t str,
-		label: Option<&'t str>,
+		identifier: impl Into<Cow<'t, str>>,
+		label: Option<Cow<'t, str>>,
 		position: Option<Position>
 	) -> Self {
-		Self { identifier, label, position }
+		Self { identifier: identifier.into(), label, position }
 	}
 }
 
@@ -833,47 +1242,47 @@ impl<'t> Heading<'t> {
 
 impl<'t> Image<'t> {
 	pub fn new(
-		alt: Option<&'t str>,
-		url: &'t str,
-		title: Option<&'t str>,
+		alt: impl Into<Cow<'t, str>>,
+		url: impl Into<Cow<'t, str>>,
+		title: Option<Cow<'t, str>>,
 		position: Option<Position>
 	) -> Self {
-		Self { alt, url, title, position }
+		Self { alt: alt.into(), url: url.into(), title, position }
 	}
 }
 
 impl<'t> ImageReference<'t> {
 	pub fn new(
-		alt: Option<&'t str>,
-		identifier: &'t str,
-		label: Option<&'t str>,
+		alt: impl Into<Cow<'t, str>>,
+		identifier: impl Into<Cow<'t, str>>,
+		label: Option<Cow<'t, str>>,
 		kind: ReferenceKind,
 		position: Option<Position>
 	) -> Self {
-		Self { alt, identifier, label, kind, position }
+		Self { alt: alt.into(), identifier: identifier.into(), label, kind, position }
 	}
 }
 
 impl<'t> Link<'t> {
 	pub fn new(
-		url: &'t str,
-		title: Option<&'t str>,
+		url: impl Into<Cow<'t, str>>,
+		title: Option<Cow<'t, str>>,
 		children: Vec<StaticPhrasingContent<'t>>,
 		position: Option<Position>
 	) -> Self {
-		Self { url, title, children, position }
+		Self { url: url.into(), title, children, position }
 	}
 }
 
 impl<'t> LinkReference<'t> {
 	pub fn new(
-		identifier: &'t str,
-		label: Option<&'t str>,
+		identifier: impl Into<Cow<'t, str>>,
+		label: Option<Cow<'t, str>>,
 		kind: ReferenceKind,
 		children: Vec<StaticPhrasingContent<'t>>,
 		position: Option<Position>
 	) -> Self {
-		Self { identifier, label, kind, children, position }
+		Self { identifier: identifier.into(), label, kind, children, position }
 	}
 }
 
@@ -881,7 +1290,7 @@ impl<'t> List<'t> {
 	pub fn new(
 		ordered: bool,
 		start: Option<u32>,
-		spread: Option<bool>,
+		spread: bool,
 		children: Vec<ListItem<'t>>,
 		position: Option<Position>
 	) -> Self {
@@ -902,17 +1311,17 @@ impl<'t> ListItem<'t> {
 
 impl<'t> Math<'t> {
 	pub fn new(
-		value: &'t str,
-		meta: Option<&'t str>,
+		value: impl Into<Cow<'t, str>>,
+		meta: Option<Cow<'t, str>>,
 		position: Option<Position>
 	) -> Self {
-		Self { value, meta, position }
+		Self { value: value.into(), meta, position }
 	}
 }
 
 impl<'t> Quote<'t> {
 	pub fn new(
-		author: Option<&'t str>,
+		author: Option<Cow<'t, str>>,
 		children: Vec<FlowContent<'t>>,
 		position: Option<Position>
 	) -> Self {
@@ -920,12 +1329,403 @@ impl<'t> Quote<'t> {
 	}
 }
 
+impl<'t> Spoiler<'t> {
+	pub fn new(
+		summary: Option<Cow<'t, str>>,
+		children: Vec<FlowContent<'t>>,
+		position: Option<Position>
+	) -> Self {
+		Self { summary, children, position }
+	}
+}
+
 impl<'t> Table<'t> {
 	pub fn new(
-		align: Option<Vec<AlignKind>>,
+		align: Vec<AlignKind>,
 		children: Vec<TableRow<'t>>,
 		position: Option<Position>
 	) -> Self {
 		Self { align, children, position }
 	}
 }
+
+impl<'t> FlowContent<'t> {
+	/// Flattens the node into phrasing content, for parents that can't contain
+	/// flow content. Blocks are separated by line breaks, code and math become
+	/// inline, and definitions and thematic breaks are dropped.
+	pub fn into_phrasing(self) -> Vec<PhrasingContent<'t>> {
+		match self {
+			Self::Align(Align { children, .. }) => flatten_flow(children),
+			Self::Code(Code { value, position, .. }) => vec![InlineCode { value, position }.into()],
+			Self::Content(TextContent::Definition(_)) | Self::ThematicBreak(_) => vec![],
+			Self::Content(TextContent::Paragraph(Paragraph { children, .. })) |
+			Self::Heading(Heading { children, .. }) => children,
+			Self::FootnoteDef(FootnoteDef { children, .. }) => flatten_flow(children),
+			Self::Html(html) => vec![html.into()],
+			Self::List(List { children, .. }) =>
+				join_lines(children.into_iter().map(|item| flatten_flow(item.children))),
+			Self::Math(Math { value, position, .. }) => vec![InlineMath { value, position }.into()],
+			Self::Quote(Quote { author, children, .. }) => {
+				let author = author.map(|author| vec![Text::new(format!("—{author}"), None).into()]);
+				join_lines([flatten_flow(children)].into_iter().chain(author))
+			}
+			Self::Spoiler(Spoiler { summary, children, .. }) => {
+				let summary = summary.map(|summary| vec![Text::new(summary, None).into()]);
+				join_lines(summary.into_iter().chain([flatten_flow(children)]))
+			}
+			Self::Table(Table { children, .. }) =>
+				join_lines(
+					children.into_iter().map(|row| {
+						let mut line = Vec::new();
+
+						for (i, cell) in row.children.into_iter().enumerate() {
+							if i > 0 {
+								line.push(Text::new(" | ", None).into());
+							}
+
+							line.extend(cell.children);
+						}
+
+						line
+					})
+				),
+		}
+	}
+}
+
+impl<'t> PhrasingContent<'t> {
+	/// Flattens the node into static phrasing content, for link children. Links
+	/// are replaced with their children, and footnote references are dropped.
+	pub fn into_static(self) -> Vec<StaticPhrasingContent<'t>> {
+		match self {
+			Self::FootnoteRef(_) => vec![],
+			Self::Link(Link { children, .. }) |
+			Self::LinkRef(LinkReference { children, .. }) => children,
+			Self::Static(node) => vec![node],
+		}
+	}
+}
+
+fn flatten_flow(children: Vec<FlowContent>) -> Vec<PhrasingContent> {
+	join_lines(children.into_iter().map(FlowContent::into_phrasing))
+}
+
+fn join_lines<'t>(lines: impl IntoIterator<Item = Vec<PhrasingContent<'t>>>) -> Vec<PhrasingContent<'t>> {
+	let mut joined = Vec::new();
+
+	for line in lines.into_iter().filter(|line| !line.is_empty()) {
+		if !joined.is_empty() {
+			joined.push(Break::new(None).into());
+		}
+
+		joined.extend(line);
+	}
+
+	joined
+}
+
+// Conversions from nodes into the content they can appear in. HTML converts to
+// phrasing content; block HTML must be wrapped in FlowContent::Html explicitly.
+
+macro_rules! impl_from {
+	($($enum:ident { $($variant:ident($node:ty)),+ $(,)? })+) => {
+		$($(
+			impl<'t> From<$node> for $enum<'t> {
+				fn from(value: $node) -> Self { Self::$variant(value) }
+			}
+		)+)+
+	};
+}
+
+macro_rules! impl_from_via {
+	($($enum:ident via $via:ident { $($node:ty),+ $(,)? })+) => {
+		$($(
+			impl<'t> From<$node> for $enum<'t> {
+				fn from(value: $node) -> Self { $via::from(value).into() }
+			}
+		)+)+
+	};
+}
+
+impl_from! {
+	Content {
+		Flow(FlowContent<'t>),
+		Phrasing(PhrasingContent<'t>),
+	}
+	FlowContent {
+		Align(Align<'t>),
+		Code(Code<'t>),
+		Content(TextContent<'t>),
+		FootnoteDef(FootnoteDef<'t>),
+		Heading(Heading<'t>),
+		List(List<'t>),
+		Math(Math<'t>),
+		Quote(Quote<'t>),
+		Spoiler(Spoiler<'t>),
+		Table(Table<'t>),
+		ThematicBreak(ThematicBreak),
+	}
+	PhrasingContent {
+		FootnoteRef(FootnoteRef<'t>),
+		Link(Link<'t>),
+		LinkRef(LinkReference<'t>),
+		Static(StaticPhrasingContent<'t>),
+	}
+	StaticPhrasingContent {
+		Break(Break),
+		Color(Color<'t>),
+		Delete(Delete<'t>),
+		Embed(Embed<'t>),
+		Emphasis(Emphasis<'t>),
+		FontSize(FontSize<'t>),
+		Html(Html<'t>),
+		Image(Image<'t>),
+		ImageRef(ImageReference<'t>),
+		InlineCode(InlineCode<'t>),
+		InlineMath(InlineMath<'t>),
+		Strong(Strong<'t>),
+		Subscript(Subscript<'t>),
+		Superscript(Superscript<'t>),
+		Text(Text<'t>),
+		Underline(Underline<'t>),
+	}
+	TextContent {
+		Definition(Definition<'t>),
+		Paragraph(Paragraph<'t>),
+	}
+}
+
+impl_from_via! {
+	FlowContent via TextContent {
+		Definition<'t>,
+		Paragraph<'t>,
+	}
+	PhrasingContent via StaticPhrasingContent {
+		Break,
+		Color<'t>,
+		Delete<'t>,
+		Embed<'t>,
+		Emphasis<'t>,
+		FontSize<'t>,
+		Html<'t>,
+		Image<'t>,
+		ImageReference<'t>,
+		InlineCode<'t>,
+		InlineMath<'t>,
+		Strong<'t>,
+		Subscript<'t>,
+		Superscript<'t>,
+		Text<'t>,
+		Underline<'t>,
+	}
+	Content via FlowContent {
+		TextContent<'t>,
+		Align<'t>,
+		Code<'t>,
+		Definition<'t>,
+		FootnoteDef<'t>,
+		Heading<'t>,
+		List<'t>,
+		Math<'t>,
+		Paragraph<'t>,
+		Quote<'t>,
+		Spoiler<'t>,
+		Table<'t>,
+		ThematicBreak,
+	}
+	Content via PhrasingContent {
+		StaticPhrasingContent<'t>,
+		Break,
+		Color<'t>,
+		Delete<'t>,
+		Embed<'t>,
+		Emphasis<'t>,
+		FontSize<'t>,
+		FootnoteRef<'t>,
+		Html<'t>,
+		Image<'t>,
+		ImageReference<'t>,
+		InlineCode<'t>,
+		InlineMath<'t>,
+		Link<'t>,
+		LinkReference<'t>,
+		Strong<'t>,
+		Subscript<'t>,
+		Superscript<'t>,
+		Text<'t>,
+		Underline<'t>,
+	}
+}
+
+/// Converts a tree borrowing from its source into one that owns all its strings,
+/// so it can outlive the source.
+pub trait IntoOwned {
+	type Owned : 'static;
+
+	fn into_owned(self) -> Self::Owned;
+}
+
+impl IntoOwned for Cow<'_, str> {
+	type Owned = Cow<'static, str>;
+
+	fn into_owned(self) -> Cow<'static, str> {
+		Cow::Owned(Cow::into_owned(self))
+	}
+}
+
+impl<T : IntoOwned> IntoOwned for Option<T> {
+	type Owned = Option<T::Owned>;
+
+	fn into_owned(self) -> Self::Owned {
+		self.map(IntoOwned::into_owned)
+	}
+}
+
+impl<T : IntoOwned> IntoOwned for Vec<T> {
+	type Owned = Vec<T::Owned>;
+
+	fn into_owned(self) -> Self::Owned {
+		self.into_iter().map(IntoOwned::into_owned).collect()
+	}
+}
+
+macro_rules! impl_into_owned {
+	(static $($ty:ty)+) => {
+		$(
+			impl IntoOwned for $ty {
+				type Owned = Self;
+
+				fn into_owned(self) -> Self { self }
+			}
+		)+
+	};
+	(enum $($name:ident { $($variant:ident),+ $(,)? })+) => {
+		$(
+			impl IntoOwned for $name<'_> {
+				type Owned = $name<'static>;
+
+				fn into_owned(self) -> Self::Owned {
+					match self {
+						$(Self::$variant(node) => $name::$variant(node.into_owned()),)+
+					}
+				}
+			}
+		)+
+	};
+	(struct $($name:ident { $($field:ident),+ $(,)? })+) => {
+		$(
+			impl IntoOwned for $name<'_> {
+				type Owned = $name<'static>;
+
+				fn into_owned(self) -> Self::Owned {
+					$name { $($field: IntoOwned::into_owned(self.$field)),+ }
+				}
+			}
+		)+
+	};
+}
+
+impl_into_owned! {
+	static bool u8 u32 AlignKind ReferenceKind Position Break ThematicBreak
+}
+
+impl_into_owned! {
+	enum
+	Content { Flow, Phrasing }
+	FlowContent { Align, Code, Content, FootnoteDef, Heading, Html, List, Math, Quote, Spoiler, Table, ThematicBreak }
+	PhrasingContent { FootnoteRef, Link, LinkRef, Static }
+	StaticPhrasingContent {
+		Break, Color, Delete, Embed, Emphasis, FontSize, Html, Image, ImageRef, InlineCode, InlineMath, Strong,
+		Subscript, Superscript, Text, Underline
+	}
+	TextContent { Definition, Paragraph }
+}
+
+impl_into_owned! {
+	struct
+	Align { align, children, position }
+	Code { value, lang, meta, position }
+	Color { color, children, position }
+	Definition { url, title, identifier, label, position }
+	Delete { children, position }
+	Embed { url, position }
+	Emphasis { children, position }
+	FontSize { size, children, position }
+	FootnoteDef { identifier, label, children, position }
+	FootnoteRef { identifier, label, position }
+	Heading { depth, children, position }
+	Html { value, position }
+	InlineCode { value, position }
+	InlineMath { value, position }
+	Image { alt, url, title, position }
+	ImageReference { alt, identifier, label, kind, position }
+	Link { url, title, children, position }
+	LinkReference { identifier, label, kind, children, position }
+	List { ordered, start, spread, children, position }
+	ListItem { spread, checked, children, position }
+	Math { value, meta, position }
+	Paragraph { children, position }
+	Quote { author, children, position }
+	Root { children, position }
+	Spoiler { summary, children, position }
+	Strong { children, position }
+	Subscript { children, position }
+	Superscript { children, position }
+	Table { align, children, position }
+	TableCell { children, position }
+	TableRow { children, position }
+	Text { value, position }
+	Underline { children, position }
+}
+
+#[cfg(all(test, feature = "serde", feature = "markdown"))]
+mod tests {
+	use markdown::mdast;
+
+	use crate::{MarkdownFlavor, TmDoc};
+
+	use super::*;
+
+	const MARKDOWN: &str = "# Title\n\n\
+		> Some *emphasis*, a [link](/a \"A\"), ![img][ref] and <b>html</b>.\\\n> Next\n\n\
+		1. [ ] Item[^1]\n\n\
+		| a | b |\n|:--|--:|\n| `c` | d |\n\n\
+		[ref]: /img.png\n\n\
+		[^1]: Note\n";
+
+	fn parse() -> TmDoc<'static> {
+		TmDoc::parse_markdown(MARKDOWN, MarkdownFlavor::GFM)
+			.expect("parse failed")
+	}
+
+	#[test]
+	fn mdast_json() {
+		let doc = parse();
+		let json = serde_json::to_value(&doc).unwrap();
+
+		assert_eq!(json, serde_json::to_value(mdast::Node::from(doc.0)).unwrap());
+		assert_eq!(json["type"], "root");
+		assert_eq!(json["children"][1]["type"], "blockquote");
+		assert_eq!(json["children"][0]["position"]["start"]["line"], 1);
+	}
+
+	#[test]
+	fn json_round_trip() {
+		let doc = parse();
+		let json = serde_json::to_string(&doc).unwrap();
+
+		assert_eq!(serde_json::from_str::<TmDoc>(&json).unwrap(), doc);
+	}
+
+	#[test]
+	fn quote_author() {
+		let root = Root {
+			children: vec![Quote::new(Some("Jo".into()), vec![], None).into()],
+			position: None
+		};
+		let json = serde_json::to_string(&root).unwrap();
+
+		assert_eq!(json, r#"{"type":"root","children":[{"type":"blockquote","author":"Jo","children":[]}]}"#);
+		assert_eq!(serde_json::from_str::<Root>(&json).unwrap(), root);
+	}
+}
@@ -0,0 +1,802 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions between tmast and [mdast](markdown::mdast) trees. Owned and
+//! borrowed trees share the same conversion code: strings are moved out of owned
+//! trees, and borrowed from or copied out of borrowed ones.
+//!
+//! Nodes mdast allows in places tmast doesn't are coerced: phrasing content in a
+//! flow parent is wrapped in paragraphs, flow content in a phrasing parent is
+//! flattened, and stray list items, table rows and cells are given a parent.
+//!
+//! Nodes mdast has no equivalent of, like underlines and spoilers, become MDX JSX
+//! elements named after the HTML they stand for, such as `<u>` or `<details>`,
+//! and are read back from them. Quotes with an author become a `<blockquote>`
+//! element with an `author` attribute. Other MDX and frontmatter nodes are dropped.
+
+use std::borrow::Cow;
+use std::mem::take;
+
+use markdown::mdast as md;
+
+use super::*;
+
+impl<'t> From<&'t md::Node> for Root<'t> {
+	fn from(node: &'t md::Node) -> Self { node.root() }
+}
+
+impl From<md::Node> for Root<'static> {
+	fn from(node: md::Node) -> Self { node.root() }
+}
+
+impl From<Root<'_>> for md::Node {
+	fn from(root: Root<'_>) -> Self { root.into_mdast() }
+}
+
+impl From<&Root<'_>> for md::Node {
+	fn from(root: &Root<'_>) -> Self { root.into_mdast() }
+}
+
+/// Converts a field value, moving or copying it as needed.
+trait Conv<T> {
+	fn conv(self) -> T;
+}
+
+impl<'t> Conv<Cow<'t, str>> for String {
+	fn conv(self) -> Cow<'t, str> { Cow::Owned(self) }
+}
+
+impl<'t> Conv<Cow<'t, str>> for &'t String {
+	fn conv(self) -> Cow<'t, str> { Cow::Borrowed(self) }
+}
+
+impl<'t> Conv<Option<Cow<'t, str>>> for Option<String> {
+	fn conv(self) -> Option<Cow<'t, str>> { self.map(Cow::Owned) }
+}
+
+impl<'t> Conv<Option<Cow<'t, str>>> for &'t Option<String> {
+	fn conv(self) -> Option<Cow<'t, str>> { self.as_deref().map(Cow::Borrowed) }
+}
+
+impl Conv<String> for Cow<'_, str> {
+	fn conv(self) -> String { self.into_owned() }
+}
+
+impl Conv<String> for &Cow<'_, str> {
+	fn conv(self) -> String { self.to_string() }
+}
+
+impl Conv<Option<String>> for Option<Cow<'_, str>> {
+	fn conv(self) -> Option<String> { self.map(Cow::into_owned) }
+}
+
+impl Conv<Option<String>> for &Option<Cow<'_, str>> {
+	fn conv(self) -> Option<String> { self.as_deref().map(str::to_string) }
+}
+
+macro_rules! impl_conv {
+	($($ty:ty),+) => {
+		$(
+			impl Conv<$ty> for $ty {
+				fn conv(self) -> $ty { self }
+			}
+
+			impl Conv<$ty> for &$ty {
+				fn conv(self) -> $ty { self.clone() }
+			}
+		)+
+	};
+}
+
+impl_conv! { bool, u8, Option<bool>, Option<u32>, Option<Position>, AlignKind, Vec<AlignKind>, ReferenceKind }
+
+/// Finds the literal value of an MDX JSX attribute, moving or borrowing it.
+trait Attr<'t> {
+	fn attr(&mut self, name: &str) -> Option<Cow<'t, str>>;
+}
+
+impl<'t> Attr<'t> for Vec<md::AttributeContent> {
+	fn attr(&mut self, name: &str) -> Option<Cow<'t, str>> {
+		let index = self.iter().position(|attr| matches!(
+			attr,
+			md::AttributeContent::Property(md::MdxJsxAttribute {
+				name: key,
+				value: Some(md::AttributeValue::Literal(_))
+			}) if key == name
+		))?;
+
+		let md::AttributeContent::Property(md::MdxJsxAttribute {
+			value: Some(md::AttributeValue::Literal(value)), ..
+		}) = self.remove(index) else {
+			unreachable!()
+		};
+
+		Some(Cow::Owned(value))
+	}
+}
+
+impl<'t> Attr<'t> for &'t Vec<md::AttributeContent> {
+	fn attr(&mut self, name: &str) -> Option<Cow<'t, str>> {
+		let attrs: &'t Vec<_> = self;
+		attrs.iter().find_map(|attr| match attr {
+			md::AttributeContent::Property(md::MdxJsxAttribute {
+				name: key,
+				value: Some(md::AttributeValue::Literal(value))
+			}) if key == name => Some(Cow::Borrowed(value.as_str())),
+			_ => None
+		})
+	}
+}
+
+// mdast to tmast
+
+trait FromMdast<'t> : Sized {
+	fn root(self) -> Root<'t>;
+	fn content(self) -> Vec<Content<'t>>;
+	fn list_item(self) -> ListItem<'t>;
+	fn table_row(self) -> TableRow<'t>;
+	fn table_cell(self) -> TableCell<'t>;
+}
+
+macro_rules! impl_from_mdast {
+	($($node:ty),+) => {
+		$(
+			impl<'t> FromMdast<'t> for $node {
+				fn root(self) -> Root<'t> {
+					match self {
+						md::Node::Root(md::Root { children, position }) =>
+							Root {
+								children: children.into_iter().flat_map(FromMdast::content).map(block).collect(),
+								position: position.conv()
+							},
+						node => Root::new(node.content().into_iter().map(block).collect(), None)
+					}
+				}
+
+				fn content(self) -> Vec<Content<'t>> {
+					let node: Content = match self {
+						md::Node::Root(md::Root { children, .. }) =>
+							return children.into_iter().flat_map(FromMdast::content).collect(),
+						md::Node::Blockquote(md::Blockquote { children, position }) =>
+							Quote::new(None, flow(children), position.conv()).into(),
+						md::Node::FootnoteDefinition(md::FootnoteDefinition { children, position, identifier, label }) =>
+							FootnoteDef {
+								identifier: identifier.conv(),
+								label: label.conv(),
+								children: flow(children),
+								position: position.conv()
+							}.into(),
+						md::Node::List(md::List { children, position, ordered, start, spread }) =>
+							List {
+								ordered: ordered.conv(),
+								start: start.conv(),
+								spread: spread.conv(),
+								children: children.into_iter().map(FromMdast::list_item).collect(),
+								position: position.conv()
+							}.into(),
+						md::Node::ListItem(_) => List::new(false, None, false, vec![self.list_item()], None).into(),
+						md::Node::Break(md::Break { position }) => Break::new(position.conv()).into(),
+						md::Node::InlineCode(md::InlineCode { value, position }) =>
+							InlineCode::new(value.conv(), position.conv()).into(),
+						md::Node::InlineMath(md::InlineMath { value, position }) =>
+							InlineMath::new(value.conv(), position.conv()).into(),
+						md::Node::Delete(md::Delete { children, position }) =>
+							Delete::new(phrasing(children), position.conv()).into(),
+						md::Node::Emphasis(md::Emphasis { children, position }) =>
+							Emphasis::new(phrasing(children), position.conv()).into(),
+						md::Node::FootnoteReference(md::FootnoteReference { position, identifier, label }) =>
+							FootnoteRef::new(identifier.conv(), label.conv(), position.conv()).into(),
+						md::Node::Html(md::Html { value, position }) => Html::new(value.conv(), position.conv()).into(),
+						md::Node::Image(md::Image { position, alt, url, title }) =>
+							Image::new(alt.conv(), url.conv(), title.conv(), position.conv()).into(),
+						md::Node::ImageReference(md::ImageReference { position, alt, reference_kind, identifier, label }) =>
+							ImageReference::new(
+								alt.conv(),
+								identifier.conv(),
+								label.conv(),
+								reference_kind.conv(),
+								position.conv()
+							).into(),
+						md::Node::Link(md::Link { children, position, url, title }) =>
+							Link::new(url.conv(), title.conv(), static_phrasing(children), position.conv()).into(),
+						md::Node::LinkReference(md::LinkReference { children, position, reference_kind, identifier, label }) =>
+							LinkReference::new(
+								identifier.conv(),
+								label.conv(),
+								reference_kind.conv(),
+								static_phrasing(children),
+								position.conv()
+							).into(),
+						md::Node::Strong(md::Strong { children, position }) =>
+							Strong::new(phrasing(children), position.conv()).into(),
+						md::Node::Text(md::Text { value, position }) => Text::new(value.conv(), position.conv()).into(),
+						md::Node::Code(md::Code { value, position, lang, meta }) =>
+							Code::new(value.conv(), lang.conv(), meta.conv(), position.conv()).into(),
+						md::Node::Math(md::Math { value, position, meta }) =>
+							Math::new(value.conv(), meta.conv(), position.conv()).into(),
+						md::Node::Heading(md::Heading { children, position, depth }) =>
+							Heading::new(depth.conv(), phrasing(children), position.conv()).into(),
+						md::Node::Table(md::Table { children, position, align }) =>
+							Table::new(
+								align.conv(),
+								children.into_iter().map(FromMdast::table_row).collect(),
+								position.conv()
+							).into(),
+						md::Node::TableRow(_) => Table::new(vec![], vec![self.table_row()], None).into(),
+						md::Node::TableCell(_) =>
+							Table::new(vec![], vec![TableRow::new(vec![self.table_cell()], None)], None).into(),
+						md::Node::ThematicBreak(md::ThematicBreak { position }) => ThematicBreak::new(position.conv()).into(),
+						md::Node::Definition(md::Definition { position, url, title, identifier, label }) =>
+							Definition::new(
+								url.conv(),
+								title.conv(),
+								identifier.conv(),
+								label.conv(),
+								position.conv()
+							).into(),
+						md::Node::Paragraph(md::Paragraph { children, position }) =>
+							Paragraph::new(phrasing(children), position.conv()).into(),
+						md::Node::MdxJsxFlowElement(md::MdxJsxFlowElement { children, position, name, attributes }) => {
+							let mut attributes = attributes;
+
+							match name.as_deref() {
+								Some("div") => {
+									let align = match attributes.attr("align").as_deref() {
+										Some("left")   => AlignKind::Left,
+										Some("right")  => AlignKind::Right,
+										Some("center") => AlignKind::Center,
+										_              => AlignKind::None,
+									};
+									Align::new(align, flow(children), position.conv()).into()
+								}
+								Some("blockquote") =>
+									Quote::new(attributes.attr("author"), flow(children), position.conv()).into(),
+								Some("details") =>
+									Spoiler::new(attributes.attr("summary"), flow(children), position.conv()).into(),
+								_ => return vec![]
+							}
+						}
+						md::Node::MdxJsxTextElement(md::MdxJsxTextElement { children, position, name, attributes }) => {
+							let mut attributes = attributes;
+
+							match name.as_deref() {
+								Some("embed") => {
+									let Some(url) = attributes.attr("src") else { return vec![] };
+									Embed::new(url, position.conv()).into()
+								}
+								Some("font") => {
+									if let Some(color) = attributes.attr("color") {
+										Color::new(color, phrasing(children), position.conv()).into()
+									} else if let Some(size) = attributes.attr("size") {
+										FontSize::new(size, phrasing(children), position.conv()).into()
+									} else {
+										return vec![]
+									}
+								}
+								Some("sub") => Subscript  ::new(phrasing(children), position.conv()).into(),
+								Some("sup") => Superscript::new(phrasing(children), position.conv()).into(),
+								Some("u")   => Underline  ::new(phrasing(children), position.conv()).into(),
+								_ => return vec![]
+							}
+						}
+						// Other MDX and frontmatter aren't supported.
+						_ => return vec![]
+					};
+
+					vec![node]
+				}
+
+				fn list_item(self) -> ListItem<'t> {
+					match self {
+						md::Node::ListItem(md::ListItem { children, position, spread, checked }) =>
+							ListItem::new(spread.conv(), checked.conv(), flow(children), position.conv()),
+						node => ListItem::new(false, None, flow([node]), None)
+					}
+				}
+
+				fn table_row(self) -> TableRow<'t> {
+					match self {
+						md::Node::TableRow(md::TableRow { children, position }) =>
+							TableRow::new(children.into_iter().map(FromMdast::table_cell).collect(), position.conv()),
+						node => TableRow::new(vec![node.table_cell()], None)
+					}
+				}
+
+				fn table_cell(self) -> TableCell<'t> {
+					match self {
+						md::Node::TableCell(md::TableCell { children, position }) =>
+							TableCell::new(phrasing(children), position.conv()),
+						node => TableCell::new(phrasing([node]), None)
+					}
+				}
+			}
+		)+
+	};
+}
+
+impl_from_mdast! { md::Node, &'t md::Node }
+
+/// Reads HTML as a block where flow content is allowed.
+fn block(content: Content) -> Content {
+	match content {
+		Content::Phrasing(PhrasingContent::Static(StaticPhrasingContent::Html(html))) =>
+			FlowContent::Html(html).into(),
+		content => content
+	}
+}
+
+fn flow<'t, N : FromMdast<'t>>(nodes: impl IntoIterator<Item = N>) -> Vec<FlowContent<'t>> {
+	let mut flow = Vec::new();
+	let mut para = Vec::new();
+
+	for content in nodes.into_iter().flat_map(FromMdast::content).map(block) {
+		match content {
+			Content::Flow(node) => {
+				if !para.is_empty() {
+					flow.push(Paragraph::new(take(&mut para), None).into());
+				}
+
+				flow.push(node);
+			}
+			Content::Phrasing(node) => para.push(node)
+		}
+	}
+
+	if !para.is_empty() {
+		flow.push(Paragraph::new(para, None).into());
+	}
+
+	flow
+}
+
+fn phrasing<'t, N : FromMdast<'t>>(nodes: impl IntoIterator<Item = N>) -> Vec<PhrasingContent<'t>> {
+	nodes.into_iter()
+		 .flat_map(FromMdast::content)
+		 .flat_map(|content| match content {
+			 Content::Flow    (node) => node.into_phrasing(),
+			 Content::Phrasing(node) => vec![node],
+		 })
+		 .collect()
+}
+
+fn static_phrasing<'t, N : FromMdast<'t>>(nodes: impl IntoIterator<Item = N>) -> Vec<StaticPhrasingContent<'t>> {
+	phrasing(nodes).into_iter().flat_map(PhrasingContent::into_static).collect()
+}
+
+// tmast to mdast
+
+trait IntoMdast {
+	fn into_mdast(self) -> md::Node;
+}
+
+fn nodes<N : IntoMdast>(children: impl IntoIterator<Item = N>) -> Vec<md::Node> {
+	children.into_iter().map(IntoMdast::into_mdast).collect()
+}
+
+/// Creates an MDX JSX text element.
+fn jsx_text<const N: usize>(
+	name: &str,
+	attrs: [(&str, String); N],
+	children: Vec<md::Node>,
+	position: Option<Position>
+) -> md::Node {
+	md::Node::MdxJsxTextElement(md::MdxJsxTextElement {
+		children,
+		position,
+		name: Some(name.to_string()),
+		attributes: attributes(attrs.map(|(name, value)| (name, Some(value))))
+	})
+}
+
+/// Converts literal MDX JSX attributes, leaving out those without a value.
+fn attributes<'a>(attrs: impl IntoIterator<Item = (&'a str, Option<String>)>) -> Vec<md::AttributeContent> {
+	attrs.into_iter()
+		 .filter_map(|(name, value)|
+			 Some(
+				 md::AttributeContent::Property(md::MdxJsxAttribute {
+					 name: name.to_string(),
+					 value: Some(md::AttributeValue::Literal(value?))
+				 })
+			 )
+		 )
+		 .collect()
+}
+
+macro_rules! impl_into_mdast {
+	($($name:ident $body:tt)+) => {
+		$(
+			impl IntoMdast for $name<'_> $body
+
+			impl IntoMdast for &$name<'_> $body
+		)+
+	};
+}
+
+impl_into_mdast! {
+	Root {
+		fn into_mdast(self) -> md::Node {
+			let Root { children, position } = self;
+			md::Node::Root(md::Root { children: nodes(children), position: position.conv() })
+		}
+	}
+
+	Content {
+		fn into_mdast(self) -> md::Node {
+			match self {
+				Content::Flow    (node) => node.into_mdast(),
+				Content::Phrasing(node) => node.into_mdast(),
+			}
+		}
+	}
+
+	FlowContent {
+		fn into_mdast(self) -> md::Node {
+			match self {
+				FlowContent::Align(Align { align, children, position }) => {
+					let align = match align.conv() {
+						AlignKind::Left   => Some("left"),
+						AlignKind::Right  => Some("right"),
+						AlignKind::Center => Some("center"),
+						AlignKind::None   => None,
+					};
+
+					md::Node::MdxJsxFlowElement(md::MdxJsxFlowElement {
+						children: nodes(children),
+						position: position.conv(),
+						name: Some("div".to_string()),
+						attributes: attributes([("align", align.map(str::to_string))])
+					})
+				}
+				FlowContent::Code(Code { value, lang, meta, position }) =>
+					md::Node::Code(md::Code {
+						value: value.conv(),
+						position: position.conv(),
+						lang: lang.conv(),
+						meta: meta.conv()
+					}),
+				FlowContent::Content(node) => node.into_mdast(),
+				FlowContent::FootnoteDef(FootnoteDef { identifier, label, children, position }) =>
+					md::Node::FootnoteDefinition(md::FootnoteDefinition {
+						children: nodes(children),
+						position: position.conv(),
+						identifier: identifier.conv(),
+						label: label.conv()
+					}),
+				FlowContent::Heading(Heading { depth, children, position }) =>
+					md::Node::Heading(md::Heading {
+						children: nodes(children),
+						position: position.conv(),
+						depth: depth.conv()
+					}),
+				FlowContent::Html(Html { value, position }) =>
+					md::Node::Html(md::Html { value: value.conv(), position: position.conv() }),
+				FlowContent::List(List { ordered, start, spread, children, position }) =>
+					md::Node::List(md::List {
+						children: nodes(children),
+						position: position.conv(),
+						ordered: ordered.conv(),
+						start: start.conv(),
+						spread: spread.conv()
+					}),
+				FlowContent::Math(Math { value, meta, position }) =>
+					md::Node::Math(md::Math { value: value.conv(), position: position.conv(), meta: meta.conv() }),
+				FlowContent::Quote(Quote { author: None, children, position }) =>
+					md::Node::Blockquote(md::Blockquote { children: nodes(children), position: position.conv() }),
+				FlowContent::Quote(Quote { author: Some(author), children, position }) =>
+					md::Node::MdxJsxFlowElement(md::MdxJsxFlowElement {
+						children: nodes(children),
+						position: position.conv(),
+						name: Some("blockquote".to_string()),
+						attributes: attributes([("author", Some(author.conv()))])
+					}),
+				FlowContent::Spoiler(Spoiler { summary, children, position }) =>
+					md::Node::MdxJsxFlowElement(md::MdxJsxFlowElement {
+						children: nodes(children),
+						position: position.conv(),
+						name: Some("details".to_string()),
+						attributes: attributes([("summary", summary.conv())])
+					}),
+				FlowContent::Table(Table { align, children, position }) =>
+					md::Node::Table(md::Table {
+						children: nodes(children),
+						position: position.conv(),
+						align: align.conv()
+					}),
+				FlowContent::ThematicBreak(ThematicBreak { position }) =>
+					md::Node::ThematicBreak(md::ThematicBreak { position: position.conv() }),
+			}
+		}
+	}
+
+	TextContent {
+		fn into_mdast(self) -> md::Node {
+			match self {
+				TextContent::Definition(Definition { url, title, identifier, label, position }) =>
+					md::Node::Definition(md::Definition {
+						position: position.conv(),
+						url: url.conv(),
+						title: title.conv(),
+						identifier: identifier.conv(),
+						label: label.conv()
+					}),
+				TextContent::Paragraph(Paragraph { children, position }) =>
+					md::Node::Paragraph(md::Paragraph { children: nodes(children), position: position.conv() }),
+			}
+		}
+	}
+
+	PhrasingContent {
+		fn into_mdast(self) -> md::Node {
+			match self {
+				PhrasingContent::FootnoteRef(FootnoteRef { identifier, label, position }) =>
+					md::Node::FootnoteReference(md::FootnoteReference {
+						position: position.conv(),
+						identifier: identifier.conv(),
+						label: label.conv()
+					}),
+				PhrasingContent::Link(Link { url, title, children, position }) =>
+					md::Node::Link(md::Link {
+						children: nodes(children),
+						position: position.conv(),
+						url: url.conv(),
+						title: title.conv()
+					}),
+				PhrasingContent::LinkRef(LinkReference { identifier, label, kind, children, position }) =>
+					md::Node::LinkReference(md::LinkReference {
+						children: nodes(children),
+						position: position.conv(),
+						reference_kind: kind.conv(),
+						identifier: identifier.conv(),
+						label: label.conv()
+					}),
+				PhrasingContent::Static(node) => node.into_mdast(),
+			}
+		}
+	}
+
+	StaticPhrasingContent {
+		fn into_mdast(self) -> md::Node {
+			match self {
+				StaticPhrasingContent::Break(Break { position }) =>
+					md::Node::Break(md::Break { position: position.conv() }),
+				StaticPhrasingContent::Color(Color { color, children, position }) =>
+					jsx_text("font", [("color", color.conv())], nodes(children), position.conv()),
+				StaticPhrasingContent::Delete(Delete { children, position }) =>
+					md::Node::Delete(md::Delete { children: nodes(children), position: position.conv() }),
+				StaticPhrasingContent::Embed(Embed { url, position }) =>
+					jsx_text("embed", [("src", url.conv())], vec![], position.conv()),
+				StaticPhrasingContent::Emphasis(Emphasis { children, position }) =>
+					md::Node::Emphasis(md::Emphasis { children: nodes(children), position: position.conv() }),
+				StaticPhrasingContent::FontSize(FontSize { size, children, position }) =>
+					jsx_text("font", [("size", size.conv())], nodes(children), position.conv()),
+				StaticPhrasingContent::Html(Html { value, position }) =>
+					md::Node::Html(md::Html { value: value.conv(), position: position.conv() }),
+				StaticPhrasingContent::Image(Image { alt, url, title, position }) =>
+					md::Node::Image(md::Image {
+						position: position.conv(),
+						alt: alt.conv(),
+						url: url.conv(),
+						title: title.conv()
+					}),
+				StaticPhrasingContent::ImageRef(ImageReference { alt, identifier, label, kind, position }) =>
+					md::Node::ImageReference(md::ImageReference {
+						position: position.conv(),
+						alt: alt.conv(),
+						reference_kind: kind.conv(),
+						identifier: identifier.conv(),
+						label: label.conv()
+					}),
+				StaticPhrasingContent::InlineCode(InlineCode { value, position }) =>
+					md::Node::InlineCode(md::InlineCode { value: value.conv(), position: position.conv() }),
+				StaticPhrasingContent::InlineMath(InlineMath { value, position }) =>
+					md::Node::InlineMath(md::InlineMath { value: value.conv(), position: position.conv() }),
+				StaticPhrasingContent::Strong(Strong { children, position }) =>
+					md::Node::Strong(md::Strong { children: nodes(children), position: position.conv() }),
+				StaticPhrasingContent::Subscript(Subscript { children, position }) =>
+					jsx_text("sub", [], nodes(children), position.conv()),
+				StaticPhrasingContent::Superscript(Superscript { children, position }) =>
+					jsx_text("sup", [], nodes(children), position.conv()),
+				StaticPhrasingContent::Text(Text { value, position }) =>
+					md::Node::Text(md::Text { value: value.conv(), position: position.conv() }),
+				StaticPhrasingContent::Underline(Underline { children, position }) =>
+					jsx_text("u", [], nodes(children), position.conv()),
+			}
+		}
+	}
+
+	ListItem {
+		fn into_mdast(self) -> md::Node {
+			let ListItem { spread, checked, children, position } = self;
+
+			md::Node::ListItem(md::ListItem {
+				children: nodes(children),
+				position: position.conv(),
+				spread: spread.conv(),
+				checked: checked.conv()
+			})
+		}
+	}
+
+	TableRow {
+		fn into_mdast(self) -> md::Node {
+			let TableRow { children, position } = self;
+			md::Node::TableRow(md::TableRow { children: nodes(children), position: position.conv() })
+		}
+	}
+
+	TableCell {
+		fn into_mdast(self) -> md::Node {
+			let TableCell { children, position } = self;
+			md::Node::TableCell(md::TableCell { children: nodes(children), position: position.conv() })
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::borrow::Cow;
+
+	use markdown::{mdast, to_mdast, ParseOptions};
+
+	use crate::tmast::*;
+
+	const MARKDOWN: &str = "\
+# Title
+
+Some **bold**, *emphasis*, ~~deleted~~, `code` and <b>html</b>.\\
+A [link](https://example.com \"Title\"), ![image](a.png) and [ref][id].[^note]
+
+[id]: https://example.com/ref
+
+> Quoted
+> - [x] Checked
+> - [ ] Unchecked
+
+1. One
+
+   Two
+
+| A | B |
+|:-:|--:|
+| 1 | 2 |
+
+<div>block</div>
+
+---
+
+```rust meta
+fn main() {}
+```
+
+[^note]: A note.
+";
+
+	fn mdast() -> mdast::Node {
+		to_mdast(MARKDOWN, &ParseOptions::gfm()).unwrap()
+	}
+
+	#[test]
+	fn round_trip_owned() {
+		let md = mdast();
+
+		assert_eq!(mdast::Node::from(Root::from(md.clone())), md);
+	}
+
+	#[test]
+	fn round_trip_borrowed() {
+		let md = mdast();
+		let root = Root::from(&md);
+
+		assert_eq!(mdast::Node::from(&root), md);
+		assert_eq!(Root::from(mdast::Node::from(&root)), root);
+	}
+
+	#[test]
+	fn borrows_strings() {
+		let md = mdast();
+		let root = Root::from(&md);
+
+		let Content::Flow(FlowContent::Heading(heading)) = &root.children[0] else {
+			panic!("expected heading")
+		};
+		let PhrasingContent::Static(StaticPhrasingContent::Text(text)) = &heading.children[0] else {
+			panic!("expected text")
+		};
+
+		assert_eq!(text.value, "Title");
+		assert!(matches!(text.value, Cow::Borrowed(_)));
+		assert!(matches!(root.into_owned().children[0], Content::Flow(FlowContent::Heading(_))));
+	}
+
+	#[test]
+	fn quote_author() {
+		let md = to_mdast("<blockquote author=\"Jo Doe\">\n  Quoted\n</blockquote>\n", &ParseOptions::mdx()).unwrap();
+		let root = Root::from(&md);
+
+		let Content::Flow(FlowContent::Quote(quote)) = &root.children[0] else {
+			panic!("expected quote")
+		};
+
+		assert_eq!(quote.author.as_deref(), Some("Jo Doe"));
+		assert_eq!(quote.children.len(), 1);
+		assert_eq!(mdast::Node::from(&root), md);
+	}
+
+	#[test]
+	fn quote_dash_line() {
+		for markdown in ["> Quoted\n>\n> —not an author\n", "> —Who's there?\n"] {
+			let md = to_mdast(markdown, &ParseOptions::default()).unwrap();
+			let root = Root::from(&md);
+
+			let Content::Flow(FlowContent::Quote(quote)) = &root.children[0] else {
+				panic!("expected quote")
+			};
+
+			assert_eq!(quote.author, None);
+			assert_eq!(mdast::Node::from(&root), md);
+		}
+	}
+
+	#[test]
+	fn coerce_content() {
+		let md = mdast::Node::Root(mdast::Root {
+			children: vec![
+				mdast::Node::Blockquote(mdast::Blockquote {
+					children: vec![mdast::Node::Text(mdast::Text { value: "a".into(), position: None })],
+					position: None
+				}),
+				mdast::Node::Strong(mdast::Strong {
+					children: vec![
+						mdast::Node::Code(mdast::Code { value: "b".into(), position: None, lang: None, meta: None })
+					],
+					position: None
+				}),
+			],
+			position: None
+		});
+
+		assert_eq!(
+			Root::from(&md).children,
+			vec![
+				Quote::new(None, vec![Paragraph::new(vec![Text::new("a", None).into()], None).into()], None).into(),
+				Strong::new(vec![InlineCode::new("b", None).into()], None).into(),
+			]
+		);
+	}
+
+	#[test]
+	fn mdx_elements() {
+		let mdx = "\
+<div align=\"center\">
+  Centered <u>under</u>, <font color=\"red\">red</font>, <font size=\"2em\">big</font> and x<sup>2</sup><sub>i</sub>
+</div>
+
+<details summary=\"Plot\">
+  Hidden <embed src=\"https://youtube.com/watch?v=abc\" />
+</details>
+";
+		let md = to_mdast(mdx, &ParseOptions::mdx()).unwrap();
+		let root = Root::from(&md);
+
+		let [Content::Flow(FlowContent::Align(align)), Content::Flow(FlowContent::Spoiler(spoiler))] = root.children.as_slice() else {
+			panic!("expected align and spoiler")
+		};
+
+		assert_eq!(align.align, AlignKind::Center);
+		assert_eq!(spoiler.summary.as_deref(), Some("Plot"));
+		assert_eq!(mdast::Node::from(&root), md);
+	}
+}
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Definition lookup for writers. References may come before the definitions
+//! they use, so writers collect every definition before writing.
+
+use std::collections::HashMap;
+
+use super::*;
+use super::visit::{visit_footnote_def, Visit};
+
+/// The definitions and footnote definitions of a document, keyed by lowercase
+/// identifier, with the footnotes referenced so far. The first definition of an
+/// identifier wins.
+#[derive(Default)]
+pub(crate) struct Definitions<'a> {
+	links: HashMap<String, &'a Definition<'a>>,
+	footnote_defs: HashMap<String, &'a FootnoteDef<'a>>,
+	/// Referenced footnote identifiers in order of first reference, with their
+	/// reference count.
+	footnotes: Vec<(String, usize)>,
+}
+
+impl<'a> Definitions<'a> {
+	pub fn collect(root: &'a Root<'a>) -> Self {
+		let mut definitions = Self::default();
+		definitions.visit_root(root);
+		definitions
+	}
+
+	/// Returns the definition a link or image reference points to.
+	pub fn definition(&self, identifier: &str) -> Option<&'a Definition<'a>> {
+		self.links.get(&identifier.to_lowercase()).copied()
+	}
+
+	/// Records a reference to a footnote, returning its number from `0` in order
+	/// of first reference and how many times it's been referenced, or `None` if
+	/// the footnote isn't defined.
+	pub fn reference_footnote(&mut self, identifier: &str) -> Option<(usize, usize)> {
+		let id = identifier.to_lowercase();
+
+		if !self.footnote_defs.contains_key(&id) {
+			return None
+		}
+
+		Some(
+			if let Some(index) = self.footnotes.iter().position(|(fn_id, _)| *fn_id == id) {
+				self.footnotes[index].1 += 1;
+				(index, self.footnotes[index].1)
+			} else {
+				self.footnotes.push((id, 1));
+				(self.footnotes.len() - 1, 1)
+			}
+		)
+	}
+
+	/// Returns the `index`th referenced footnote with its lowercase identifier and
+	/// reference count. Footnote definitions may reference other footnotes, which
+	/// are added as they're written, so writers walk up from `0` until `None`.
+	pub fn footnote(&self, index: usize) -> Option<(&str, &'a FootnoteDef<'a>, usize)> {
+		let (id, count) = self.footnotes.get(index)?;
+		Some((id, self.footnote_defs[id], *count))
+	}
+}
+
+impl<'a> Visit<'a> for Definitions<'a> {
+	fn visit_definition(&mut self, node: &'a Definition<'a>) {
+		self.links.entry(node.identifier.to_lowercase()).or_insert(node);
+	}
+
+	fn visit_footnote_def(&mut self, node: &'a FootnoteDef<'a>) {
+		self.footnote_defs.entry(node.identifier.to_lowercase()).or_insert(node);
+		visit_footnote_def(self, node);
+	}
+}
@@ -0,0 +1,383 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Consuming transformation of a [tmast](super) tree. Each [Fold] method for a
+//! parent node defaults to calling the function of the same name in this module,
+//! which folds the node's children and rebuilds the node. Override a method to
+//! replace a node type.
+
+use super::*;
+
+/// Transforms a tree by value, rebuilding each node from its folded children.
+pub trait Fold<'t> {
+	fn fold_align(&mut self, node: Align<'t>) -> Align<'t> { fold_align(self, node) }
+	fn fold_break(&mut self, node: Break) -> Break { node }
+	fn fold_code(&mut self, node: Code<'t>) -> Code<'t> { node }
+	fn fold_color(&mut self, node: Color<'t>) -> Color<'t> { fold_color(self, node) }
+	fn fold_content(&mut self, node: Content<'t>) -> Content<'t> { fold_content(self, node) }
+	fn fold_definition(&mut self, node: Definition<'t>) -> Definition<'t> { node }
+	fn fold_delete(&mut self, node: Delete<'t>) -> Delete<'t> { fold_delete(self, node) }
+	fn fold_embed(&mut self, node: Embed<'t>) -> Embed<'t> { node }
+	fn fold_emphasis(&mut self, node: Emphasis<'t>) -> Emphasis<'t> { fold_emphasis(self, node) }
+	fn fold_flow_content(&mut self, node: FlowContent<'t>) -> FlowContent<'t> { fold_flow_content(self, node) }
+	fn fold_font_size(&mut self, node: FontSize<'t>) -> FontSize<'t> { fold_font_size(self, node) }
+	fn fold_footnote_def(&mut self, node: FootnoteDef<'t>) -> FootnoteDef<'t> { fold_footnote_def(self, node) }
+	fn fold_footnote_ref(&mut self, node: FootnoteRef<'t>) -> FootnoteRef<'t> { node }
+	fn fold_heading(&mut self, node: Heading<'t>) -> Heading<'t> { fold_heading(self, node) }
+	fn fold_html(&mut self, node: Html<'t>) -> Html<'t> { node }
+	fn fold_image(&mut self, node: Image<'t>) -> Image<'t> { node }
+	fn fold_image_reference(&mut self, node: ImageReference<'t>) -> ImageReference<'t> { node }
+	fn fold_inline_code(&mut self, node: InlineCode<'t>) -> InlineCode<'t> { node }
+	fn fold_inline_math(&mut self, node: InlineMath<'t>) -> InlineMath<'t> { node }
+	fn fold_link(&mut self, node: Link<'t>) -> Link<'t> { fold_link(self, node) }
+	fn fold_link_reference(&mut self, node: LinkReference<'t>) -> LinkReference<'t> { fold_link_reference(self, node) }
+	fn fold_list(&mut self, node: List<'t>) -> List<'t> { fold_list(self, node) }
+	fn fold_list_item(&mut self, node: ListItem<'t>) -> ListItem<'t> { fold_list_item(self, node) }
+	fn fold_math(&mut self, node: Math<'t>) -> Math<'t> { node }
+	fn fold_paragraph(&mut self, node: Paragraph<'t>) -> Paragraph<'t> { fold_paragraph(self, node) }
+	fn fold_phrasing_content(&mut self, node: PhrasingContent<'t>) -> PhrasingContent<'t> { fold_phrasing_content(self, node) }
+	fn fold_quote(&mut self, node: Quote<'t>) -> Quote<'t> { fold_quote(self, node) }
+	fn fold_root(&mut self, node: Root<'t>) -> Root<'t> { fold_root(self, node) }
+	fn fold_spoiler(&mut self, node: Spoiler<'t>) -> Spoiler<'t> { fold_spoiler(self, node) }
+	fn fold_static_phrasing_content(&mut self, node: StaticPhrasingContent<'t>) -> StaticPhrasingContent<'t> { fold_static_phrasing_content(self, node) }
+	fn fold_strong(&mut self, node: Strong<'t>) -> Strong<'t> { fold_strong(self, node) }
+	fn fold_subscript(&mut self, node: Subscript<'t>) -> Subscript<'t> { fold_subscript(self, node) }
+	fn fold_superscript(&mut self, node: Superscript<'t>) -> Superscript<'t> { fold_superscript(self, node) }
+	fn fold_table(&mut self, node: Table<'t>) -> Table<'t> { fold_table(self, node) }
+	fn fold_table_cell(&mut self, node: TableCell<'t>) -> TableCell<'t> { fold_table_cell(self, node) }
+	fn fold_table_row(&mut self, node: TableRow<'t>) -> TableRow<'t> { fold_table_row(self, node) }
+	fn fold_text(&mut self, node: Text<'t>) -> Text<'t> { node }
+	fn fold_text_content(&mut self, node: TextContent<'t>) -> TextContent<'t> { fold_text_content(self, node) }
+	fn fold_thematic_break(&mut self, node: ThematicBreak) -> ThematicBreak { node }
+	fn fold_underline(&mut self, node: Underline<'t>) -> Underline<'t> { fold_underline(self, node) }
+}
+
+pub fn fold_align<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Align<'t>) -> Align<'t> {
+	Align {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_flow_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_color<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Color<'t>) -> Color<'t> {
+	Color {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_content<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Content<'t>) -> Content<'t> {
+	match node {
+		Content::Flow(node)     => Content::Flow(folder.fold_flow_content(node)),
+		Content::Phrasing(node) => Content::Phrasing(folder.fold_phrasing_content(node)),
+	}
+}
+
+pub fn fold_delete<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Delete<'t>) -> Delete<'t> {
+	Delete {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_emphasis<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Emphasis<'t>) -> Emphasis<'t> {
+	Emphasis {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_flow_content<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: FlowContent<'t>) -> FlowContent<'t> {
+	match node {
+		FlowContent::Align(node)         => FlowContent::Align(folder.fold_align(node)),
+		FlowContent::Code(node)          => FlowContent::Code(folder.fold_code(node)),
+		FlowContent::Content(node)       => FlowContent::Content(folder.fold_text_content(node)),
+		FlowContent::FootnoteDef(node)   => FlowContent::FootnoteDef(folder.fold_footnote_def(node)),
+		FlowContent::Heading(node)       => FlowContent::Heading(folder.fold_heading(node)),
+		FlowContent::Html(node)          => FlowContent::Html(folder.fold_html(node)),
+		FlowContent::List(node)          => FlowContent::List(folder.fold_list(node)),
+		FlowContent::Math(node)          => FlowContent::Math(folder.fold_math(node)),
+		FlowContent::Quote(node)         => FlowContent::Quote(folder.fold_quote(node)),
+		FlowContent::Spoiler(node)       => FlowContent::Spoiler(folder.fold_spoiler(node)),
+		FlowContent::Table(node)         => FlowContent::Table(folder.fold_table(node)),
+		FlowContent::ThematicBreak(node) => FlowContent::ThematicBreak(folder.fold_thematic_break(node)),
+	}
+}
+
+pub fn fold_font_size<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: FontSize<'t>) -> FontSize<'t> {
+	FontSize {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_footnote_def<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: FootnoteDef<'t>) -> FootnoteDef<'t> {
+	FootnoteDef {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_flow_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_heading<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Heading<'t>) -> Heading<'t> {
+	Heading {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_link<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Link<'t>) -> Link<'t> {
+	Link {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_static_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_link_reference<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: LinkReference<'t>) -> LinkReference<'t> {
+	LinkReference {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_static_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_list<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: List<'t>) -> List<'t> {
+	List {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_list_item(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_list_item<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: ListItem<'t>) -> ListItem<'t> {
+	ListItem {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_flow_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_paragraph<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Paragraph<'t>) -> Paragraph<'t> {
+	Paragraph {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_phrasing_content<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: PhrasingContent<'t>) -> PhrasingContent<'t> {
+	match node {
+		PhrasingContent::FootnoteRef(node) => PhrasingContent::FootnoteRef(folder.fold_footnote_ref(node)),
+		PhrasingContent::Link(node)        => PhrasingContent::Link(folder.fold_link(node)),
+		PhrasingContent::LinkRef(node)     => PhrasingContent::LinkRef(folder.fold_link_reference(node)),
+		PhrasingContent::Static(node)      => PhrasingContent::Static(folder.fold_static_phrasing_content(node)),
+	}
+}
+
+pub fn fold_quote<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Quote<'t>) -> Quote<'t> {
+	Quote {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_flow_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_root<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Root<'t>) -> Root<'t> {
+	Root {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_spoiler<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Spoiler<'t>) -> Spoiler<'t> {
+	Spoiler {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_flow_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_static_phrasing_content<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: StaticPhrasingContent<'t>) -> StaticPhrasingContent<'t> {
+	match node {
+		StaticPhrasingContent::Break(node)       => StaticPhrasingContent::Break(folder.fold_break(node)),
+		StaticPhrasingContent::Color(node)       => StaticPhrasingContent::Color(folder.fold_color(node)),
+		StaticPhrasingContent::Delete(node)      => StaticPhrasingContent::Delete(folder.fold_delete(node)),
+		StaticPhrasingContent::Embed(node)       => StaticPhrasingContent::Embed(folder.fold_embed(node)),
+		StaticPhrasingContent::Emphasis(node)    => StaticPhrasingContent::Emphasis(folder.fold_emphasis(node)),
+		StaticPhrasingContent::FontSize(node)    => StaticPhrasingContent::FontSize(folder.fold_font_size(node)),
+		StaticPhrasingContent::Html(node)        => StaticPhrasingContent::Html(folder.fold_html(node)),
+		StaticPhrasingContent::Image(node)       => StaticPhrasingContent::Image(folder.fold_image(node)),
+		StaticPhrasingContent::ImageRef(node)    => StaticPhrasingContent::ImageRef(folder.fold_image_reference(node)),
+		StaticPhrasingContent::InlineCode(node)  => StaticPhrasingContent::InlineCode(folder.fold_inline_code(node)),
+		StaticPhrasingContent::InlineMath(node)  => StaticPhrasingContent::InlineMath(folder.fold_inline_math(node)),
+		StaticPhrasingContent::Strong(node)      => StaticPhrasingContent::Strong(folder.fold_strong(node)),
+		StaticPhrasingContent::Subscript(node)   => StaticPhrasingContent::Subscript(folder.fold_subscript(node)),
+		StaticPhrasingContent::Superscript(node) => StaticPhrasingContent::Superscript(folder.fold_superscript(node)),
+		StaticPhrasingContent::Text(node)        => StaticPhrasingContent::Text(folder.fold_text(node)),
+		StaticPhrasingContent::Underline(node)   => StaticPhrasingContent::Underline(folder.fold_underline(node)),
+	}
+}
+
+pub fn fold_strong<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Strong<'t>) -> Strong<'t> {
+	Strong {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_subscript<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Subscript<'t>) -> Subscript<'t> {
+	Subscript {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_superscript<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Superscript<'t>) -> Superscript<'t> {
+	Superscript {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_table<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Table<'t>) -> Table<'t> {
+	Table {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_table_row(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_table_cell<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: TableCell<'t>) -> TableCell<'t> {
+	TableCell {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_table_row<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: TableRow<'t>) -> TableRow<'t> {
+	TableRow {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_table_cell(child))
+			.collect(),
+		..node
+	}
+}
+
+pub fn fold_text_content<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: TextContent<'t>) -> TextContent<'t> {
+	match node {
+		TextContent::Definition(node) => TextContent::Definition(folder.fold_definition(node)),
+		TextContent::Paragraph(node)  => TextContent::Paragraph(folder.fold_paragraph(node)),
+	}
+}
+
+pub fn fold_underline<'t, F : Fold<'t> + ?Sized>(folder: &mut F, node: Underline<'t>) -> Underline<'t> {
+	Underline {
+		children: node.children
+			.into_iter()
+			.map(|child| folder.fold_phrasing_content(child))
+			.collect(),
+		..node
+	}
+}
+
+
+#[cfg(all(test, feature = "html", feature = "markdown"))]
+mod tests {
+	use crate::{IntoHtmlText, MarkdownFlavor, TmDoc};
+
+	use super::*;
+
+	/// Replaces emphasis with strong text, and shouts all text.
+	struct Shout;
+
+	impl<'t> Fold<'t> for Shout {
+		fn fold_static_phrasing_content(&mut self, node: StaticPhrasingContent<'t>) -> StaticPhrasingContent<'t> {
+			match fold_static_phrasing_content(self, node) {
+				StaticPhrasingContent::Emphasis(Emphasis { children, position }) =>
+					StaticPhrasingContent::Strong(Strong { children, position }),
+				node => node
+			}
+		}
+
+		fn fold_text(&mut self, node: Text<'t>) -> Text<'t> {
+			Text::new(node.value.to_uppercase(), node.position)
+		}
+	}
+
+	#[test]
+	fn replace_nodes() {
+		let doc = TmDoc::parse_markdown("# Hi *there*\n\n- [a *b*](/c)\n", MarkdownFlavor::GFM)
+			.expect("parse failed");
+
+		assert_eq!(
+			TmDoc(Shout.fold_root(doc.0)).into_html_text(),
+			"<h1>HI <strong>THERE</strong></h1>\n<ul>\n<li><a href=\"/c\">A <strong>B</strong></a></li>\n</ul>\n"
+		);
+	}
+}
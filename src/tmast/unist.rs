@@ -14,12 +14,12 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
 use std::cmp::min;
+use std::ops::Range;
 
 pub use markdown::unist::*;
 
-use crate::util::LastIndex;
-
 /// A node.
 pub trait Node {
 	/// Returns the node [Position] within the document.
@@ -32,7 +32,7 @@ pub trait Node {
 /// A node containing other nodes.
 pub trait Parent<N> : Node {
 	/// Returns a slice of the node's children.
-	fn children<'c>(&'c self) -> &'c [N];
+	fn children(&self) -> &[N];
 
 	/// Appends a child the the node.
 	fn append_child(&mut self, node: N);
@@ -41,10 +41,10 @@ pub trait Parent<N> : Node {
 /// A leaf node that contains a literal string.
 pub trait Literal<'v> : Node {
 	/// Returns the value.
-	fn value(&self) -> &'v str;
+	fn value(&self) -> &str;
 
 	/// Sets the value.
-	fn set_value(&mut self, value: &'v str);
+	fn set_value(&mut self, value: impl Into<Cow<'v, str>>);
 }
 
 pub trait PositionSlice {
@@ -54,11 +54,19 @@ pub trait PositionSlice {
 
 pub trait PointOffset {
 	/// Creates a [Point] with the specified offset, calulating its line and column
-	/// from the value. If the offset falls outside the value, the line number will
-	/// be the value's line count, and the column will be the remaining characters.
+	/// from the value. Columns are counted in bytes. If the offset falls outside
+	/// the value, the line number will be the value's line count, and the column
+	/// will be the remaining characters. This scans the value on each call, so a
+	/// [LineIndex] is faster for many points in one value.
 	fn new_offset(offset: usize, value: &str) -> Point;
 }
 
+pub trait PositionRange {
+	/// Creates a [Position] spanning the specified byte range, calculating its
+	/// points from the value as [PointOffset::new_offset] does.
+	fn new_range(range: Range<usize>, value: &str) -> Position;
+}
+
 impl PositionSlice for Position {
 	fn slice<'t>(&self, value: &'t str) -> &'t str {
 		let start = self.start.offset;
@@ -67,39 +75,150 @@ impl PositionSlice for Position {
 	}
 }
 
+impl PositionRange for Position {
+	fn new_range(range: Range<usize>, value: &str) -> Position {
+		Position {
+			start: Point::new_offset(range.start, value),
+			end:   Point::new_offset(range.end,   value),
+		}
+	}
+}
+
 impl PointOffset for Point {
 	fn new_offset(offset: usize, value: &str) -> Point {
-		fn take_lines(str: &str, count: usize) -> &str {
-			let mut n = 0;
+		// Lines are counted by bytes, so offsets needn't fall on char boundaries.
+		let before = &value.as_bytes()[..min(offset, value.len())];
+		let line   = before.iter().filter(|b| **b == b'\n').count() + 1;
+		let start  = before.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+		
+		Point { line, column: offset - start + 1, offset }
+	}
+}
 
-			for line in str.lines().take(count) {
-				n += line.len();
+/// An index of line starts in a document, built once to map between byte offsets
+/// and [Point]s by binary search, where [PointOffset::new_offset] rescans the
+/// document on each call. Lines end at LF, so CRLF and mixed endings are mapped
+/// the same way.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineIndex {
+	/// Offsets at which each line starts.
+	lines: Vec<usize>,
+	/// Offsets and byte lengths of multibyte characters, for UTF-16 columns.
+	wide: Vec<(usize, usize)>,
+	len: usize,
+}
 
-				let get_next = |v: &str|
-					str.get(n..n + v.len())
-						.filter(|next| *next == v);
+impl LineIndex {
+	pub fn new(value: &str) -> Self {
+		let mut lines = vec![0];
+		let mut wide = Vec::new();
 
-				n += get_next("\n") // LF
-					.or_else(|| get_next("\r\n")) // CRLF
-					.map_or(0, str::len);
+		for (offset, char) in value.char_indices() {
+			match char {
+				'\n' => lines.push(offset + 1),
+				_ if !char.is_ascii() => wide.push((offset, char.len_utf8())),
+				_ => { }
 			}
-
-			&str[..=n]
 		}
 
-		let len  = min(value.len(), offset + 1);
-		let str  = &value[..len];
-		let line = str.lines().count();
-		let mut column = offset + 1;
-		column -= take_lines(str, line - 1).last_index().unwrap_or_default();
-		
+		Self { lines, wide, len: value.len() }
+	}
+
+	/// Returns the number of lines.
+	pub fn line_count(&self) -> usize {
+		self.lines.len()
+	}
+
+	/// Returns a [Point] at the specified offset, with columns counted in bytes,
+	/// like [PointOffset::new_offset].
+	pub fn point(&self, offset: usize) -> Point {
+		let line = self.line(offset);
+		let column = offset - self.lines[line - 1] + 1;
 		Point { line, column, offset }
 	}
+
+	/// Returns a [Point] at the specified offset, with columns counted in UTF-16
+	/// code units as LSP clients expect.
+	pub fn point_utf16(&self, offset: usize) -> Point {
+		let line  = self.line(offset);
+		let start = self.lines[line - 1];
+		let extra = self.wide_in(start..offset)
+			.iter()
+			.map(|&(_, len)| len - utf16_len(len))
+			.sum::<usize>();
+
+		Point { line, column: offset - start - extra + 1, offset }
+	}
+
+	/// Returns a [Position] spanning the specified byte range, with columns counted
+	/// in bytes.
+	pub fn position(&self, range: Range<usize>) -> Position {
+		Position {
+			start: self.point(range.start),
+			end:   self.point(range.end),
+		}
+	}
+
+	/// Returns the offset at a line and byte column, or `None` if it's outside the
+	/// document. The column may point just past the last character of the line.
+	pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+		let range  = self.line_range(line)?;
+		let offset = range.start + column.checked_sub(1)?;
+		(offset <= range.end).then_some(offset)
+	}
+
+	/// Returns the offset at a line and UTF-16 column, or `None` if it's outside
+	/// the document or splits a surrogate pair.
+	pub fn offset_utf16(&self, line: usize, column: usize) -> Option<usize> {
+		let range = self.line_range(line)?;
+		let mut units  = column.checked_sub(1)?;
+		let mut offset = range.start;
+
+		for &(start, len) in self.wide_in(range.clone()) {
+			let narrow = start - offset;
+
+			if units <= narrow {
+				break
+			}
+
+			units -= narrow;
+			units = units.checked_sub(utf16_len(len))?;
+			offset = start + len;
+		}
+
+		offset += units;
+		(offset <= range.end).then_some(offset)
+	}
+
+	/// Returns the 1-based line containing the offset. Offsets past the end are on
+	/// the last line.
+	fn line(&self, offset: usize) -> usize {
+		self.lines.partition_point(|&start| start <= offset)
+	}
+
+	/// Returns the range of a 1-based line, excluding its LF.
+	fn line_range(&self, line: usize) -> Option<Range<usize>> {
+		let start = *self.lines.get(line.checked_sub(1)?)?;
+		let end   = self.lines.get(line).map_or(self.len, |next| next - 1);
+		Some(start..end)
+	}
+
+	/// Returns the multibyte characters starting within the range.
+	fn wide_in(&self, range: Range<usize>) -> &[(usize, usize)] {
+		let start = self.wide.partition_point(|&(offset, _)| offset < range.start);
+		let end   = self.wide.partition_point(|&(offset, _)| offset < range.end);
+		&self.wide[start..end.max(start)]
+	}
+}
+
+/// Returns the UTF-16 length of a character from its UTF-8 length.
+fn utf16_len(len: usize) -> usize {
+	if len == 4 { 2 } else { 1 }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Point, PointOffset};
+	use super::{LineIndex, Point, PointOffset};
 
 	#[test]
 	fn single_line_point() {
@@ -165,4 +284,56 @@ mod tests {
 
 		assert_eq!(Point::new_offset(offset, value), exp);
 	}
+
+	#[test]
+	fn multibyte_point() {
+		let value = "The quick — brown\nfox 🦊 jumps";
+		let offset = 28;
+		let exp = Point {
+			line: 2,
+			column: 9,
+			offset
+		};
+
+		assert_eq!(Point::new_offset(offset, value), exp);
+	}
+
+	#[test]
+	fn line_index_points() {
+		let value = "The quick\r\nbrown fox\njumps over\r\nthe lazy — dog 🦊\n";
+		let index = LineIndex::new(value);
+
+		assert_eq!(index.line_count(), 5);
+
+		for offset in 0..=value.len() + 2 {
+			let point = index.point(offset);
+			assert_eq!(point, Point::new_offset(offset, value), "at {offset}");
+
+			if offset <= value.len() {
+				assert_eq!(index.offset(point.line, point.column), Some(offset), "at {offset}");
+			}
+		}
+
+		assert_eq!(index.offset(2, 20), None);
+		assert_eq!(index.offset(6, 1), None);
+		assert_eq!(index.offset(1, 0), None);
+	}
+
+	#[test]
+	fn line_index_utf16() {
+		let value = "ab\n— 🦊 c";
+		let index = LineIndex::new(value);
+		let fox = value.find('🦊').unwrap();
+		let c   = value.find('c').unwrap();
+
+		assert_eq!(index.point_utf16(fox), Point { line: 2, column: 3, offset: fox });
+		assert_eq!(index.point_utf16(c  ), Point { line: 2, column: 6, offset: c   });
+		assert_eq!(index.point_utf16(1  ), Point { line: 1, column: 2, offset: 1   });
+
+		assert_eq!(index.offset_utf16(2, 3), Some(fox));
+		assert_eq!(index.offset_utf16(2, 6), Some(c));
+		assert_eq!(index.offset_utf16(2, 7), Some(value.len()));
+		assert_eq!(index.offset_utf16(2, 4), None);
+		assert_eq!(index.offset_utf16(2, 8), None);
+	}
 }
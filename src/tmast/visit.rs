@@ -0,0 +1,304 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Traversal of a borrowed [tmast](super) tree. Each [Visit] method for a parent
+//! node defaults to calling the function of the same name in this module, which
+//! visits the node's children. Override a method to act on a node type, calling
+//! its function to keep walking into the children.
+
+use super::*;
+
+/// Visits the nodes of a borrowed tree, parents before their children. The
+/// `'a` lifetime lets a visitor keep references into the tree.
+pub trait Visit<'a> {
+	fn visit_align(&mut self, node: &'a Align<'a>) { visit_align(self, node) }
+	fn visit_break(&mut self, _node: &'a Break) { }
+	fn visit_code(&mut self, _node: &'a Code<'a>) { }
+	fn visit_color(&mut self, node: &'a Color<'a>) { visit_color(self, node) }
+	fn visit_content(&mut self, node: &'a Content<'a>) { visit_content(self, node) }
+	fn visit_definition(&mut self, _node: &'a Definition<'a>) { }
+	fn visit_delete(&mut self, node: &'a Delete<'a>) { visit_delete(self, node) }
+	fn visit_embed(&mut self, _node: &'a Embed<'a>) { }
+	fn visit_emphasis(&mut self, node: &'a Emphasis<'a>) { visit_emphasis(self, node) }
+	fn visit_flow_content(&mut self, node: &'a FlowContent<'a>) { visit_flow_content(self, node) }
+	fn visit_font_size(&mut self, node: &'a FontSize<'a>) { visit_font_size(self, node) }
+	fn visit_footnote_def(&mut self, node: &'a FootnoteDef<'a>) { visit_footnote_def(self, node) }
+	fn visit_footnote_ref(&mut self, _node: &'a FootnoteRef<'a>) { }
+	fn visit_heading(&mut self, node: &'a Heading<'a>) { visit_heading(self, node) }
+	fn visit_html(&mut self, _node: &'a Html<'a>) { }
+	fn visit_image(&mut self, _node: &'a Image<'a>) { }
+	fn visit_image_reference(&mut self, _node: &'a ImageReference<'a>) { }
+	fn visit_inline_code(&mut self, _node: &'a InlineCode<'a>) { }
+	fn visit_inline_math(&mut self, _node: &'a InlineMath<'a>) { }
+	fn visit_link(&mut self, node: &'a Link<'a>) { visit_link(self, node) }
+	fn visit_link_reference(&mut self, node: &'a LinkReference<'a>) { visit_link_reference(self, node) }
+	fn visit_list(&mut self, node: &'a List<'a>) { visit_list(self, node) }
+	fn visit_list_item(&mut self, node: &'a ListItem<'a>) { visit_list_item(self, node) }
+	fn visit_math(&mut self, _node: &'a Math<'a>) { }
+	fn visit_paragraph(&mut self, node: &'a Paragraph<'a>) { visit_paragraph(self, node) }
+	fn visit_phrasing_content(&mut self, node: &'a PhrasingContent<'a>) { visit_phrasing_content(self, node) }
+	fn visit_quote(&mut self, node: &'a Quote<'a>) { visit_quote(self, node) }
+	fn visit_root(&mut self, node: &'a Root<'a>) { visit_root(self, node) }
+	fn visit_spoiler(&mut self, node: &'a Spoiler<'a>) { visit_spoiler(self, node) }
+	fn visit_static_phrasing_content(&mut self, node: &'a StaticPhrasingContent<'a>) { visit_static_phrasing_content(self, node) }
+	fn visit_strong(&mut self, node: &'a Strong<'a>) { visit_strong(self, node) }
+	fn visit_subscript(&mut self, node: &'a Subscript<'a>) { visit_subscript(self, node) }
+	fn visit_superscript(&mut self, node: &'a Superscript<'a>) { visit_superscript(self, node) }
+	fn visit_table(&mut self, node: &'a Table<'a>) { visit_table(self, node) }
+	fn visit_table_cell(&mut self, node: &'a TableCell<'a>) { visit_table_cell(self, node) }
+	fn visit_table_row(&mut self, node: &'a TableRow<'a>) { visit_table_row(self, node) }
+	fn visit_text(&mut self, _node: &'a Text<'a>) { }
+	fn visit_text_content(&mut self, node: &'a TextContent<'a>) { visit_text_content(self, node) }
+	fn visit_thematic_break(&mut self, _node: &'a ThematicBreak) { }
+	fn visit_underline(&mut self, node: &'a Underline<'a>) { visit_underline(self, node) }
+}
+
+pub fn visit_align<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Align<'a>) {
+	for child in &node.children {
+		visitor.visit_flow_content(child);
+	}
+}
+
+pub fn visit_color<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Color<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_content<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Content<'a>) {
+	match node {
+		Content::Flow(node)     => visitor.visit_flow_content(node),
+		Content::Phrasing(node) => visitor.visit_phrasing_content(node),
+	}
+}
+
+pub fn visit_delete<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Delete<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_emphasis<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Emphasis<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_flow_content<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a FlowContent<'a>) {
+	match node {
+		FlowContent::Align(node)         => visitor.visit_align(node),
+		FlowContent::Code(node)          => visitor.visit_code(node),
+		FlowContent::Content(node)       => visitor.visit_text_content(node),
+		FlowContent::FootnoteDef(node)   => visitor.visit_footnote_def(node),
+		FlowContent::Heading(node)       => visitor.visit_heading(node),
+		FlowContent::Html(node)          => visitor.visit_html(node),
+		FlowContent::List(node)          => visitor.visit_list(node),
+		FlowContent::Math(node)          => visitor.visit_math(node),
+		FlowContent::Quote(node)         => visitor.visit_quote(node),
+		FlowContent::Spoiler(node)       => visitor.visit_spoiler(node),
+		FlowContent::Table(node)         => visitor.visit_table(node),
+		FlowContent::ThematicBreak(node) => visitor.visit_thematic_break(node),
+	}
+}
+
+pub fn visit_font_size<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a FontSize<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_footnote_def<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a FootnoteDef<'a>) {
+	for child in &node.children {
+		visitor.visit_flow_content(child);
+	}
+}
+
+pub fn visit_heading<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Heading<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_link<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Link<'a>) {
+	for child in &node.children {
+		visitor.visit_static_phrasing_content(child);
+	}
+}
+
+pub fn visit_link_reference<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a LinkReference<'a>) {
+	for child in &node.children {
+		visitor.visit_static_phrasing_content(child);
+	}
+}
+
+pub fn visit_list<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a List<'a>) {
+	for child in &node.children {
+		visitor.visit_list_item(child);
+	}
+}
+
+pub fn visit_list_item<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a ListItem<'a>) {
+	for child in &node.children {
+		visitor.visit_flow_content(child);
+	}
+}
+
+pub fn visit_paragraph<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Paragraph<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_phrasing_content<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a PhrasingContent<'a>) {
+	match node {
+		PhrasingContent::FootnoteRef(node) => visitor.visit_footnote_ref(node),
+		PhrasingContent::Link(node)        => visitor.visit_link(node),
+		PhrasingContent::LinkRef(node)     => visitor.visit_link_reference(node),
+		PhrasingContent::Static(node)      => visitor.visit_static_phrasing_content(node),
+	}
+}
+
+pub fn visit_quote<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Quote<'a>) {
+	for child in &node.children {
+		visitor.visit_flow_content(child);
+	}
+}
+
+pub fn visit_root<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Root<'a>) {
+	for child in &node.children {
+		visitor.visit_content(child);
+	}
+}
+
+pub fn visit_spoiler<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Spoiler<'a>) {
+	for child in &node.children {
+		visitor.visit_flow_content(child);
+	}
+}
+
+pub fn visit_static_phrasing_content<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a StaticPhrasingContent<'a>) {
+	match node {
+		StaticPhrasingContent::Break(node)       => visitor.visit_break(node),
+		StaticPhrasingContent::Color(node)       => visitor.visit_color(node),
+		StaticPhrasingContent::Delete(node)      => visitor.visit_delete(node),
+		StaticPhrasingContent::Embed(node)       => visitor.visit_embed(node),
+		StaticPhrasingContent::Emphasis(node)    => visitor.visit_emphasis(node),
+		StaticPhrasingContent::FontSize(node)    => visitor.visit_font_size(node),
+		StaticPhrasingContent::Html(node)        => visitor.visit_html(node),
+		StaticPhrasingContent::Image(node)       => visitor.visit_image(node),
+		StaticPhrasingContent::ImageRef(node)    => visitor.visit_image_reference(node),
+		StaticPhrasingContent::InlineCode(node)  => visitor.visit_inline_code(node),
+		StaticPhrasingContent::InlineMath(node)  => visitor.visit_inline_math(node),
+		StaticPhrasingContent::Strong(node)      => visitor.visit_strong(node),
+		StaticPhrasingContent::Subscript(node)   => visitor.visit_subscript(node),
+		StaticPhrasingContent::Superscript(node) => visitor.visit_superscript(node),
+		StaticPhrasingContent::Text(node)        => visitor.visit_text(node),
+		StaticPhrasingContent::Underline(node)   => visitor.visit_underline(node),
+	}
+}
+
+pub fn visit_strong<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Strong<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_subscript<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Subscript<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_superscript<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Superscript<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_table<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Table<'a>) {
+	for child in &node.children {
+		visitor.visit_table_row(child);
+	}
+}
+
+pub fn visit_table_cell<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a TableCell<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+pub fn visit_table_row<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a TableRow<'a>) {
+	for child in &node.children {
+		visitor.visit_table_cell(child);
+	}
+}
+
+pub fn visit_text_content<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a TextContent<'a>) {
+	match node {
+		TextContent::Definition(node) => visitor.visit_definition(node),
+		TextContent::Paragraph(node)  => visitor.visit_paragraph(node),
+	}
+}
+
+pub fn visit_underline<'a, V : Visit<'a> + ?Sized>(visitor: &mut V, node: &'a Underline<'a>) {
+	for child in &node.children {
+		visitor.visit_phrasing_content(child);
+	}
+}
+
+
+#[cfg(all(test, feature = "markdown"))]
+mod tests {
+	use crate::{MarkdownFlavor, TmDoc};
+
+	use super::*;
+
+	#[derive(Default)]
+	struct Stats<'a> {
+		urls: Vec<&'a str>,
+		headings: usize,
+	}
+
+	impl<'a> Visit<'a> for Stats<'a> {
+		fn visit_heading(&mut self, node: &'a Heading<'a>) {
+			self.headings += 1;
+			visit_heading(self, node);
+		}
+
+		fn visit_link(&mut self, node: &'a Link<'a>) {
+			self.urls.push(&node.url);
+			visit_link(self, node);
+		}
+
+		fn visit_image(&mut self, node: &'a Image<'a>) {
+			self.urls.push(&node.url);
+		}
+	}
+
+	#[test]
+	fn collect_urls() {
+		let doc = TmDoc::parse_markdown(
+			"# [Home](/home)\n\n> - [Docs](/docs) ![Logo](/logo.png)\n\n| [A](/a) |\n|---|\n",
+			MarkdownFlavor::GFM
+		).expect("parse failed");
+
+		let mut stats = Stats::default();
+		stats.visit_root(&doc.0);
+
+		assert_eq!(stats.urls, ["/home", "/docs", "/logo.png", "/a"]);
+		assert_eq!(stats.headings, 1);
+	}
+}
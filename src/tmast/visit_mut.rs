@@ -0,0 +1,305 @@
+/*
+ * Copyright 2023 Strixpyrr
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! In-place traversal of a [tmast](super) tree. Each [VisitMut] method for a
+//! parent node defaults to calling the function of the same name in this module,
+//! which visits the node's children. Override a method to change a node type,
+//! calling its function to keep walking into the children.
+
+use super::*;
+
+/// Visits the nodes of a tree mutably, parents before their children.
+pub trait VisitMut<'t> {
+	fn visit_align_mut(&mut self, node: &mut Align<'t>) { visit_align_mut(self, node) }
+	fn visit_break_mut(&mut self, _node: &mut Break) { }
+	fn visit_code_mut(&mut self, _node: &mut Code<'t>) { }
+	fn visit_color_mut(&mut self, node: &mut Color<'t>) { visit_color_mut(self, node) }
+	fn visit_content_mut(&mut self, node: &mut Content<'t>) { visit_content_mut(self, node) }
+	fn visit_definition_mut(&mut self, _node: &mut Definition<'t>) { }
+	fn visit_delete_mut(&mut self, node: &mut Delete<'t>) { visit_delete_mut(self, node) }
+	fn visit_embed_mut(&mut self, _node: &mut Embed<'t>) { }
+	fn visit_emphasis_mut(&mut self, node: &mut Emphasis<'t>) { visit_emphasis_mut(self, node) }
+	fn visit_flow_content_mut(&mut self, node: &mut FlowContent<'t>) { visit_flow_content_mut(self, node) }
+	fn visit_font_size_mut(&mut self, node: &mut FontSize<'t>) { visit_font_size_mut(self, node) }
+	fn visit_footnote_def_mut(&mut self, node: &mut FootnoteDef<'t>) { visit_footnote_def_mut(self, node) }
+	fn visit_footnote_ref_mut(&mut self, _node: &mut FootnoteRef<'t>) { }
+	fn visit_heading_mut(&mut self, node: &mut Heading<'t>) { visit_heading_mut(self, node) }
+	fn visit_html_mut(&mut self, _node: &mut Html<'t>) { }
+	fn visit_image_mut(&mut self, _node: &mut Image<'t>) { }
+	fn visit_image_reference_mut(&mut self, _node: &mut ImageReference<'t>) { }
+	fn visit_inline_code_mut(&mut self, _node: &mut InlineCode<'t>) { }
+	fn visit_inline_math_mut(&mut self, _node: &mut InlineMath<'t>) { }
+	fn visit_link_mut(&mut self, node: &mut Link<'t>) { visit_link_mut(self, node) }
+	fn visit_link_reference_mut(&mut self, node: &mut LinkReference<'t>) { visit_link_reference_mut(self, node) }
+	fn visit_list_item_mut(&mut self, node: &mut ListItem<'t>) { visit_list_item_mut(self, node) }
+	fn visit_list_mut(&mut self, node: &mut List<'t>) { visit_list_mut(self, node) }
+	fn visit_math_mut(&mut self, _node: &mut Math<'t>) { }
+	fn visit_paragraph_mut(&mut self, node: &mut Paragraph<'t>) { visit_paragraph_mut(self, node) }
+	fn visit_phrasing_content_mut(&mut self, node: &mut PhrasingContent<'t>) { visit_phrasing_content_mut(self, node) }
+	fn visit_quote_mut(&mut self, node: &mut Quote<'t>) { visit_quote_mut(self, node) }
+	fn visit_root_mut(&mut self, node: &mut Root<'t>) { visit_root_mut(self, node) }
+	fn visit_spoiler_mut(&mut self, node: &mut Spoiler<'t>) { visit_spoiler_mut(self, node) }
+	fn visit_static_phrasing_content_mut(&mut self, node: &mut StaticPhrasingContent<'t>) { visit_static_phrasing_content_mut(self, node) }
+	fn visit_strong_mut(&mut self, node: &mut Strong<'t>) { visit_strong_mut(self, node) }
+	fn visit_subscript_mut(&mut self, node: &mut Subscript<'t>) { visit_subscript_mut(self, node) }
+	fn visit_superscript_mut(&mut self, node: &mut Superscript<'t>) { visit_superscript_mut(self, node) }
+	fn visit_table_cell_mut(&mut self, node: &mut TableCell<'t>) { visit_table_cell_mut(self, node) }
+	fn visit_table_mut(&mut self, node: &mut Table<'t>) { visit_table_mut(self, node) }
+	fn visit_table_row_mut(&mut self, node: &mut TableRow<'t>) { visit_table_row_mut(self, node) }
+	fn visit_text_content_mut(&mut self, node: &mut TextContent<'t>) { visit_text_content_mut(self, node) }
+	fn visit_text_mut(&mut self, _node: &mut Text<'t>) { }
+	fn visit_thematic_break_mut(&mut self, _node: &mut ThematicBreak) { }
+	fn visit_underline_mut(&mut self, node: &mut Underline<'t>) { visit_underline_mut(self, node) }
+}
+
+pub fn visit_align_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Align<'t>) {
+	for child in &mut node.children {
+		visitor.visit_flow_content_mut(child);
+	}
+}
+
+pub fn visit_color_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Color<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_content_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Content<'t>) {
+	match node {
+		Content::Flow(node)     => visitor.visit_flow_content_mut(node),
+		Content::Phrasing(node) => visitor.visit_phrasing_content_mut(node),
+	}
+}
+
+pub fn visit_delete_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Delete<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_emphasis_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Emphasis<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_flow_content_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut FlowContent<'t>) {
+	match node {
+		FlowContent::Align(node)         => visitor.visit_align_mut(node),
+		FlowContent::Code(node)          => visitor.visit_code_mut(node),
+		FlowContent::Content(node)       => visitor.visit_text_content_mut(node),
+		FlowContent::FootnoteDef(node)   => visitor.visit_footnote_def_mut(node),
+		FlowContent::Heading(node)       => visitor.visit_heading_mut(node),
+		FlowContent::Html(node)          => visitor.visit_html_mut(node),
+		FlowContent::List(node)          => visitor.visit_list_mut(node),
+		FlowContent::Math(node)          => visitor.visit_math_mut(node),
+		FlowContent::Quote(node)         => visitor.visit_quote_mut(node),
+		FlowContent::Spoiler(node)       => visitor.visit_spoiler_mut(node),
+		FlowContent::Table(node)         => visitor.visit_table_mut(node),
+		FlowContent::ThematicBreak(node) => visitor.visit_thematic_break_mut(node),
+	}
+}
+
+pub fn visit_font_size_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut FontSize<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_footnote_def_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut FootnoteDef<'t>) {
+	for child in &mut node.children {
+		visitor.visit_flow_content_mut(child);
+	}
+}
+
+pub fn visit_heading_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Heading<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_link_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Link<'t>) {
+	for child in &mut node.children {
+		visitor.visit_static_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_link_reference_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut LinkReference<'t>) {
+	for child in &mut node.children {
+		visitor.visit_static_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_list_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut List<'t>) {
+	for child in &mut node.children {
+		visitor.visit_list_item_mut(child);
+	}
+}
+
+pub fn visit_list_item_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut ListItem<'t>) {
+	for child in &mut node.children {
+		visitor.visit_flow_content_mut(child);
+	}
+}
+
+pub fn visit_paragraph_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Paragraph<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_phrasing_content_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut PhrasingContent<'t>) {
+	match node {
+		PhrasingContent::FootnoteRef(node) => visitor.visit_footnote_ref_mut(node),
+		PhrasingContent::Link(node)        => visitor.visit_link_mut(node),
+		PhrasingContent::LinkRef(node)     => visitor.visit_link_reference_mut(node),
+		PhrasingContent::Static(node)      => visitor.visit_static_phrasing_content_mut(node),
+	}
+}
+
+pub fn visit_quote_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Quote<'t>) {
+	for child in &mut node.children {
+		visitor.visit_flow_content_mut(child);
+	}
+}
+
+pub fn visit_root_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Root<'t>) {
+	for child in &mut node.children {
+		visitor.visit_content_mut(child);
+	}
+}
+
+pub fn visit_spoiler_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Spoiler<'t>) {
+	for child in &mut node.children {
+		visitor.visit_flow_content_mut(child);
+	}
+}
+
+pub fn visit_static_phrasing_content_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut StaticPhrasingContent<'t>) {
+	match node {
+		StaticPhrasingContent::Break(node)       => visitor.visit_break_mut(node),
+		StaticPhrasingContent::Color(node)       => visitor.visit_color_mut(node),
+		StaticPhrasingContent::Delete(node)      => visitor.visit_delete_mut(node),
+		StaticPhrasingContent::Embed(node)       => visitor.visit_embed_mut(node),
+		StaticPhrasingContent::Emphasis(node)    => visitor.visit_emphasis_mut(node),
+		StaticPhrasingContent::FontSize(node)    => visitor.visit_font_size_mut(node),
+		StaticPhrasingContent::Html(node)        => visitor.visit_html_mut(node),
+		StaticPhrasingContent::Image(node)       => visitor.visit_image_mut(node),
+		StaticPhrasingContent::ImageRef(node)    => visitor.visit_image_reference_mut(node),
+		StaticPhrasingContent::InlineCode(node)  => visitor.visit_inline_code_mut(node),
+		StaticPhrasingContent::InlineMath(node)  => visitor.visit_inline_math_mut(node),
+		StaticPhrasingContent::Strong(node)      => visitor.visit_strong_mut(node),
+		StaticPhrasingContent::Subscript(node)   => visitor.visit_subscript_mut(node),
+		StaticPhrasingContent::Superscript(node) => visitor.visit_superscript_mut(node),
+		StaticPhrasingContent::Text(node)        => visitor.visit_text_mut(node),
+		StaticPhrasingContent::Underline(node)   => visitor.visit_underline_mut(node),
+	}
+}
+
+pub fn visit_strong_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Strong<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_subscript_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Subscript<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_superscript_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Superscript<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_table_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Table<'t>) {
+	for child in &mut node.children {
+		visitor.visit_table_row_mut(child);
+	}
+}
+
+pub fn visit_table_cell_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut TableCell<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+pub fn visit_table_row_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut TableRow<'t>) {
+	for child in &mut node.children {
+		visitor.visit_table_cell_mut(child);
+	}
+}
+
+pub fn visit_text_content_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut TextContent<'t>) {
+	match node {
+		TextContent::Definition(node) => visitor.visit_definition_mut(node),
+		TextContent::Paragraph(node)  => visitor.visit_paragraph_mut(node),
+	}
+}
+
+pub fn visit_underline_mut<'t, V : VisitMut<'t> + ?Sized>(visitor: &mut V, node: &mut Underline<'t>) {
+	for child in &mut node.children {
+		visitor.visit_phrasing_content_mut(child);
+	}
+}
+
+
+#[cfg(all(test, feature = "html", feature = "markdown"))]
+mod tests {
+	use crate::{IntoHtmlText, MarkdownFlavor, TmDoc};
+
+	use super::*;
+
+	/// Makes relative URLs absolute.
+	struct Rebase;
+
+	impl Rebase {
+		fn rebase(url: &mut Cow<str>) {
+			if url.starts_with('/') {
+				*url = format!("https://example.com{url}").into();
+			}
+		}
+	}
+
+	impl VisitMut<'_> for Rebase {
+		fn visit_definition_mut(&mut self, node: &mut Definition) {
+			Self::rebase(&mut node.url);
+		}
+
+		fn visit_link_mut(&mut self, node: &mut Link) {
+			Self::rebase(&mut node.url);
+			visit_link_mut(self, node);
+		}
+	}
+
+	#[test]
+	fn rewrite_links() {
+		let mut doc = TmDoc::parse_markdown(
+			"*[Docs](/docs)* and [ref] and [away](https://example.org)\n\n[ref]: /ref\n",
+			MarkdownFlavor::GFM
+		).expect("parse failed");
+
+		Rebase.visit_root_mut(&mut doc.0);
+
+		assert_eq!(
+			doc.into_html_text(),
+			"<p><em><a href=\"https://example.com/docs\">Docs</a></em> and \
+			<a href=\"https://example.com/ref\">ref</a> and <a href=\"https://example.org\">away</a></p>\n"
+		);
+	}
+}
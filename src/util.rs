@@ -14,33 +14,102 @@
  * limitations under the License.
  */
 
-pub trait Consume<T> {
-	fn consume(self, consume: impl FnOnce(T) -> ());
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+use std::borrow::Cow;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+use std::io;
+
+#[cfg(any(feature = "bbcode", feature = "html", feature = "text"))]
+use markdown::{decode_named, decode_numeric};
+#[cfg(any(feature = "bbcode", feature = "html", feature = "text"))]
+use regex::Captures;
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+use regex_macro::regex;
+
+/// Decodes HTML character references, such as `&amp;` and `&#x27;`, in a string.
+/// Unknown references are left as-is.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "text"))]
+pub fn decode_entities(text: &str) -> Cow<'_, str> {
+	let reference = regex!(r"&(?:#([0-9]{1,7})|#[xX]([0-9a-fA-F]{1,6})|([A-Za-z][A-Za-z0-9]{0,31}));");
+
+	reference.replace_all(text, |caps: &Captures| {
+		if let Some(dec) = caps.get(1) {
+			decode_numeric(dec.as_str(), 10)
+		} else if let Some(hex) = caps.get(2) {
+			decode_numeric(hex.as_str(), 16)
+		} else {
+			decode_named(&caps[3], true).unwrap_or_else(|| caps[0].to_string())
+		}
+	})
 }
 
-impl<T> Consume<T> for Option<T> {
-	fn consume(self, consume: impl FnOnce(T) -> ()) {
-		self.map(consume);
+/// Escapes characters with special meaning in HTML text and attribute values.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+pub fn escape_html(text: &str) -> Cow<'_, str> {
+	if !text.contains(['&', '<', '>', '"']) {
+		return Cow::Borrowed(text)
+	}
+
+	let mut escaped = String::with_capacity(text.len() + 8);
+
+	for char in text.chars() {
+		match char {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			_   => escaped.push(char),
+		}
 	}
-}
 
-pub trait Zip<T, E> {
-	fn zip(self, other: Result<T, E>) -> Result<(T, T), E>;
+	Cow::Owned(escaped)
 }
 
-impl<T, E> Zip<T, E> for Result<T, E> {
-	fn zip(self, other: Result<T, E>) -> Result<(T, T), E> {
-		self.and_then(|x| Ok((x, other?)))
+/// Applies a string transform to a [Cow], keeping it borrowed if it was borrowed,
+/// and avoiding a copy if the transform leaves an owned string unchanged.
+#[cfg(any(feature = "bbcode", feature = "html"))]
+pub fn map_cow<'t>(value: Cow<'t, str>, f: impl for<'a> FnOnce(&'a str) -> Cow<'a, str>) -> Cow<'t, str> {
+	match value {
+		Cow::Borrowed(value) => f(value),
+		Cow::Owned(value) => {
+			let mapped = match f(&value) {
+				Cow::Borrowed(mapped) if mapped.len() == value.len() => None,
+				mapped => Some(mapped.into_owned()),
+			};
+
+			Cow::Owned(mapped.unwrap_or(value))
+		}
 	}
 }
 
-pub trait LastIndex {
-	/// Returns the last index of an indexed value.
-	fn last_index(&self) -> Option<usize>;
+/// Returns the id of a YouTube video from its watch, short or embed URL, or
+/// `None` if the URL isn't a YouTube video.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown"))]
+pub fn youtube_id(url: &str) -> Option<&str> {
+	let video = regex!(
+		r"^(?:https?://)?(?:www\.|m\.)?(?:youtube\.com/(?:watch\?(?:[^#]*&)?v=|embed/|shorts/)|youtu\.be/)([\w-]+)"
+	);
+
+	Some(video.captures(url)?.get(1)?.as_str())
 }
 
-impl LastIndex for str {
-	fn last_index(&self) -> Option<usize> {
-		self.len().checked_sub(1)
-	}
+/// Receives output from a writer in chunks, as each top-level block is finished.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+pub(crate) type Emit<'e> = dyn FnMut(&str) -> io::Result<()> + 'e;
+
+/// Collects the chunks a writer emits into a string.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+pub(crate) fn collect(write: impl FnOnce(&mut Emit) -> io::Result<()>) -> String {
+	let mut out = String::new();
+	write(&mut |chunk| {
+		out.push_str(chunk);
+		Ok(())
+	}).expect("writing to a string can't fail");
+	out
+}
+
+/// Writes the chunks a writer emits straight to `out`.
+#[cfg(any(feature = "bbcode", feature = "html", feature = "markdown", feature = "text"))]
+pub(crate) fn stream(out: &mut (impl io::Write + ?Sized), write: impl FnOnce(&mut Emit) -> io::Result<()>) -> io::Result<()> {
+	write(&mut |chunk| out.write_all(chunk.as_bytes()))
 }